@@ -0,0 +1,22 @@
+#[define(ALPHA_CUTOFF, 0.5)]
+
+layout(location = 0) in vec2 f_texCoord;
+layout(location = 1) in vec4 f_rectangle;
+
+layout(location = 0) out vec4 out_color;
+
+uniform sampler2D u_texture;
+
+void main()
+{
+    vec2 texCoord = vec2(0.0, 1.0) + (f_rectangle.xy + f_texCoord * f_rectangle.zw) * vec2(1.0, -1.0);
+    out_color = texture(u_texture, texCoord);
+#[if TINT]
+    out_color *= TINT;
+#[endif]
+#[if ALPHA_TESTED]
+    if (out_color.a < ALPHA_CUTOFF) {
+        discard;
+    }
+#[endif]
+}
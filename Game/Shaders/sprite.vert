@@ -0,0 +1,17 @@
+#[feature(camera)]
+layout(location = 0) in mat4 i_matrix;
+layout(location = 4) in vec4 i_rectangle;
+layout(location = 5) in vec2 v_position;
+layout(location = 6) in vec2 v_texCoord;
+
+layout(location = 0) out vec2 f_texCoord;
+layout(location = 1) out vec4 f_rectangle;
+
+out gl_PerVertex { vec4 gl_Position; };
+
+void main()
+{
+    f_texCoord = v_texCoord;
+    f_rectangle = i_rectangle;
+    gl_Position = applyProjection(applyView(i_matrix * vec4(v_position, 0.0, 1.0)));
+}
@@ -0,0 +1,73 @@
+#[allow(dead_code)]
+#[path = "../types/asset_archive.rs"]
+mod asset_archive;
+
+use asset_archive::*;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+fn collect_files(root: &Path, directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn archive_name(root: &Path, path: &Path) -> String {
+    // Names always use forward slashes so archives are the same on every platform
+    path.strip_prefix(root)
+        .unwrap()
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn pack(root: &Path, output: &Path) -> io::Result<()> {
+    let mut files = Vec::new();
+    collect_files(root, root, &mut files)?;
+    files.sort();
+
+    let mut writer = AssetArchiveWriter::new();
+    for path in files.iter() {
+        let name = archive_name(root, path);
+        writer.add(&name, &fs::read(path)?);
+        println!("Packed {}", name);
+    }
+    writer.write(output)?;
+
+    // Read the archive back so a bad pack is caught here rather than in the game
+    let archive = AssetArchive::open(output)?;
+    let (size, compressed_size) =
+        archive
+            .entries()
+            .fold((0, 0), |(size, compressed), (_, entry)| {
+                (size + entry.size(), compressed + entry.compressed_size())
+            });
+    println!(
+        "Wrote {} assets to {} ({} bytes compressed to {})",
+        writer.len(),
+        output.display(),
+        size,
+        compressed_size
+    );
+    Ok(())
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<String>>();
+    if args.len() != 3 {
+        eprintln!("Usage: {} <asset directory> <archive>", args[0]);
+        std::process::exit(2);
+    }
+    if let Err(error) = pack(Path::new(&args[1]), Path::new(&args[2])) {
+        eprintln!("Could not pack assets: {}", error);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::*;
+use std::fmt;
+use std::path::PathBuf;
+
+pub type BloomResult<T> = Result<T, BloomError>;
+
+#[derive(Debug)]
+pub enum BloomError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Image {
+        path: Option<PathBuf>,
+        error: image::ImageError,
+    },
+    InvalidTextureSize(Vec2u),
+    UnknownShaderFeature(String),
+    UnknownShaderDirective(String),
+    InvalidShaderDirective(String),
+    UnknownShaderInclude(String),
+    ShaderIncludeCycle(Vec<String>),
+    ShaderSource {
+        file: String,
+        line: usize,
+        error: Box<BloomError>,
+    },
+    ShaderCompile {
+        stage: ShaderStage,
+        log: String,
+    },
+    PipelineValidation(String),
+    UnknownUniform(String),
+    UniformType {
+        name: String,
+        expected: GLenum,
+        given: GLenum,
+    },
+    UniformArrayLength {
+        name: String,
+        size: GLint,
+        given: usize,
+    },
+    WindowCreation,
+    BufferCreation {
+        size: GLsizeiptr,
+        gl_error: GLenum,
+    },
+}
+
+impl fmt::Display for BloomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BloomError::Io { path, error } => {
+                write!(f, "Could not read {}: {}", path.display(), error)
+            }
+            BloomError::Image {
+                path: Some(path),
+                error,
+            } => write!(f, "Could not decode image {}: {}", path.display(), error),
+            BloomError::Image { path: None, error } => {
+                write!(f, "Could not decode image: {}", error)
+            }
+            BloomError::InvalidTextureSize(size) => write!(
+                f,
+                "Texture size {}x{} is not a power of 2 in both dimensions",
+                size[0], size[1]
+            ),
+            BloomError::UnknownShaderFeature(name) => {
+                write!(f, "Unknown shader feature {:?}", name)
+            }
+            BloomError::UnknownShaderDirective(name) => {
+                write!(f, "Unknown shader compiler directive {:?}", name)
+            }
+            BloomError::InvalidShaderDirective(message) => {
+                write!(f, "Invalid shader compiler directive: {}", message)
+            }
+            BloomError::UnknownShaderInclude(name) => {
+                write!(f, "No shader snippet is registered with name {:?}", name)
+            }
+            BloomError::ShaderIncludeCycle(chain) => {
+                write!(
+                    f,
+                    "Shader snippets include each other: {}",
+                    chain.join(" -> ")
+                )
+            }
+            BloomError::ShaderSource { file, line, error } => {
+                write!(f, "{}:{}: {}", file, line, error)
+            }
+            BloomError::ShaderCompile { stage, log } => {
+                write!(f, "Could not compile {:?} shader:\n{}", stage, log)
+            }
+            BloomError::PipelineValidation(log) => write!(f, "Pipeline is not valid:\n{}", log),
+            BloomError::UnknownUniform(name) => {
+                write!(f, "No active uniform is named {:?}", name)
+            }
+            BloomError::UniformType {
+                name,
+                expected,
+                given,
+            } => write!(
+                f,
+                "Uniform {:?} is a {} but was given a {}",
+                name,
+                gl_type_name(*expected),
+                gl_type_name(*given)
+            ),
+            BloomError::UniformArrayLength { name, size, given } => write!(
+                f,
+                "Uniform {:?} holds {} elements but was given {}",
+                name, size, given
+            ),
+            BloomError::WindowCreation => write!(f, "Could not create window"),
+            BloomError::BufferCreation { size, gl_error } => write!(
+                f,
+                "Could not create a buffer of {} bytes (GL error 0x{:04X})",
+                size, gl_error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BloomError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BloomError::Io { error, .. } => Some(error),
+            BloomError::Image { error, .. } => Some(error),
+            BloomError::ShaderSource { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+pub fn take_gl_error() -> Option<GLenum> {
+    // Drain every queued error so the next check starts clean, keeping the first
+    let mut first_error = None;
+    loop {
+        let error = unsafe { gl::GetError() };
+        if error == gl::NO_ERROR {
+            return first_error;
+        }
+        first_error.get_or_insert(error);
+    }
+}
@@ -0,0 +1,287 @@
+use crate::*;
+use fennec_algebra::*;
+use std::rc::Rc;
+
+pub const MAX_BOSS_PHASES: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpellCard {
+    name: String,
+    bonus: u64,
+    survival: bool,
+}
+
+impl SpellCard {
+    pub fn new(name: &str, bonus: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            bonus,
+            survival: false,
+        }
+    }
+
+    pub fn survival(mut self) -> Self {
+        self.survival = true;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn bonus(&self) -> u64 {
+        self.bonus
+    }
+
+    pub fn is_survival(&self) -> bool {
+        self.survival
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BossPhase {
+    health: f32,
+    timeout: f64,
+    attack: Rc<BulletPattern>,
+    attack_interval: f64,
+    spell_card: Option<SpellCard>,
+    path: Option<Rc<EnemyPath>>,
+}
+
+impl BossPhase {
+    pub fn new(health: f32, timeout: f64, attack: Rc<BulletPattern>, attack_interval: f64) -> Self {
+        if DEBUG && health <= 0.0 {
+            panic!("Boss phase health must be greater than 0");
+        }
+        if DEBUG && timeout <= 0.0 {
+            panic!("Boss phase timeout must be greater than 0");
+        }
+        if DEBUG && attack_interval <= 0.0 {
+            panic!("Boss attack interval must be greater than 0");
+        }
+        Self {
+            health,
+            timeout,
+            attack,
+            attack_interval,
+            spell_card: None,
+            path: None,
+        }
+    }
+
+    pub fn with_spell_card(mut self, spell_card: SpellCard) -> Self {
+        self.spell_card = Some(spell_card);
+        self
+    }
+
+    pub fn with_path(mut self, path: Rc<EnemyPath>) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+
+    pub fn timeout(&self) -> f64 {
+        self.timeout
+    }
+
+    pub fn attack(&self) -> &Rc<BulletPattern> {
+        &self.attack
+    }
+
+    pub fn attack_interval(&self) -> f64 {
+        self.attack_interval
+    }
+
+    pub fn spell_card(&self) -> Option<&SpellCard> {
+        self.spell_card.as_ref()
+    }
+
+    pub fn path(&self) -> Option<&Rc<EnemyPath>> {
+        self.path.as_ref()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BossStatus {
+    health_fraction: f32,
+    time_remaining: f64,
+    time_fraction: f32,
+    phases_remaining: usize,
+    spell_card_active: bool,
+}
+
+impl BossStatus {
+    pub fn health_fraction(&self) -> f32 {
+        self.health_fraction
+    }
+
+    pub fn time_remaining(&self) -> f64 {
+        self.time_remaining
+    }
+
+    pub fn time_fraction(&self) -> f32 {
+        self.time_fraction
+    }
+
+    pub fn phases_remaining(&self) -> usize {
+        self.phases_remaining
+    }
+
+    pub fn spell_card_active(&self) -> bool {
+        self.spell_card_active
+    }
+}
+
+pub struct Boss {
+    enemy: Enemy,
+    phases: Rc<Vec<BossPhase>>,
+    phase_idx: Option<usize>,
+    phase_start_time: f64,
+    next_attack_time: f64,
+    capture_failed: bool,
+    defeated: bool,
+}
+
+impl Boss {
+    pub fn new(enemy: Enemy, phases: Rc<Vec<BossPhase>>) -> Self {
+        if DEBUG && phases.is_empty() {
+            panic!("Boss must have at least one phase");
+        }
+        if DEBUG && phases.len() > MAX_BOSS_PHASES {
+            panic!("Boss can not have more than {} phases", MAX_BOSS_PHASES);
+        }
+        Self {
+            enemy,
+            phases,
+            phase_idx: None,
+            phase_start_time: 0.0,
+            next_attack_time: 0.0,
+            capture_failed: false,
+            defeated: false,
+        }
+    }
+
+    pub fn enemy(&self) -> &Enemy {
+        &self.enemy
+    }
+
+    pub fn phase_idx(&self) -> Option<usize> {
+        self.phase_idx
+    }
+
+    pub fn phase(&self) -> Option<&BossPhase> {
+        self.phase_idx.map(|idx| &self.phases[idx])
+    }
+
+    pub fn is_vulnerable(&self) -> bool {
+        self.phase_idx.is_some() && !self.defeated
+    }
+
+    pub fn is_defeated(&self) -> bool {
+        self.defeated
+    }
+
+    pub fn fail_capture(&mut self) {
+        self.capture_failed = true;
+    }
+
+    pub fn status(&self, current_time: f64) -> Option<BossStatus> {
+        let phase_idx = self.phase_idx?;
+        let phase = &self.phases[phase_idx];
+        let time_remaining = (phase.timeout() - (current_time - self.phase_start_time)).max(0.0);
+        Some(BossStatus {
+            health_fraction: (self.enemy.health() / phase.health()).max(0.0),
+            time_remaining,
+            time_fraction: (time_remaining / phase.timeout()) as f32,
+            phases_remaining: self.phases.len() - phase_idx - 1,
+            spell_card_active: phase.spell_card().is_some(),
+        })
+    }
+
+    pub fn update(
+        &mut self,
+        sprite_list: &mut SpriteList,
+        damage: f32,
+        current_time: f64,
+    ) -> Vec<EnemyEvent> {
+        let mut events = Vec::new();
+        if self.defeated {
+            return events;
+        }
+        self.enemy.update(sprite_list, current_time);
+
+        // The first phase starts once the boss has finished entering
+        let phase_idx = match self.phase_idx {
+            Some(phase_idx) => phase_idx,
+            None => {
+                if self.enemy.path_finished(current_time) {
+                    self.start_phase(0, current_time, &mut events);
+                }
+                return events;
+            }
+        };
+
+        // A phase ends when its health runs out or its time is up
+        self.enemy.damage(damage);
+        let phase = &self.phases[phase_idx];
+        let timed_out = current_time >= self.phase_start_time + phase.timeout();
+        if self.enemy.is_dead() || timed_out {
+            // Spell cards are captured by beating them without dying or bombing, or by surviving them
+            let capture_bonus = phase.spell_card().and_then(|spell_card| {
+                if !self.capture_failed && (!timed_out || spell_card.is_survival()) {
+                    Some(spell_card.bonus())
+                } else {
+                    None
+                }
+            });
+            events.push(EnemyEvent::BossPhaseEnded {
+                enemy: self.enemy.id(),
+                phase: phase_idx,
+                timed_out,
+                capture_bonus,
+            });
+            if phase_idx + 1 < self.phases.len() {
+                self.start_phase(phase_idx + 1, current_time, &mut events);
+            } else {
+                self.defeated = true;
+            }
+            return events;
+        }
+
+        // Attack at the phase's interval
+        if current_time >= self.next_attack_time {
+            self.next_attack_time += phase.attack_interval();
+            events.push(EnemyEvent::Attacked {
+                enemy: self.enemy.id(),
+                position: self.enemy.position(current_time),
+                pattern: phase.attack().clone(),
+            });
+        }
+
+        events
+    }
+
+    fn start_phase(&mut self, phase_idx: usize, current_time: f64, events: &mut Vec<EnemyEvent>) {
+        let phase = &self.phases[phase_idx];
+        self.phase_idx = Some(phase_idx);
+        self.phase_start_time = current_time;
+        self.next_attack_time = current_time + phase.attack_interval();
+        self.capture_failed = false;
+        self.enemy.set_health(phase.health());
+        if let Some(path) = phase.path() {
+            self.enemy.set_path(path.clone(), current_time);
+        }
+        events.push(EnemyEvent::BossPhaseStarted {
+            enemy: self.enemy.id(),
+            phase: phase_idx,
+            spell_card: phase.spell_card().cloned(),
+        });
+    }
+
+    pub fn into_enemy(self) -> Enemy {
+        self.enemy
+    }
+}
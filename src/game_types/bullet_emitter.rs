@@ -0,0 +1,370 @@
+use crate::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::PI;
+use std::rc::Rc;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EmitterShape {
+    Ring,
+    Spread { arc: f32 },
+    Spiral { turn_per_wave: f32 },
+    Aimed { arc: f32 },
+    RandomBurst { arc: f32 },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BulletStyle {
+    rectangle: Vec4f,
+    scale: Vec2f,
+    hitbox_radius: f32,
+    motion_program: Option<MotionProgramReference>,
+}
+
+impl BulletStyle {
+    pub fn new(rectangle: Vec4f, scale: Vec2f, hitbox_radius: f32) -> Self {
+        Self {
+            rectangle,
+            scale,
+            hitbox_radius,
+            motion_program: None,
+        }
+    }
+
+    pub fn with_motion_program(mut self, motion_program: MotionProgramReference) -> Self {
+        self.motion_program = Some(motion_program);
+        self
+    }
+
+    pub fn rectangle(&self) -> Vec4f {
+        self.rectangle
+    }
+
+    pub fn scale(&self) -> Vec2f {
+        self.scale
+    }
+
+    pub fn hitbox_radius(&self) -> f32 {
+        self.hitbox_radius
+    }
+
+    pub fn motion_program(&self) -> Option<MotionProgramReference> {
+        self.motion_program
+    }
+
+    pub fn actor(
+        &self,
+        position: Vec2f,
+        velocity: Vec2f,
+        angle: f32,
+        current_time: f64,
+    ) -> SpriteActorVertex {
+        let actor = SpriteActorVertex::new(self.rectangle, current_time)
+            .with_position(position)
+            .with_velocity(velocity)
+            .with_scale(self.scale)
+            .with_rotation(angle)
+            .with_hitbox_radius(self.hitbox_radius);
+        match self.motion_program {
+            Some(motion_program) => actor.with_motion_program(motion_program),
+            None => actor,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BulletPattern {
+    shape: EmitterShape,
+    style: BulletStyle,
+    count: usize,
+    angle_offset: f32,
+    min_speed: f32,
+    max_speed: f32,
+    waves: usize,
+    wave_delay: f64,
+    child: Option<Rc<BulletPattern>>,
+}
+
+impl BulletPattern {
+    pub fn new(shape: EmitterShape, style: BulletStyle) -> Self {
+        Self {
+            shape,
+            style,
+            count: 1,
+            angle_offset: 0.0,
+            min_speed: 1.0,
+            max_speed: 1.0,
+            waves: 1,
+            wave_delay: 0.0,
+            child: None,
+        }
+    }
+
+    pub fn with_count(mut self, count: usize) -> Self {
+        if DEBUG && count == 0 {
+            panic!("Bullet count must be greater than 0");
+        }
+        self.count = count;
+        self
+    }
+
+    pub fn with_angle_offset(mut self, angle_offset: f32) -> Self {
+        self.angle_offset = angle_offset;
+        self
+    }
+
+    pub fn with_speed(mut self, min_speed: f32, max_speed: f32) -> Self {
+        if DEBUG && min_speed > max_speed {
+            panic!(
+                "Minimum speed {} is greater than maximum speed {}",
+                min_speed, max_speed
+            );
+        }
+        self.min_speed = min_speed;
+        self.max_speed = max_speed;
+        self
+    }
+
+    pub fn with_waves(mut self, waves: usize, wave_delay: f64) -> Self {
+        if DEBUG && waves == 0 {
+            panic!("Wave count must be greater than 0");
+        }
+        if DEBUG && wave_delay < 0.0 {
+            panic!("Wave delay must not be negative");
+        }
+        self.waves = waves;
+        self.wave_delay = wave_delay;
+        self
+    }
+
+    pub fn with_child(mut self, child: Rc<BulletPattern>) -> Self {
+        self.child = Some(child);
+        self
+    }
+
+    pub fn shape(&self) -> EmitterShape {
+        self.shape
+    }
+
+    pub fn style(&self) -> BulletStyle {
+        self.style
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn waves(&self) -> usize {
+        self.waves
+    }
+
+    pub fn wave_delay(&self) -> f64 {
+        self.wave_delay
+    }
+
+    pub fn duration(&self) -> f64 {
+        let own_duration = self.wave_delay * (self.waves - 1) as f64;
+        own_duration + self.child.as_ref().map_or(0.0, |child| child.duration())
+    }
+
+    fn spread_angle(&self, arc: f32, idx: usize) -> f32 {
+        if self.count == 1 {
+            0.0
+        } else {
+            -arc * 0.5 + arc * idx as f32 / (self.count - 1) as f32
+        }
+    }
+
+    fn shots(
+        &self,
+        wave: usize,
+        base_angle: f32,
+        aim_angle: f32,
+        rng: &mut StdRng,
+    ) -> Vec<(f32, f32)> {
+        // Non-random shapes step from the minimum speed to the maximum speed over the waves
+        let wave_speed = if self.waves == 1 {
+            self.min_speed
+        } else {
+            self.min_speed
+                + (self.max_speed - self.min_speed) * wave as f32 / (self.waves - 1) as f32
+        };
+
+        // Find the angle and speed of each shot in the wave
+        let base_angle = base_angle + self.angle_offset;
+        (0..self.count)
+            .map(|idx| match self.shape {
+                EmitterShape::Ring => (
+                    base_angle + 2.0 * PI * idx as f32 / self.count as f32,
+                    wave_speed,
+                ),
+                EmitterShape::Spread { arc } => {
+                    (base_angle + self.spread_angle(arc, idx), wave_speed)
+                }
+                EmitterShape::Spiral { turn_per_wave } => (
+                    base_angle
+                        + turn_per_wave * wave as f32
+                        + 2.0 * PI * idx as f32 / self.count as f32,
+                    wave_speed,
+                ),
+                EmitterShape::Aimed { arc } => (
+                    aim_angle + self.angle_offset + self.spread_angle(arc, idx),
+                    wave_speed,
+                ),
+                EmitterShape::RandomBurst { arc } => (
+                    base_angle + random_in_range(rng, -arc * 0.5, arc * 0.5),
+                    random_in_range(rng, self.min_speed, self.max_speed),
+                ),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct BulletEmitter {
+    pattern: Rc<BulletPattern>,
+    origin: Vec2f,
+    base_angle: f32,
+    start_time: f64,
+    seed: u64,
+    waves_fired: usize,
+    children: Vec<BulletEmitter>,
+}
+
+impl BulletEmitter {
+    pub fn new(
+        pattern: Rc<BulletPattern>,
+        origin: Vec2f,
+        base_angle: f32,
+        seed: u64,
+        start_time: f64,
+    ) -> Self {
+        Self {
+            pattern,
+            origin,
+            base_angle,
+            start_time,
+            seed,
+            waves_fired: 0,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn pattern(&self) -> &Rc<BulletPattern> {
+        &self.pattern
+    }
+
+    pub fn origin(&self) -> Vec2f {
+        self.origin
+    }
+
+    pub fn set_origin(&mut self, origin: Vec2f) {
+        self.origin = origin;
+    }
+
+    pub fn base_angle(&self) -> f32 {
+        self.base_angle
+    }
+
+    pub fn set_base_angle(&mut self, base_angle: f32) {
+        self.base_angle = base_angle;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.waves_fired >= self.pattern.waves && self.children.is_empty()
+    }
+
+    pub fn update(
+        &mut self,
+        sprite_list: &mut SpriteList,
+        aim_target: Vec2f,
+        current_time: f64,
+    ) -> Vec<SpriteReference> {
+        let mut spawned = Vec::new();
+
+        // Fire every wave that is due, timed from the emitter's start so frame rate doesn't matter
+        while self.waves_fired < self.pattern.waves {
+            let wave_time = self.start_time + self.pattern.wave_delay * self.waves_fired as f64;
+            if wave_time > current_time {
+                break;
+            }
+            self.fire_wave(
+                self.waves_fired,
+                wave_time,
+                sprite_list,
+                aim_target,
+                current_time,
+                &mut spawned,
+            );
+            self.waves_fired += 1;
+        }
+
+        // Update nested emitters and drop the ones that are done
+        for child in self.children.iter_mut() {
+            spawned.extend(child.update(sprite_list, aim_target, current_time));
+        }
+        self.children.retain(|child| !child.is_finished());
+
+        spawned
+    }
+
+    fn fire_wave(
+        &mut self,
+        wave: usize,
+        wave_time: f64,
+        sprite_list: &mut SpriteList,
+        aim_target: Vec2f,
+        current_time: f64,
+        spawned: &mut Vec<SpriteReference>,
+    ) {
+        // Each wave gets its own random sequence so patterns are reproducible
+        let mut rng = StdRng::seed_from_u64(derive_seed(self.seed, wave as u64));
+        let aim_offset = aim_target - self.origin;
+        let aim_angle = aim_offset[1].atan2(aim_offset[0]);
+        let shots = self
+            .pattern
+            .shots(wave, self.base_angle, aim_angle, &mut rng);
+
+        for (shot_idx, (angle, speed)) in shots.into_iter().enumerate() {
+            if let Some(child) = self.pattern.child.as_ref() {
+                // Nested emitters start where and when the shot would have been fired, facing its direction
+                let child_seed =
+                    derive_seed(self.seed, (wave * self.pattern.count + shot_idx) as u64 + 1);
+                self.children.push(BulletEmitter::new(
+                    child.clone(),
+                    self.origin,
+                    angle,
+                    child_seed,
+                    wave_time,
+                ));
+            } else {
+                // Bullets fired late are moved to where they would be by now, and bullets that don't
+                // fit in the sprite list are dropped
+                let velocity = Vec2f::from_angle(angle) * speed;
+                let position = self.origin + velocity * (current_time - wave_time) as f32;
+                spawned.extend(sprite_list.try_add_sprite(self.pattern.style.actor(
+                    position,
+                    velocity,
+                    angle,
+                    current_time,
+                )));
+            }
+        }
+    }
+}
+
+fn random_in_range(rng: &mut StdRng, min: f32, max: f32) -> f32 {
+    if max > min {
+        rng.gen_range(min, max)
+    } else {
+        min
+    }
+}
+
+fn derive_seed(seed: u64, salt: u64) -> u64 {
+    // SplitMix64 step so that nearby salts give unrelated seeds
+    let mut z = seed.wrapping_add(salt.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
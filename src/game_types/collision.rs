@@ -0,0 +1,539 @@
+use crate::*;
+use fennec_algebra::*;
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CollisionLayer {
+    Player,
+    PlayerShot,
+    Enemy,
+    EnemyShot,
+    Item,
+}
+
+impl CollisionLayer {
+    pub fn bit(self) -> u32 {
+        match self {
+            CollisionLayer::Player => 1 << 0,
+            CollisionLayer::PlayerShot => 1 << 1,
+            CollisionLayer::Enemy => 1 << 2,
+            CollisionLayer::EnemyShot => 1 << 3,
+            CollisionLayer::Item => 1 << 4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CollisionMask {
+    bits: u32,
+}
+
+impl CollisionMask {
+    pub fn none() -> Self {
+        Self { bits: 0 }
+    }
+
+    pub fn with(self, layer: CollisionLayer) -> Self {
+        Self {
+            bits: self.bits | layer.bit(),
+        }
+    }
+
+    pub fn contains(self, layer: CollisionLayer) -> bool {
+        (self.bits & layer.bit()) != 0
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CollisionShape {
+    Circle {
+        radius: f32,
+    },
+    Aabb {
+        half_size: Vec2f,
+    },
+    OrientedRect {
+        half_size: Vec2f,
+        rotation: f32,
+    },
+    Capsule {
+        start: Vec2f,
+        end: Vec2f,
+        radius: f32,
+    },
+}
+
+impl CollisionShape {
+    fn at(self, position: Vec2f) -> PlacedShape {
+        // Circles and AABBs are special cases of capsules and oriented rectangles respectively
+        match self {
+            CollisionShape::Circle { radius } => PlacedShape::Capsule {
+                start: position,
+                end: position,
+                radius,
+            },
+            CollisionShape::Aabb { half_size } => PlacedShape::Rect {
+                center: position,
+                half_size,
+                rotation: 0.0,
+            },
+            CollisionShape::OrientedRect {
+                half_size,
+                rotation,
+            } => PlacedShape::Rect {
+                center: position,
+                half_size,
+                rotation,
+            },
+            CollisionShape::Capsule { start, end, radius } => PlacedShape::Capsule {
+                start: position + start,
+                end: position + end,
+                radius,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum PlacedShape {
+    Capsule {
+        start: Vec2f,
+        end: Vec2f,
+        radius: f32,
+    },
+    Rect {
+        center: Vec2f,
+        half_size: Vec2f,
+        rotation: f32,
+    },
+}
+
+impl PlacedShape {
+    fn bounds(&self) -> (Vec2f, Vec2f) {
+        match *self {
+            PlacedShape::Capsule { start, end, radius } => (
+                vector!(start[0].min(end[0]) - radius, start[1].min(end[1]) - radius),
+                vector!(start[0].max(end[0]) + radius, start[1].max(end[1]) + radius),
+            ),
+            PlacedShape::Rect { .. } => {
+                let corners = self.rect_corners();
+                corners
+                    .iter()
+                    .fold((corners[0], corners[0]), |(min, max), corner| {
+                        (
+                            vector!(min[0].min(corner[0]), min[1].min(corner[1])),
+                            vector!(max[0].max(corner[0]), max[1].max(corner[1])),
+                        )
+                    })
+            }
+        }
+    }
+
+    fn rect_corners(&self) -> [Vec2f; 4] {
+        match *self {
+            PlacedShape::Rect {
+                center,
+                half_size,
+                rotation,
+            } => [
+                center + vector!(-half_size[0], -half_size[1]).rotated(rotation),
+                center + vector!(half_size[0], -half_size[1]).rotated(rotation),
+                center + vector!(half_size[0], half_size[1]).rotated(rotation),
+                center + vector!(-half_size[0], half_size[1]).rotated(rotation),
+            ],
+            PlacedShape::Capsule { .. } => panic!("Shape is not a rectangle"),
+        }
+    }
+
+    fn overlaps(&self, other: &PlacedShape) -> bool {
+        match (*self, *other) {
+            (
+                PlacedShape::Capsule {
+                    start: start_a,
+                    end: end_a,
+                    radius: radius_a,
+                },
+                PlacedShape::Capsule {
+                    start: start_b,
+                    end: end_b,
+                    radius: radius_b,
+                },
+            ) => segment_segment_distance(start_a, end_a, start_b, end_b) <= radius_a + radius_b,
+            (
+                PlacedShape::Capsule { start, end, radius },
+                PlacedShape::Rect {
+                    center,
+                    half_size,
+                    rotation,
+                },
+            )
+            | (
+                PlacedShape::Rect {
+                    center,
+                    half_size,
+                    rotation,
+                },
+                PlacedShape::Capsule { start, end, radius },
+            ) => {
+                // Work in the rectangle's local space, where it is axis-aligned and centered on the origin
+                let start = (start - center).rotated(-rotation);
+                let end = (end - center).rotated(-rotation);
+                segment_rect_distance(start, end, half_size) <= radius
+            }
+            (PlacedShape::Rect { .. }, PlacedShape::Rect { .. }) => {
+                rects_overlap(&self.rect_corners(), &other.rect_corners())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Collider {
+    position: Vec2f,
+    shape: CollisionShape,
+    layer: CollisionLayer,
+    mask: CollisionMask,
+}
+
+impl Collider {
+    pub fn new(
+        position: Vec2f,
+        shape: CollisionShape,
+        layer: CollisionLayer,
+        mask: CollisionMask,
+    ) -> Self {
+        Self {
+            position,
+            shape,
+            layer,
+            mask,
+        }
+    }
+
+    pub fn position(&self) -> Vec2f {
+        self.position
+    }
+
+    pub fn shape(&self) -> CollisionShape {
+        self.shape
+    }
+
+    pub fn layer(&self) -> CollisionLayer {
+        self.layer
+    }
+
+    pub fn mask(&self) -> CollisionMask {
+        self.mask
+    }
+
+    pub fn can_touch(&self, other: &Collider) -> bool {
+        self.mask.contains(other.layer) || other.mask.contains(self.layer)
+    }
+
+    pub fn overlaps(&self, other: &Collider) -> bool {
+        self.shape
+            .at(self.position)
+            .overlaps(&other.shape.at(other.position))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ColliderHandle {
+    idx: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Contact {
+    first: ColliderHandle,
+    first_layer: CollisionLayer,
+    second: ColliderHandle,
+    second_layer: CollisionLayer,
+}
+
+impl Contact {
+    pub fn first(&self) -> ColliderHandle {
+        self.first
+    }
+
+    pub fn first_layer(&self) -> CollisionLayer {
+        self.first_layer
+    }
+
+    pub fn second(&self) -> ColliderHandle {
+        self.second
+    }
+
+    pub fn second_layer(&self) -> CollisionLayer {
+        self.second_layer
+    }
+
+    pub fn involves(&self, handle: ColliderHandle) -> bool {
+        self.first == handle || self.second == handle
+    }
+
+    pub fn other(&self, handle: ColliderHandle) -> Option<ColliderHandle> {
+        if self.first == handle {
+            Some(self.second)
+        } else if self.second == handle {
+            Some(self.first)
+        } else {
+            None
+        }
+    }
+
+    pub fn between(&self, layer_a: CollisionLayer, layer_b: CollisionLayer) -> bool {
+        (self.first_layer == layer_a && self.second_layer == layer_b)
+            || (self.first_layer == layer_b && self.second_layer == layer_a)
+    }
+}
+
+pub struct CollisionWorld {
+    field_size: Vec2f,
+    cell_size: f32,
+    cell_counts: Vec2u,
+    colliders: Vec<Option<Collider>>,
+    cells: Vec<Vec<ColliderHandle>>,
+}
+
+impl CollisionWorld {
+    pub fn new(playing_field: &PlayingField, cell_size: f32) -> Self {
+        if DEBUG && cell_size <= 0.0 {
+            panic!("Cell size must be greater than 0");
+        }
+
+        // Cover the whole playing field with cells
+        let field_size = playing_field.size();
+        let cell_counts = vector!(
+            ((field_size[0] / cell_size).ceil() as u32).max(1),
+            ((field_size[1] / cell_size).ceil() as u32).max(1),
+        );
+
+        Self {
+            field_size,
+            cell_size,
+            cell_counts,
+            colliders: Vec::new(),
+            cells: (0..cell_counts[0] * cell_counts[1])
+                .map(|_| Vec::new())
+                .collect(),
+        }
+    }
+
+    pub fn add_collider(&mut self, collider: Collider) -> ColliderHandle {
+        // Reuse the first free slot, if any
+        if let Some(idx) = self.colliders.iter().position(Option::is_none) {
+            self.colliders[idx] = Some(collider);
+            ColliderHandle { idx }
+        } else {
+            self.colliders.push(Some(collider));
+            ColliderHandle {
+                idx: self.colliders.len() - 1,
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        // Every handle handed out so far becomes invalid
+        self.colliders.clear();
+    }
+
+    pub fn remove_collider(&mut self, handle: ColliderHandle) {
+        if DEBUG && self.colliders[handle.idx].is_none() {
+            panic!(
+                "ColliderHandle {:?} does not point to a valid collider",
+                handle
+            );
+        }
+        self.colliders[handle.idx] = None;
+    }
+
+    pub fn collider(&self, handle: ColliderHandle) -> Option<&Collider> {
+        self.colliders.get(handle.idx).and_then(Option::as_ref)
+    }
+
+    pub fn set_position(&mut self, handle: ColliderHandle, position: Vec2f) {
+        self.collider_mut(handle).position = position;
+    }
+
+    pub fn set_shape(&mut self, handle: ColliderHandle, shape: CollisionShape) {
+        self.collider_mut(handle).shape = shape;
+    }
+
+    pub fn set_mask(&mut self, handle: ColliderHandle, mask: CollisionMask) {
+        self.collider_mut(handle).mask = mask;
+    }
+
+    fn collider_mut(&mut self, handle: ColliderHandle) -> &mut Collider {
+        self.colliders[handle.idx].as_mut().unwrap_or_else(|| {
+            panic!(
+                "ColliderHandle {:?} does not point to a valid collider",
+                handle
+            )
+        })
+    }
+
+    fn cell_coordinate(&self, position: Vec2f) -> Vec2u {
+        // The playing field is centered on the origin; positions outside of it land in the edge cells
+        let x = ((position[0] + self.field_size[0] * 0.5) / self.cell_size).floor();
+        let y = ((position[1] + self.field_size[1] * 0.5) / self.cell_size).floor();
+        vector!(
+            (x.max(0.0) as u32).min(self.cell_counts[0] - 1),
+            (y.max(0.0) as u32).min(self.cell_counts[1] - 1),
+        )
+    }
+
+    fn rebuild_cells(&mut self) {
+        // Empty all cells
+        self.cells.iter_mut().for_each(Vec::clear);
+
+        // Insert every collider into each cell its bounds cover
+        for (idx, collider) in self.colliders.iter().enumerate() {
+            if let Some(collider) = collider {
+                let (min, max) = collider.shape.at(collider.position).bounds();
+                let min_cell = self.cell_coordinate(min);
+                let max_cell = self.cell_coordinate(max);
+                for y in min_cell[1]..=max_cell[1] {
+                    for x in min_cell[0]..=max_cell[0] {
+                        self.cells[(y * self.cell_counts[0] + x) as usize]
+                            .push(ColliderHandle { idx });
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn detect(&mut self) -> Vec<Contact> {
+        self.rebuild_cells();
+
+        // Test every pair of colliders sharing a cell, reporting each pair only once
+        let mut tested = HashSet::new();
+        let mut contacts = Vec::new();
+        for cell in self.cells.iter() {
+            for (cell_idx, &first) in cell.iter().enumerate() {
+                for &second in cell.iter().skip(cell_idx + 1) {
+                    if !tested.insert((first.idx.min(second.idx), first.idx.max(second.idx))) {
+                        continue;
+                    }
+
+                    let first_collider = self.colliders[first.idx].as_ref().unwrap();
+                    let second_collider = self.colliders[second.idx].as_ref().unwrap();
+                    if first_collider.can_touch(second_collider)
+                        && first_collider.overlaps(second_collider)
+                    {
+                        contacts.push(Contact {
+                            first,
+                            first_layer: first_collider.layer,
+                            second,
+                            second_layer: second_collider.layer,
+                        });
+                    }
+                }
+            }
+        }
+        contacts
+    }
+}
+
+fn dot(a: Vec2f, b: Vec2f) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn cross(a: Vec2f, b: Vec2f) -> f32 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+fn point_segment_distance(point: Vec2f, start: Vec2f, end: Vec2f) -> f32 {
+    // Find the closest point on the segment by projecting onto it
+    let segment = end - start;
+    let length_squared = dot(segment, segment);
+    let t = if length_squared > 0.0 {
+        (dot(point - start, segment) / length_squared)
+            .max(0.0)
+            .min(1.0)
+    } else {
+        0.0
+    };
+    let offset = point - (start + segment * t);
+    dot(offset, offset).sqrt()
+}
+
+fn segments_intersect(start_a: Vec2f, end_a: Vec2f, start_b: Vec2f, end_b: Vec2f) -> bool {
+    // Each segment's endpoints must lie on opposite sides of the other segment
+    let dir_a = end_a - start_a;
+    let dir_b = end_b - start_b;
+    let side_b_start = cross(dir_a, start_b - start_a);
+    let side_b_end = cross(dir_a, end_b - start_a);
+    let side_a_start = cross(dir_b, start_a - start_b);
+    let side_a_end = cross(dir_b, end_a - start_b);
+    side_b_start * side_b_end < 0.0 && side_a_start * side_a_end < 0.0
+}
+
+fn segment_segment_distance(start_a: Vec2f, end_a: Vec2f, start_b: Vec2f, end_b: Vec2f) -> f32 {
+    if segments_intersect(start_a, end_a, start_b, end_b) {
+        return 0.0;
+    }
+
+    // Otherwise the closest points include an endpoint of one of the segments
+    point_segment_distance(start_a, start_b, end_b)
+        .min(point_segment_distance(end_a, start_b, end_b))
+        .min(point_segment_distance(start_b, start_a, end_a))
+        .min(point_segment_distance(end_b, start_a, end_a))
+}
+
+fn point_rect_distance(point: Vec2f, half_size: Vec2f) -> f32 {
+    let outside = vector!(
+        (point[0].abs() - half_size[0]).max(0.0),
+        (point[1].abs() - half_size[1]).max(0.0),
+    );
+    dot(outside, outside).sqrt()
+}
+
+fn segment_rect_distance(start: Vec2f, end: Vec2f, half_size: Vec2f) -> f32 {
+    // The segment and rectangle (axis-aligned, centered on the origin) touch if an endpoint is inside or the segment crosses an edge
+    let corners = [
+        vector!(-half_size[0], -half_size[1]),
+        vector!(half_size[0], -half_size[1]),
+        vector!(half_size[0], half_size[1]),
+        vector!(-half_size[0], half_size[1]),
+    ];
+    let crosses_edge =
+        (0..4).any(|idx| segments_intersect(start, end, corners[idx], corners[(idx + 1) % 4]));
+    if crosses_edge
+        || point_rect_distance(start, half_size) == 0.0
+        || point_rect_distance(end, half_size) == 0.0
+    {
+        return 0.0;
+    }
+
+    // Otherwise the closest points are either a segment endpoint or a rectangle corner
+    corners
+        .iter()
+        .map(|&corner| point_segment_distance(corner, start, end))
+        .fold(
+            point_rect_distance(start, half_size).min(point_rect_distance(end, half_size)),
+            f32::min,
+        )
+}
+
+fn rects_overlap(corners_a: &[Vec2f; 4], corners_b: &[Vec2f; 4]) -> bool {
+    // Separating axis test using the edge normals of both rectangles
+    let axes = [
+        corners_a[1] - corners_a[0],
+        corners_a[3] - corners_a[0],
+        corners_b[1] - corners_b[0],
+        corners_b[3] - corners_b[0],
+    ];
+    axes.iter().all(|&axis| {
+        let project = |corners: &[Vec2f; 4]| {
+            corners
+                .iter()
+                .fold((f32::MAX, f32::MIN), |(min, max), &corner| {
+                    let projected = dot(corner, axis);
+                    (min.min(projected), max.max(projected))
+                })
+        };
+        let (min_a, max_a) = project(corners_a);
+        let (min_b, max_b) = project(corners_b);
+        min_a <= max_b && min_b <= max_a
+    })
+}
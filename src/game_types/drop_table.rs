@@ -0,0 +1,68 @@
+use crate::*;
+use rand::rngs::StdRng;
+use rand::Rng;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ItemKind {
+    Power,
+    Point,
+    Life,
+    Bomb,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ItemDrop {
+    kind: ItemKind,
+    count: usize,
+    chance: f32,
+}
+
+impl ItemDrop {
+    pub fn kind(&self) -> ItemKind {
+        self.kind
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn chance(&self) -> f32 {
+        self.chance
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct DropTable {
+    drops: Vec<ItemDrop>,
+}
+
+impl DropTable {
+    pub fn new() -> Self {
+        Self { drops: Vec::new() }
+    }
+
+    pub fn with_drop(mut self, kind: ItemKind, count: usize, chance: f32) -> Self {
+        if DEBUG && !(0.0..=1.0).contains(&chance) {
+            panic!("Drop chance {} must be between 0 and 1", chance);
+        }
+        self.drops.push(ItemDrop {
+            kind,
+            count,
+            chance,
+        });
+        self
+    }
+
+    pub fn drops(&self) -> &[ItemDrop] {
+        &self.drops
+    }
+
+    pub fn roll(&self, rng: &mut StdRng) -> Vec<ItemKind> {
+        // Every entry rolls on its own, dropping all of its items or none
+        self.drops
+            .iter()
+            .filter(|drop| drop.chance >= 1.0 || rng.gen::<f32>() < drop.chance)
+            .flat_map(|drop| (0..drop.count).map(move |_| drop.kind))
+            .collect()
+    }
+}
@@ -0,0 +1,201 @@
+use crate::*;
+use fennec_algebra::*;
+use std::rc::Rc;
+
+#[derive(Clone, Debug)]
+pub struct EnemyTemplate {
+    rectangle: Vec4f,
+    scale: Vec2f,
+    health: f32,
+    hitbox_radius: f32,
+    score_value: u64,
+    drop_table: DropTable,
+    attack: Option<(Rc<BulletPattern>, f64)>,
+}
+
+impl EnemyTemplate {
+    pub fn new(rectangle: Vec4f, scale: Vec2f, health: f32, hitbox_radius: f32) -> Self {
+        if DEBUG && health <= 0.0 {
+            panic!("Enemy health must be greater than 0");
+        }
+        Self {
+            rectangle,
+            scale,
+            health,
+            hitbox_radius,
+            score_value: 0,
+            drop_table: DropTable::new(),
+            attack: None,
+        }
+    }
+
+    pub fn with_score_value(mut self, score_value: u64) -> Self {
+        self.score_value = score_value;
+        self
+    }
+
+    pub fn with_drop_table(mut self, drop_table: DropTable) -> Self {
+        self.drop_table = drop_table;
+        self
+    }
+
+    pub fn with_attack(mut self, pattern: Rc<BulletPattern>, delay: f64) -> Self {
+        if DEBUG && delay < 0.0 {
+            panic!("Attack delay must not be negative");
+        }
+        self.attack = Some((pattern, delay));
+        self
+    }
+
+    pub fn rectangle(&self) -> Vec4f {
+        self.rectangle
+    }
+
+    pub fn scale(&self) -> Vec2f {
+        self.scale
+    }
+
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+
+    pub fn hitbox_radius(&self) -> f32 {
+        self.hitbox_radius
+    }
+
+    pub fn score_value(&self) -> u64 {
+        self.score_value
+    }
+
+    pub fn drop_table(&self) -> &DropTable {
+        &self.drop_table
+    }
+
+    pub fn attack(&self) -> Option<&(Rc<BulletPattern>, f64)> {
+        self.attack.as_ref()
+    }
+}
+
+pub struct Enemy {
+    id: EnemyId,
+    sprite_object: SpriteObject,
+    template: Rc<EnemyTemplate>,
+    path: Rc<EnemyPath>,
+    path_start_time: f64,
+    health: f32,
+    attacked: bool,
+    seed: u64,
+}
+
+impl Enemy {
+    pub fn new(
+        id: EnemyId,
+        sprite_list: &mut SpriteList,
+        template: Rc<EnemyTemplate>,
+        path: Rc<EnemyPath>,
+        seed: u64,
+        current_time: f64,
+    ) -> Self {
+        let rectangle = sprite_list.rectangle_to_texcoord(template.rectangle());
+        let sprite_object = sprite_list.new_sprite_object(
+            SpriteActorVertex::new(rectangle, current_time)
+                .with_position(path.start())
+                .with_scale(template.scale())
+                .with_hitbox_radius(template.hitbox_radius()),
+        );
+        Self {
+            id,
+            sprite_object,
+            health: template.health(),
+            template,
+            path,
+            path_start_time: current_time,
+            attacked: false,
+            seed,
+        }
+    }
+
+    pub fn id(&self) -> EnemyId {
+        self.id
+    }
+
+    pub fn template(&self) -> &Rc<EnemyTemplate> {
+        &self.template
+    }
+
+    pub fn path(&self) -> &Rc<EnemyPath> {
+        &self.path
+    }
+
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health <= 0.0
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn position(&self, current_time: f64) -> Vec2f {
+        self.path.position(current_time - self.path_start_time)
+    }
+
+    pub fn path_finished(&self, current_time: f64) -> bool {
+        self.path.is_finished(current_time - self.path_start_time)
+    }
+
+    pub fn set_path(&mut self, path: Rc<EnemyPath>, current_time: f64) {
+        self.path = path;
+        self.path_start_time = current_time;
+    }
+
+    pub fn set_health(&mut self, health: f32) {
+        self.health = health;
+    }
+
+    pub fn damage(&mut self, damage: f32) {
+        self.health -= damage;
+    }
+
+    pub fn collider(&self, current_time: f64) -> Collider {
+        Collider::new(
+            self.position(current_time),
+            CollisionShape::Circle {
+                radius: self.template.hitbox_radius(),
+            },
+            CollisionLayer::Enemy,
+            CollisionMask::none()
+                .with(CollisionLayer::Player)
+                .with(CollisionLayer::PlayerShot),
+        )
+    }
+
+    pub fn update(
+        &mut self,
+        sprite_list: &mut SpriteList,
+        current_time: f64,
+    ) -> Option<Rc<BulletPattern>> {
+        // Follow the path
+        let position = self.position(current_time);
+        self.sprite_object
+            .set_position(sprite_list, position, current_time);
+
+        // Attack once the delay has passed
+        match self.template.attack() {
+            Some((pattern, delay))
+                if !self.attacked && current_time >= self.path_start_time + delay =>
+            {
+                self.attacked = true;
+                Some(pattern.clone())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn remove(self, sprite_list: &mut SpriteList) {
+        self.sprite_object.remove(sprite_list);
+    }
+}
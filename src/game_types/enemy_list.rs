@@ -0,0 +1,291 @@
+use crate::*;
+use fennec_algebra::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const ENEMY_ESCAPE_MARGIN: f32 = 0.3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct EnemyId(u64);
+
+#[derive(Clone, Debug)]
+pub enum EnemyEvent {
+    Attacked {
+        enemy: EnemyId,
+        position: Vec2f,
+        pattern: Rc<BulletPattern>,
+    },
+    Died {
+        enemy: EnemyId,
+        position: Vec2f,
+        score_value: u64,
+        drops: Vec<ItemKind>,
+    },
+    Escaped {
+        enemy: EnemyId,
+    },
+    BossPhaseStarted {
+        enemy: EnemyId,
+        phase: usize,
+        spell_card: Option<SpellCard>,
+    },
+    BossPhaseEnded {
+        enemy: EnemyId,
+        phase: usize,
+        timed_out: bool,
+        capture_bonus: Option<u64>,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct EnemySpawn {
+    time: f64,
+    template: Rc<EnemyTemplate>,
+    path: Rc<EnemyPath>,
+}
+
+impl EnemySpawn {
+    pub fn new(time: f64, template: Rc<EnemyTemplate>, path: Rc<EnemyPath>) -> Self {
+        Self {
+            time,
+            template,
+            path,
+        }
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+}
+
+pub struct EnemyList {
+    max_enemies: usize,
+    enemies: Vec<Enemy>,
+    boss: Option<Boss>,
+    sprite_list: SpriteList,
+    script: Vec<EnemySpawn>,
+    next_id: u64,
+}
+
+impl EnemyList {
+    pub fn new(
+        texture: Rc<Texture<{ TextureType::Texture2D }>>,
+        sprite_pipeline: Rc<Pipeline>,
+        max_enemies: usize,
+    ) -> Self {
+        if DEBUG && max_enemies == 0 {
+            panic!("Max enemies must be greater than 0");
+        }
+
+        Self {
+            max_enemies,
+            enemies: Vec::new(),
+            boss: None,
+            sprite_list: SpriteList::new(
+                SpriteMaterial::new(sprite_pipeline),
+                SpriteAnimator::new(),
+                texture,
+                // One extra sprite for the boss
+                max_enemies as GLsizeiptr + 1,
+            ),
+            script: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn schedule_spawn(&mut self, spawn: EnemySpawn) {
+        // Keep the script sorted by time, with spawns at the same time in the order they were added
+        let idx = self
+            .script
+            .iter()
+            .position(|scheduled| scheduled.time > spawn.time)
+            .unwrap_or_else(|| self.script.len());
+        self.script.insert(idx, spawn);
+    }
+
+    pub fn clear_script(&mut self) {
+        self.script.clear();
+    }
+
+    pub fn spawn(
+        &mut self,
+        template: Rc<EnemyTemplate>,
+        path: Rc<EnemyPath>,
+        current_time: f64,
+    ) -> Option<EnemyId> {
+        // The sprite list only has room for max_enemies, so a full list spawns nothing
+        if self.enemies.len() >= self.max_enemies {
+            return None;
+        }
+
+        let enemy = self.new_enemy(template, path, current_time);
+        let id = enemy.id();
+        self.enemies.push(enemy);
+        Some(id)
+    }
+
+    pub fn spawn_boss(
+        &mut self,
+        template: Rc<EnemyTemplate>,
+        path: Rc<EnemyPath>,
+        phases: Rc<Vec<BossPhase>>,
+        current_time: f64,
+    ) -> Option<EnemyId> {
+        // Only one boss can exist at once, so a second one isn't spawned
+        if self.boss.is_some() {
+            return None;
+        }
+
+        let enemy = self.new_enemy(template, path, current_time);
+        let id = enemy.id();
+        self.boss = Some(Boss::new(enemy, phases));
+        Some(id)
+    }
+
+    fn new_enemy(
+        &mut self,
+        template: Rc<EnemyTemplate>,
+        path: Rc<EnemyPath>,
+        current_time: f64,
+    ) -> Enemy {
+        // Enemies are seeded in spawn order so their drops are the same every time
+        let id = EnemyId(self.next_id);
+        self.next_id += 1;
+        Enemy::new(
+            id,
+            &mut self.sprite_list,
+            template,
+            path,
+            id.0,
+            current_time,
+        )
+    }
+
+    pub fn enemy(&self, id: EnemyId) -> Option<&Enemy> {
+        self.enemies
+            .iter()
+            .chain(self.boss.iter().map(|boss| boss.enemy()))
+            .find(|enemy| enemy.id() == id)
+    }
+
+    pub fn boss(&self) -> Option<&Boss> {
+        self.boss.as_ref()
+    }
+
+    pub fn fail_boss_capture(&mut self) {
+        if let Some(boss) = self.boss.as_mut() {
+            boss.fail_capture();
+        }
+    }
+
+    pub fn enemy_count(&self) -> usize {
+        self.enemies.len()
+    }
+
+    pub fn colliders(&self, current_time: f64) -> Vec<(EnemyId, Collider)> {
+        self.enemies
+            .iter()
+            .chain(self.boss.iter().map(|boss| boss.enemy()))
+            .map(|enemy| (enemy.id(), enemy.collider(current_time)))
+            .collect()
+    }
+
+    pub fn is_vulnerable(&self, id: EnemyId) -> bool {
+        // Only a boss between phases can't be damaged
+        self.boss
+            .as_ref()
+            .filter(|boss| boss.enemy().id() == id)
+            .map_or(true, Boss::is_vulnerable)
+    }
+
+    pub fn update(
+        &mut self,
+        playing_field: &PlayingField,
+        damage: &HashMap<EnemyId, f32>,
+        current_time: f64,
+    ) -> Vec<EnemyEvent> {
+        let mut events = Vec::new();
+
+        // Spawn everything in the script that is due, leaving spawns that don't fit in the script
+        // until enough enemies have left
+        let room = self.max_enemies - self.enemies.len();
+        let due = self
+            .script
+            .iter()
+            .take_while(|spawn| spawn.time <= current_time)
+            .take(room)
+            .count();
+        for spawn in self.script.drain(..due).collect::<Vec<_>>() {
+            self.spawn(spawn.template, spawn.path, current_time);
+        }
+
+        // Move the enemies, let them attack and apply the damage they took from player shots
+        let escape_limit =
+            playing_field.size() * 0.5 + vector!(ENEMY_ESCAPE_MARGIN, ENEMY_ESCAPE_MARGIN);
+        let mut idx = 0;
+        while idx < self.enemies.len() {
+            let enemy = &mut self.enemies[idx];
+            if let Some(pattern) = enemy.update(&mut self.sprite_list, current_time) {
+                events.push(EnemyEvent::Attacked {
+                    enemy: enemy.id(),
+                    position: enemy.position(current_time),
+                    pattern,
+                });
+            }
+            let position = enemy.position(current_time);
+            enemy.damage(damage.get(&enemy.id()).copied().unwrap_or(0.0));
+
+            // Enemies that die or finish their path off the field are removed
+            let escaped = enemy.path_finished(current_time)
+                && (position[0].abs() > escape_limit[0] || position[1].abs() > escape_limit[1]);
+            if enemy.is_dead() {
+                let enemy = self.enemies.remove(idx);
+                events.push(Self::death_event(&enemy, position));
+                enemy.remove(&mut self.sprite_list);
+            } else if escaped {
+                let enemy = self.enemies.remove(idx);
+                events.push(EnemyEvent::Escaped { enemy: enemy.id() });
+                enemy.remove(&mut self.sprite_list);
+            } else {
+                idx += 1;
+            }
+        }
+
+        // Bosses only take damage while a phase is running
+        if let Some(boss) = self.boss.as_mut() {
+            let position = boss.enemy().position(current_time);
+            let damage = if boss.is_vulnerable() {
+                damage.get(&boss.enemy().id()).copied().unwrap_or(0.0)
+            } else {
+                0.0
+            };
+            events.extend(boss.update(&mut self.sprite_list, damage, current_time));
+            if boss.is_defeated() {
+                let enemy = self.boss.take().unwrap().into_enemy();
+                events.push(Self::death_event(&enemy, position));
+                enemy.remove(&mut self.sprite_list);
+            }
+        }
+
+        events
+    }
+
+    fn death_event(enemy: &Enemy, position: Vec2f) -> EnemyEvent {
+        let drops = enemy
+            .template()
+            .drop_table()
+            .roll(&mut StdRng::seed_from_u64(enemy.seed()));
+        EnemyEvent::Died {
+            enemy: enemy.id(),
+            position,
+            score_value: enemy.template().score_value(),
+            drops,
+        }
+    }
+
+    pub fn draw(&mut self, gfx: &mut GFX, delta_time: f64, current_time: f64) {
+        self.sprite_list.draw(gfx, delta_time, current_time);
+    }
+}
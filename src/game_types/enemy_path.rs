@@ -0,0 +1,128 @@
+use crate::*;
+use fennec_algebra::*;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PathSegment {
+    Linear {
+        to: Vec2f,
+        duration: f64,
+    },
+    Bezier {
+        control0: Vec2f,
+        control1: Vec2f,
+        to: Vec2f,
+        duration: f64,
+    },
+    Wait {
+        duration: f64,
+    },
+}
+
+impl PathSegment {
+    pub fn duration(&self) -> f64 {
+        match self {
+            PathSegment::Linear { duration, .. } => *duration,
+            PathSegment::Bezier { duration, .. } => *duration,
+            PathSegment::Wait { duration } => *duration,
+        }
+    }
+
+    pub fn end(&self, from: Vec2f) -> Vec2f {
+        match self {
+            PathSegment::Linear { to, .. } => *to,
+            PathSegment::Bezier { to, .. } => *to,
+            PathSegment::Wait { .. } => from,
+        }
+    }
+
+    fn position(&self, from: Vec2f, t: f32) -> Vec2f {
+        match self {
+            PathSegment::Linear { to, .. } => from + (*to - from) * t,
+            PathSegment::Bezier {
+                control0,
+                control1,
+                to,
+                ..
+            } => {
+                // Cubic bezier from the end of the previous segment
+                let u = 1.0 - t;
+                from * (u * u * u)
+                    + *control0 * (3.0 * u * u * t)
+                    + *control1 * (3.0 * u * t * t)
+                    + *to * (t * t * t)
+            }
+            PathSegment::Wait { .. } => from,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnemyPath {
+    start: Vec2f,
+    segments: Vec<PathSegment>,
+}
+
+impl EnemyPath {
+    pub fn new(start: Vec2f) -> Self {
+        Self {
+            start,
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn then(mut self, segment: PathSegment) -> Self {
+        if DEBUG && segment.duration() < 0.0 {
+            panic!("Path segment duration must not be negative");
+        }
+        self.segments.push(segment);
+        self
+    }
+
+    pub fn line_to(self, to: Vec2f, duration: f64) -> Self {
+        self.then(PathSegment::Linear { to, duration })
+    }
+
+    pub fn bezier_to(self, control0: Vec2f, control1: Vec2f, to: Vec2f, duration: f64) -> Self {
+        self.then(PathSegment::Bezier {
+            control0,
+            control1,
+            to,
+            duration,
+        })
+    }
+
+    pub fn wait(self, duration: f64) -> Self {
+        self.then(PathSegment::Wait { duration })
+    }
+
+    pub fn start(&self) -> Vec2f {
+        self.start
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.segments.iter().map(|segment| segment.duration()).sum()
+    }
+
+    pub fn is_finished(&self, elapsed: f64) -> bool {
+        elapsed >= self.duration()
+    }
+
+    pub fn position(&self, elapsed: f64) -> Vec2f {
+        // Walk the segments until reaching the one that is running at this time
+        let mut from = self.start;
+        let mut remaining = elapsed.max(0.0);
+        for segment in self.segments.iter() {
+            let duration = segment.duration();
+            if remaining < duration {
+                return segment.position(from, (remaining / duration) as f32);
+            }
+            remaining -= duration;
+            from = segment.end(from);
+        }
+        from
+    }
+}
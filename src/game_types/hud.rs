@@ -0,0 +1,141 @@
+use crate::*;
+use fennec_algebra::*;
+use std::rc::Rc;
+
+const HUD_BAR_RECTANGLE: Vec4f = vector!(192.0, 0.0, 16.0, 16.0);
+const HUD_PIP_RECTANGLE: Vec4f = vector!(208.0, 0.0, 16.0, 16.0);
+const HUD_BAR_HEIGHT: f32 = 0.04;
+const HUD_PIP_SIZE: f32 = 0.05;
+const HUD_HEALTH_BAR_Y: f32 = -0.9;
+const HUD_TIMER_BAR_Y: f32 = -0.84;
+const HUD_PIP_Y: f32 = -0.77;
+const HUD_MARGIN: f32 = 0.1;
+
+pub struct Hud {
+    viewport_ratio: ViewportRatio,
+    sprite_list: SpriteList,
+    health_bar: SpriteObject,
+    timer_bar: SpriteObject,
+    phase_pips: Vec<SpriteObject>,
+}
+
+impl Hud {
+    pub fn new(
+        viewport_ratio: Vec4f,
+        texture: Rc<Texture<{ TextureType::Texture2D }>>,
+        sprite_pipeline: Rc<Pipeline>,
+    ) -> Self {
+        let mut sprite_list = SpriteList::new(
+            SpriteMaterial::new(sprite_pipeline),
+            SpriteAnimator::new(),
+            texture,
+            2 + MAX_BOSS_PHASES as GLsizeiptr,
+        );
+
+        // Everything starts hidden until there is a boss to show
+        let bar_rectangle = sprite_list.rectangle_to_texcoord(HUD_BAR_RECTANGLE);
+        let pip_rectangle = sprite_list.rectangle_to_texcoord(HUD_PIP_RECTANGLE);
+        let hidden = |rectangle| SpriteActorVertex::new(rectangle, 0.0).with_scale(Vector::zero());
+        let health_bar = sprite_list.new_sprite_object(hidden(bar_rectangle));
+        let timer_bar = sprite_list.new_sprite_object(hidden(bar_rectangle));
+        let phase_pips = (0..MAX_BOSS_PHASES)
+            .map(|_| sprite_list.new_sprite_object(hidden(pip_rectangle)))
+            .collect();
+
+        Self {
+            viewport_ratio: ViewportRatio::new(viewport_ratio),
+            sprite_list,
+            health_bar,
+            timer_bar,
+            phase_pips,
+        }
+    }
+
+    pub fn viewport(&self, window_size: Vec2u) -> Vec4f {
+        self.viewport_ratio.viewport(window_size)
+    }
+
+    pub fn viewport_pixels(&self, window_size: Vec2u) -> Vec4i {
+        self.viewport_ratio.viewport_pixels(window_size)
+    }
+
+    pub fn viewport_aspect_ratio(&self, window_size: Vec2u) -> f32 {
+        self.viewport_ratio.aspect_ratio(window_size)
+    }
+
+    fn set_bar(
+        sprite_list: &mut SpriteList,
+        bar: &mut SpriteObject,
+        y: f32,
+        fraction: f32,
+        full_width: f32,
+        current_time: f64,
+    ) {
+        // Bars shrink toward their left end
+        let width = full_width * fraction.max(0.0).min(1.0);
+        let position = vector!(-full_width * 0.5 + width * 0.5, y);
+        let scale = vector!(width, HUD_BAR_HEIGHT);
+        bar.set_position(sprite_list, position, current_time);
+        bar.set_scale(sprite_list, scale, current_time);
+    }
+
+    fn show_boss(&mut self, status: Option<BossStatus>, full_width: f32, current_time: f64) {
+        let (health_fraction, time_fraction, phases_remaining) = match status {
+            Some(status) => (
+                status.health_fraction(),
+                status.time_fraction(),
+                status.phases_remaining(),
+            ),
+            None => (0.0, 0.0, 0),
+        };
+        Self::set_bar(
+            &mut self.sprite_list,
+            &mut self.health_bar,
+            HUD_HEALTH_BAR_Y,
+            health_fraction,
+            full_width,
+            current_time,
+        );
+        Self::set_bar(
+            &mut self.sprite_list,
+            &mut self.timer_bar,
+            HUD_TIMER_BAR_Y,
+            time_fraction,
+            full_width,
+            current_time,
+        );
+
+        // One pip for each phase still to come
+        for (idx, pip) in self.phase_pips.iter_mut().enumerate() {
+            let position = vector!(
+                -full_width * 0.5 + HUD_PIP_SIZE * (idx as f32 + 0.5),
+                HUD_PIP_Y
+            );
+            let scale = if idx < phases_remaining {
+                vector!(HUD_PIP_SIZE, HUD_PIP_SIZE)
+            } else {
+                Vector::zero()
+            };
+            pip.set_position(&mut self.sprite_list, position, current_time);
+            pip.set_scale(&mut self.sprite_list, scale, current_time);
+        }
+    }
+
+    pub fn draw(
+        &mut self,
+        gfx: &mut GFX,
+        window_size: Vec2u,
+        boss_status: Option<BossStatus>,
+        delta_time: f64,
+        current_time: f64,
+    ) {
+        // The HUD has its own viewport beside the playing field, spanning -1 to 1 vertically
+        let aspect_ratio = self.viewport_aspect_ratio(window_size);
+        gfx.viewport(self.viewport_pixels(window_size), true);
+        gfx.set_projection(Mat4f::ortho(vector!(aspect_ratio * 2.0, 2.0), -1.0, 1.0));
+
+        let full_width = aspect_ratio * 2.0 - HUD_MARGIN;
+        self.show_boss(boss_status, full_width, current_time);
+        self.sprite_list.draw(gfx, delta_time, current_time);
+    }
+}
@@ -0,0 +1,288 @@
+use crate::*;
+use fennec_algebra::*;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+const ITEM_GRAVITY: f32 = 1.5;
+const ITEM_MAX_FALL_SPEED: f32 = 0.6;
+const ITEM_POP_SPEED: f32 = 0.7;
+const ITEM_SPREAD: f32 = 0.06;
+const ITEM_ATTRACT_SPEED: f32 = 3.0;
+const ITEM_CULL_MARGIN: f32 = 0.1;
+const ITEM_HITBOX_RADIUS: f32 = 0.03;
+const PLAYER_COLLECT_RADIUS: f32 = 0.08;
+const FOCUS_ATTRACT_RADIUS: f32 = 0.35;
+const MIN_POINT_VALUE: f32 = 0.3;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ItemStyle {
+    rectangle: Vec4f,
+    scale: Vec2f,
+}
+
+impl ItemStyle {
+    pub fn new(rectangle: Vec4f, scale: Vec2f) -> Self {
+        Self { rectangle, scale }
+    }
+
+    pub fn rectangle(&self) -> Vec4f {
+        self.rectangle
+    }
+
+    pub fn scale(&self) -> Vec2f {
+        self.scale
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ItemEvent {
+    Collected {
+        kind: ItemKind,
+        position: Vec2f,
+        value: f32,
+    },
+}
+
+#[derive(Debug)]
+struct Item {
+    kind: ItemKind,
+    sprite: SpriteReference,
+    position: Vec2f,
+    velocity: Vec2f,
+    attracted: bool,
+    auto_collected: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ItemCollector {
+    position: Vec2f,
+    focused: bool,
+}
+
+impl ItemCollector {
+    pub fn new(position: Vec2f, focused: bool) -> Self {
+        Self { position, focused }
+    }
+
+    pub fn position(&self) -> Vec2f {
+        self.position
+    }
+
+    pub fn focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn collider(&self) -> Collider {
+        Collider::new(
+            self.position,
+            CollisionShape::Circle {
+                radius: PLAYER_COLLECT_RADIUS,
+            },
+            CollisionLayer::Player,
+            CollisionMask::none().with(CollisionLayer::Item),
+        )
+    }
+}
+
+pub struct ItemList {
+    sprite_list: SpriteList,
+    items: Vec<Item>,
+    styles: [ItemStyle; 4],
+    field_half_size: Vec2f,
+    auto_collect_line: f32,
+}
+
+impl ItemList {
+    pub fn new(
+        texture: Rc<Texture<{ TextureType::Texture2D }>>,
+        sprite_pipeline: Rc<Pipeline>,
+        styles: [ItemStyle; 4],
+        max_items: GLsizeiptr,
+        playing_field: &PlayingField,
+        auto_collect_line: f32,
+    ) -> Self {
+        let mut sprite_list = SpriteList::new(
+            SpriteMaterial::new(sprite_pipeline),
+            SpriteAnimator::new(),
+            texture,
+            max_items,
+        );
+        sprite_list.set_cull_bounds(playing_field, ITEM_CULL_MARGIN);
+        Self {
+            sprite_list,
+            items: Vec::new(),
+            styles,
+            field_half_size: playing_field.size() * 0.5,
+            auto_collect_line,
+        }
+    }
+
+    fn style_idx(kind: ItemKind) -> usize {
+        match kind {
+            ItemKind::Power => 0,
+            ItemKind::Point => 1,
+            ItemKind::Life => 2,
+            ItemKind::Bomb => 3,
+        }
+    }
+
+    pub fn item_count(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn auto_collect_line(&self) -> f32 {
+        self.auto_collect_line
+    }
+
+    pub fn spawn_item(&mut self, kind: ItemKind, position: Vec2f, current_time: f64) {
+        // Items pop upward before falling, and drops that don't fit in the item pool are skipped
+        let style = self.styles[Self::style_idx(kind)];
+        let velocity = vector!(0.0, -ITEM_POP_SPEED);
+        let sprite = match self.sprite_list.try_add_sprite(
+            SpriteActorVertex::new(
+                self.sprite_list.rectangle_to_texcoord(style.rectangle()),
+                current_time,
+            )
+            .with_position(position)
+            .with_scale(style.scale())
+            .with_hitbox_radius(ITEM_HITBOX_RADIUS),
+        ) {
+            Some(sprite) => sprite,
+            None => return,
+        };
+        self.items.push(Item {
+            kind,
+            sprite,
+            position,
+            velocity,
+            attracted: false,
+            auto_collected: false,
+        });
+    }
+
+    pub fn spawn_drops(&mut self, drops: &[ItemKind], position: Vec2f, current_time: f64) {
+        // Spread the drops out in a ring so they don't stack
+        for (idx, &kind) in drops.iter().enumerate() {
+            let offset = if drops.len() == 1 {
+                Vector::zero()
+            } else {
+                Vec2f::from_angle(idx as f32 / drops.len() as f32 * std::f32::consts::PI * 2.0)
+                    * ITEM_SPREAD
+            };
+            self.spawn_item(kind, position + offset, current_time);
+        }
+    }
+
+    pub fn attract_all(&mut self) {
+        for item in self.items.iter_mut() {
+            item.attracted = true;
+            item.auto_collected = true;
+        }
+    }
+
+    pub fn colliders(&self) -> Vec<(SpriteReference, Collider)> {
+        self.items
+            .iter()
+            .map(|item| {
+                let collider = Collider::new(
+                    item.position,
+                    CollisionShape::Circle {
+                        radius: ITEM_HITBOX_RADIUS,
+                    },
+                    CollisionLayer::Item,
+                    CollisionMask::none().with(CollisionLayer::Player),
+                );
+                (item.sprite, collider)
+            })
+            .collect()
+    }
+
+    pub fn update(
+        &mut self,
+        collector: Option<ItemCollector>,
+        collected: &HashSet<SpriteReference>,
+        delta_time: f64,
+        current_time: f64,
+    ) -> Vec<ItemEvent> {
+        let delta_time = delta_time as f32;
+
+        // Pick up the items that touched the collector
+        let (collected, items): (Vec<Item>, Vec<Item>) = self
+            .items
+            .drain(..)
+            .partition(|item| collected.contains(&item.sprite));
+        self.items = items;
+        let events = collected
+            .into_iter()
+            .map(|item| {
+                self.sprite_list.remove_sprite(item.sprite);
+                ItemEvent::Collected {
+                    kind: item.kind,
+                    position: item.position,
+                    value: self.item_value(&item),
+                }
+            })
+            .collect();
+
+        // Everything is pulled in while the collector is above the auto-collect line
+        if let Some(collector) = collector {
+            if collector.position()[1] < self.auto_collect_line {
+                self.attract_all();
+            }
+        }
+
+        // Move the rest towards the collector, or let them fall
+        for item in self.items.iter_mut() {
+            match collector {
+                Some(collector) => {
+                    // Focusing pulls in nearby items
+                    let offset = collector.position() - item.position;
+                    let distance = (offset[0] * offset[0] + offset[1] * offset[1]).sqrt();
+                    if collector.focused() && distance < FOCUS_ATTRACT_RADIUS {
+                        item.attracted = true;
+                    }
+                    if item.attracted && distance > 0.0 {
+                        item.velocity = offset * (ITEM_ATTRACT_SPEED / distance);
+                    }
+                }
+                // Nothing to be attracted to without a collector
+                None => item.attracted = false,
+            }
+
+            // Fall with gravity unless attracted
+            if !item.attracted {
+                item.velocity = vector!(
+                    0.0,
+                    (item.velocity[1] + ITEM_GRAVITY * delta_time).min(ITEM_MAX_FALL_SPEED)
+                );
+            }
+            item.position = item.position + item.velocity * delta_time;
+            let position = item.position;
+            self.sprite_list.change_sprite(&item.sprite, |actor| {
+                actor.set_position(position, current_time)
+            });
+        }
+
+        events
+    }
+
+    fn item_value(&self, item: &Item) -> f32 {
+        // Items are worth the most when auto-collected, and less the lower they are caught
+        if item.auto_collected || item.position[1] < self.auto_collect_line {
+            return 1.0;
+        }
+        let depth = (item.position[1] - self.auto_collect_line)
+            / (self.field_half_size[1] - self.auto_collect_line);
+        (1.0 - depth * (1.0 - MIN_POINT_VALUE)).max(MIN_POINT_VALUE)
+    }
+
+    pub fn draw(&mut self, gfx: &mut GFX, delta_time: f64, current_time: f64) {
+        self.sprite_list.draw(gfx, delta_time, current_time);
+
+        // Forget the items that fell off the field
+        let culled = self.sprite_list.take_culled();
+        if !culled.is_empty() {
+            self.items.retain(|item| !culled.contains(&item.sprite));
+        }
+    }
+}
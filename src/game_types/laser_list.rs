@@ -0,0 +1,487 @@
+use crate::*;
+use fennec_algebra::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const WARM_UP_WIDTH_SCALE: f32 = 0.15;
+const WARM_UP_ALPHA: f32 = 0.5;
+const HITBOX_WIDTH_SCALE: f32 = 0.6;
+const RESTART_INDEX: u32 = u32::MAX;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LaserPhase {
+    WarmUp,
+    Active,
+    Fading,
+    Finished,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LaserTiming {
+    warm_up: f64,
+    active: Option<f64>,
+    fade: f64,
+}
+
+impl LaserTiming {
+    pub fn new(warm_up: f64, fade: f64) -> Self {
+        if DEBUG && (warm_up < 0.0 || fade < 0.0) {
+            panic!("Laser warm-up and fade durations must not be negative");
+        }
+        Self {
+            warm_up,
+            active: None,
+            fade,
+        }
+    }
+
+    pub fn with_active_duration(mut self, active: f64) -> Self {
+        if DEBUG && active < 0.0 {
+            panic!("Laser active duration must not be negative");
+        }
+        self.active = Some(active);
+        self
+    }
+
+    pub fn warm_up(&self) -> f64 {
+        self.warm_up
+    }
+
+    pub fn active(&self) -> Option<f64> {
+        self.active
+    }
+
+    pub fn fade(&self) -> f64 {
+        self.fade
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Laser {
+    points: Vec<Vec2f>,
+    max_points: usize,
+    width: f32,
+    rectangle: Vec4f,
+    texture_length: f32,
+    scroll_speed: f32,
+    timing: LaserTiming,
+    spawn_time: f64,
+    fade_start: Option<f64>,
+}
+
+impl Laser {
+    pub fn new(rectangle: Vec4f, width: f32, timing: LaserTiming, current_time: f64) -> Self {
+        if DEBUG && width <= 0.0 {
+            panic!("Laser width must be greater than 0");
+        }
+        Self {
+            points: Vec::new(),
+            max_points: 2,
+            width,
+            rectangle,
+            texture_length: width,
+            scroll_speed: 0.0,
+            timing,
+            spawn_time: current_time,
+            fade_start: None,
+        }
+    }
+
+    pub fn straight(
+        rectangle: Vec4f,
+        width: f32,
+        timing: LaserTiming,
+        origin: Vec2f,
+        angle: f32,
+        length: f32,
+        current_time: f64,
+    ) -> Self {
+        let mut laser = Self::new(rectangle, width, timing, current_time);
+        laser.set_straight(origin, angle, length);
+        laser
+    }
+
+    pub fn curvy(
+        rectangle: Vec4f,
+        width: f32,
+        timing: LaserTiming,
+        head: Vec2f,
+        max_points: usize,
+        current_time: f64,
+    ) -> Self {
+        Self::new(rectangle, width, timing, current_time)
+            .with_max_points(max_points)
+            .with_points(vec![head])
+    }
+
+    pub fn with_max_points(mut self, max_points: usize) -> Self {
+        if DEBUG && max_points < 2 {
+            panic!("A laser needs at least 2 points");
+        }
+        self.max_points = max_points;
+        self.points.truncate(max_points);
+        self
+    }
+
+    pub fn with_points(mut self, points: Vec<Vec2f>) -> Self {
+        self.set_points(points);
+        self
+    }
+
+    pub fn with_scroll(mut self, texture_length: f32, scroll_speed: f32) -> Self {
+        if DEBUG && texture_length <= 0.0 {
+            panic!("Laser texture length must be greater than 0");
+        }
+        self.texture_length = texture_length;
+        self.scroll_speed = scroll_speed;
+        self
+    }
+
+    pub fn points(&self) -> &[Vec2f] {
+        &self.points
+    }
+
+    pub fn max_points(&self) -> usize {
+        self.max_points
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn set_width(&mut self, width: f32) {
+        if DEBUG && width <= 0.0 {
+            panic!("Laser width must be greater than 0");
+        }
+        self.width = width;
+    }
+
+    pub fn set_points(&mut self, points: Vec<Vec2f>) {
+        if DEBUG && points.len() > self.max_points {
+            panic!(
+                "Laser was given {} points but can only hold {}",
+                points.len(),
+                self.max_points
+            );
+        }
+        self.points = points;
+    }
+
+    pub fn set_straight(&mut self, origin: Vec2f, angle: f32, length: f32) {
+        self.points = vec![origin, origin + Vec2f::from_angle(angle) * length];
+    }
+
+    pub fn push_head(&mut self, head: Vec2f) {
+        // The head is the first point; the oldest points fall off the tail
+        self.points.insert(0, head);
+        self.points.truncate(self.max_points);
+    }
+
+    pub fn fade_out(&mut self, current_time: f64) {
+        if self.fade_start.is_none() {
+            self.fade_start = Some(current_time);
+        }
+    }
+
+    fn fade_start(&self) -> Option<f64> {
+        self.fade_start.or_else(|| {
+            self.timing
+                .active
+                .map(|active| self.spawn_time + self.timing.warm_up + active)
+        })
+    }
+
+    pub fn phase(&self, current_time: f64) -> LaserPhase {
+        match self.fade_start() {
+            Some(fade_start) if current_time >= fade_start + self.timing.fade => {
+                LaserPhase::Finished
+            }
+            Some(fade_start) if current_time >= fade_start => LaserPhase::Fading,
+            _ if current_time < self.spawn_time + self.timing.warm_up => LaserPhase::WarmUp,
+            _ => LaserPhase::Active,
+        }
+    }
+
+    fn width_alpha(&self, current_time: f64) -> (f32, f32) {
+        match self.phase(current_time) {
+            LaserPhase::WarmUp => (self.width * WARM_UP_WIDTH_SCALE, WARM_UP_ALPHA),
+            LaserPhase::Active => (self.width, 1.0),
+            LaserPhase::Fading => {
+                let fade_start = self.fade_start().unwrap();
+                let remaining = if self.timing.fade > 0.0 {
+                    (1.0 - (current_time - fade_start) / self.timing.fade) as f32
+                } else {
+                    0.0
+                };
+                (self.width * remaining, remaining)
+            }
+            LaserPhase::Finished => (0.0, 0.0),
+        }
+    }
+
+    fn build_strip(
+        &self,
+        current_time: f64,
+        vertices: &mut Vec<LaserVertex>,
+        indices: &mut Vec<u32>,
+    ) {
+        // Nothing to draw without a visible segment
+        let (width, alpha) = self.width_alpha(current_time);
+        if self.points.len() < 2 || width <= 0.0 {
+            return;
+        }
+
+        // Separate this strip from the previous one
+        if !indices.is_empty() {
+            indices.push(RESTART_INDEX);
+        }
+
+        // Emit a pair of vertices on either side of each point, with the texture scrolling along the length
+        let scroll = self.scroll_speed * (current_time - self.spawn_time) as f32;
+        let mut distance = 0.0;
+        for (idx, &point) in self.points.iter().enumerate() {
+            if idx > 0 {
+                distance += length(point - self.points[idx - 1]);
+            }
+            let previous = self.points[idx.saturating_sub(1)];
+            let next = self.points[(idx + 1).min(self.points.len() - 1)];
+            let tangent = normalized(next - previous);
+            let side = vector!(-tangent[1], tangent[0]) * (width * 0.5);
+            let u = distance / self.texture_length - scroll;
+
+            indices.push(vertices.len() as u32);
+            vertices.push(LaserVertex::new(
+                point + side,
+                vector!(u, 0.0),
+                self.rectangle,
+                alpha,
+            ));
+            indices.push(vertices.len() as u32);
+            vertices.push(LaserVertex::new(
+                point - side,
+                vector!(u, 1.0),
+                self.rectangle,
+                alpha,
+            ));
+        }
+    }
+
+    pub fn hitbox(
+        &self,
+        current_time: f64,
+        layer: CollisionLayer,
+        mask: CollisionMask,
+    ) -> Vec<Collider> {
+        // Lasers only hurt while fully active
+        if self.phase(current_time) != LaserPhase::Active {
+            return Vec::new();
+        }
+
+        // One capsule per segment of the polyline
+        let radius = self.width * 0.5 * HITBOX_WIDTH_SCALE;
+        self.points
+            .windows(2)
+            .map(|segment| {
+                Collider::new(
+                    Vector::zero(),
+                    CollisionShape::Capsule {
+                        start: segment[0],
+                        end: segment[1],
+                        radius,
+                    },
+                    layer,
+                    mask,
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct LaserReference {
+    idx: usize,
+}
+
+pub struct LaserList {
+    max_lasers: usize,
+    max_points: usize,
+    lasers: Vec<Option<Laser>>,
+    laser_model: Model,
+    vertex_buffer: Rc<RefCell<Buffer>>,
+    index_buffer: Rc<Buffer>,
+    texture_size: Vec2f,
+}
+
+impl LaserList {
+    pub fn new(
+        texture: Rc<Texture<{ TextureType::Texture2D }>>,
+        max_lasers: usize,
+        max_points: usize,
+    ) -> Self {
+        if DEBUG && max_lasers == 0 {
+            panic!("Max lasers must be greater than 0");
+        }
+        if DEBUG && max_points < 2 {
+            panic!("Max points must be at least 2");
+        }
+
+        // Create buffers large enough for every laser to use all of its points
+        let texture_size = vector!(texture.size()[0] as f32, texture.size()[1] as f32);
+        let vertex_buffer = Rc::new(RefCell::new(Buffer::new::<LaserVertex>(
+            (max_lasers * max_points * 2) as GLsizeiptr,
+            false,
+            true,
+        )));
+        let index_buffer = Rc::new(Buffer::new::<u32>(
+            (max_lasers * (max_points * 2 + 1)) as GLsizeiptr,
+            false,
+            true,
+        ));
+
+        // Create the model used to draw every laser at once
+        let mut laser_material = LaserMaterial::new();
+        laser_material.set_texture(texture);
+        let vertex_array = VertexArray::new(
+            vec![VertexBufferBinding::new::<LaserVertex>(
+                vertex_buffer.clone(),
+                0,
+            )],
+            index_buffer.clone(),
+        );
+        let laser_model = Model::new(vec![Mesh::new(
+            Rc::new(RefCell::new(laser_material)),
+            vertex_array,
+            PrimitiveType::TriangleStrip,
+        )]);
+
+        Self {
+            max_lasers,
+            max_points,
+            lasers: Vec::with_capacity(max_lasers),
+            laser_model,
+            vertex_buffer,
+            index_buffer,
+            texture_size,
+        }
+    }
+
+    pub fn add_laser(&mut self, laser: Laser) -> LaserReference {
+        if DEBUG && laser.max_points() > self.max_points {
+            panic!(
+                "Laser can hold {} points but the list only allows {}",
+                laser.max_points(),
+                self.max_points
+            );
+        }
+
+        // Reuse the first free slot, if any
+        if let Some(idx) = self.lasers.iter().position(Option::is_none) {
+            self.lasers[idx] = Some(laser);
+            LaserReference { idx }
+        } else if self.lasers.len() < self.max_lasers {
+            self.lasers.push(Some(laser));
+            LaserReference {
+                idx: self.lasers.len() - 1,
+            }
+        } else {
+            panic!("No available laser indices left");
+        }
+    }
+
+    pub fn remove_laser(&mut self, laser: LaserReference) {
+        self.lasers[laser.idx] = None;
+    }
+
+    pub fn laser(&self, laser: &LaserReference) -> Option<&Laser> {
+        self.lasers[laser.idx].as_ref()
+    }
+
+    pub fn change_laser(&mut self, laser: &LaserReference, f: impl FnOnce(&mut Laser)) {
+        if let Some(laser_instance) = self.lasers[laser.idx].as_mut() {
+            f(laser_instance);
+            if DEBUG && laser_instance.max_points() > self.max_points {
+                panic!(
+                    "Laser can hold {} points but the list only allows {}",
+                    laser_instance.max_points(),
+                    self.max_points
+                );
+            }
+        } else {
+            panic!("LaserReference {:?} does not point to a valid laser", laser);
+        }
+    }
+
+    pub fn hitboxes(
+        &self,
+        current_time: f64,
+        layer: CollisionLayer,
+        mask: CollisionMask,
+    ) -> Vec<Collider> {
+        self.lasers
+            .iter()
+            .flatten()
+            .flat_map(|laser| laser.hitbox(current_time, layer, mask))
+            .collect()
+    }
+
+    pub fn draw(&mut self, gfx: &mut GFX, current_time: f64) {
+        // Build the strips of every laser
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        self.lasers
+            .iter()
+            .flatten()
+            .for_each(|laser| laser.build_strip(current_time, &mut vertices, &mut indices));
+        if indices.is_empty() {
+            return;
+        }
+
+        // Upload the strips
+        {
+            let vertex_buffer = self.vertex_buffer.borrow();
+            let mut mapped = vertex_buffer.map(0..vertices.len() as GLsizeiptr);
+            vertices
+                .iter()
+                .enumerate()
+                .for_each(|(idx, &vertex)| mapped[idx] = vertex);
+        }
+        {
+            let mut mapped = self.index_buffer.map(0..indices.len() as GLsizeiptr);
+            indices
+                .iter()
+                .enumerate()
+                .for_each(|(idx, &index)| mapped[idx] = index);
+        }
+
+        // Draw the strips blended, restarting the strip at each restart index
+        gfx.blend(true);
+        gfx.primitive_restart(true);
+        gfx.draw_model_partial(&self.laser_model, 1, indices.len() as GLsizei);
+        gfx.primitive_restart(false);
+        gfx.blend(false);
+    }
+
+    pub fn rectangle_to_texcoord(&self, laser_rectangle: Vec4f) -> Vec4f {
+        let texture_size = self.texture_size;
+        laser_rectangle
+            / vector!(
+                texture_size[0],
+                texture_size[1],
+                texture_size[0],
+                texture_size[1]
+            )
+    }
+}
+
+fn length(v: Vec2f) -> f32 {
+    (v[0] * v[0] + v[1] * v[1]).sqrt()
+}
+
+fn normalized(v: Vec2f) -> Vec2f {
+    let v_length = length(v);
+    if v_length > 0.0 {
+        v * (1.0 / v_length)
+    } else {
+        vector!(1.0, 0.0)
+    }
+}
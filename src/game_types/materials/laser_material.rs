@@ -0,0 +1,103 @@
+use crate::*;
+use std::rc::Rc;
+
+const VERTEX_SHADER: &str = "
+#[feature(camera)]
+layout(location = 0) in vec2 v_position;
+layout(location = 1) in vec2 v_texCoord;
+layout(location = 2) in vec4 v_rectangle;
+layout(location = 3) in float v_alpha;
+
+layout(location = 0) out vec2 f_texCoord;
+layout(location = 1) out vec4 f_rectangle;
+layout(location = 2) out float f_alpha;
+
+out gl_PerVertex { vec4 gl_Position; };
+
+void main()
+{
+    f_texCoord = v_texCoord;
+    f_rectangle = v_rectangle;
+    f_alpha = v_alpha;
+    gl_Position = applyProjection(applyView(vec4(v_position, 0.0, 1.0)));
+}";
+
+const FRAGMENT_SHADER: &str = "
+layout(location = 0) in vec2 f_texCoord;
+layout(location = 1) in vec4 f_rectangle;
+layout(location = 2) in float f_alpha;
+
+layout(location = 0) out vec4 out_color;
+
+uniform sampler2D u_texture;
+
+void main()
+{
+    vec2 repeated = vec2(fract(f_texCoord.x), f_texCoord.y);
+    vec2 texCoord = vec2(0.0, 1.0) + (f_rectangle.xy + repeated * f_rectangle.zw) * vec2(1.0, -1.0);
+    out_color = texture(u_texture, texCoord) * vec4(1.0, 1.0, 1.0, f_alpha);
+}";
+
+#[derive(Clone, Debug)]
+pub struct LaserMaterial {
+    pipeline: Rc<Pipeline>,
+    texture: Option<Rc<Texture<{ TextureType::Texture2D }>>>,
+}
+
+impl LaserMaterial {
+    pub fn new() -> Self {
+        let stages = vec![
+            Program::new(ShaderStage::Vertex, VERTEX_SHADER),
+            Program::new(ShaderStage::Fragment, FRAGMENT_SHADER),
+        ];
+
+        let pipeline = Rc::new(Pipeline::new(stages));
+
+        Self {
+            pipeline,
+            texture: None,
+        }
+    }
+
+    pub fn texture(&self) -> Option<&Rc<Texture<{ TextureType::Texture2D }>>> {
+        self.texture.as_ref()
+    }
+
+    pub fn set_texture(&mut self, texture: Rc<Texture<{ TextureType::Texture2D }>>) {
+        self.texture = Some(texture);
+    }
+}
+
+impl Material for LaserMaterial {
+    fn pipeline(&self) -> &Rc<Pipeline> {
+        &self.pipeline
+    }
+
+    fn vertex_attribute_bindings(&self) -> Vec<Vec<VertexAttributeBinding>> {
+        vec![vec![
+            VertexAttributeBinding::Float2,
+            VertexAttributeBinding::Float2,
+            VertexAttributeBinding::Float4,
+            VertexAttributeBinding::Float,
+        ]]
+    }
+
+    fn _on_bind(&self) {
+        {
+            let frag_program = self.pipeline().fragment_program();
+            let frag_texture_location = frag_program.uniform_location("u_texture");
+            if let Some(frag_texture_location) = frag_texture_location {
+                frag_program.set_uniform_texture_unit(frag_texture_location, 0);
+                unsafe {
+                    gl::BindTextureUnit(0, self.texture.as_ref().expect("Texture not set").handle())
+                };
+            }
+        }
+    }
+}
+
+impl Default for LaserMaterial {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1 +1,2 @@
-
+mod laser_material;
+pub use laser_material::*;
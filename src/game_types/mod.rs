@@ -13,8 +13,56 @@ pub use vertices::*;
 mod player;
 pub use player::*;
 
+mod player_character;
+pub use player_character::*;
+
+mod player_weapon;
+pub use player_weapon::*;
+
+mod player_shot_list;
+pub use player_shot_list::*;
+
 mod player_list;
 pub use player_list::*;
 
 mod playing_field;
 pub use playing_field::*;
+
+mod viewport_ratio;
+pub use viewport_ratio::*;
+
+mod collision;
+pub use collision::*;
+
+mod laser_list;
+pub use laser_list::*;
+
+mod bullet_emitter;
+pub use bullet_emitter::*;
+
+mod drop_table;
+pub use drop_table::*;
+
+mod enemy_path;
+pub use enemy_path::*;
+
+mod enemy;
+pub use enemy::*;
+
+mod enemy_list;
+pub use enemy_list::*;
+
+mod boss;
+pub use boss::*;
+
+mod hud;
+pub use hud::*;
+
+mod item_list;
+pub use item_list::*;
+
+mod score;
+pub use score::*;
+
+mod stage;
+pub use stage::*;
@@ -1,41 +1,402 @@
 use crate::*;
 use fennec_algebra::*;
 use glfw::Key;
+use std::f32::consts::FRAC_1_SQRT_2;
+
+pub const STARTING_LIVES: u32 = 2;
+const DEATHBOMB_WINDOW: f64 = 0.15;
+const INVULNERABILITY_DURATION: f64 = 3.0;
+const BOMB_DURATION: f64 = 2.5;
+const BLINK_INTERVAL: f64 = 0.08;
+const DEATH_POWER_LOSS: f32 = 1.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlayerState {
+    Alive,
+    Dying { hit_time: f64 },
+    GameOver,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PlayerEvent {
+    Bombed,
+    Died,
+    Respawned,
+    GameOver,
+}
 
 pub struct Player {
     sprite_object: SpriteObject,
-    scale: Vec2f,
-    sprite_rectangle: Vec4f,
+    hitbox_object: SpriteObject,
+    option_objects: Vec<SpriteObject>,
+    option_positions: Vec<Vec2f>,
+    character: PlayerCharacter,
+    spawn_position: Vec2f,
+    state: PlayerState,
+    lives: u32,
+    bombs: u32,
+    invulnerable_until: f64,
+    blinking_until: f64,
+    bomb_until: f64,
+    visible: bool,
+    focused: bool,
+    power: f32,
+    level_idx: Option<usize>,
+    fire_timers: [Vec<f64>; 2],
 }
 
 impl Player {
     pub fn new(
         sprite_list: &mut SpriteList,
         position: Vec2f,
-        scale: Vec2f,
-        sprite_rectangle: Vec4f,
+        character: PlayerCharacter,
         current_time: f64,
     ) -> Self {
-        let sprite_rectangle = sprite_list.rectangle_to_texcoord(sprite_rectangle);
+        let sprite_rectangle = sprite_list.rectangle_to_texcoord(character.sprite_rectangle());
+        let hitbox_rectangle = sprite_list.rectangle_to_texcoord(character.hitbox_rectangle());
+        let sprite_object = sprite_list.new_sprite_object(
+            SpriteActorVertex::new(sprite_rectangle, current_time)
+                .with_position(position)
+                .with_scale(character.scale()),
+        );
+
+        // The hitbox is only shown while focused, so it starts out with no size
+        let hitbox_object = sprite_list.new_sprite_object(
+            SpriteActorVertex::new(hitbox_rectangle, current_time)
+                .with_position(position)
+                .with_scale(Vector::zero())
+                .with_hitbox_radius(character.hitbox_radius()),
+        );
+
+        // Options are likewise hidden until the power level calls for them
+        let option_rectangle = character.weapon().map_or(Vector::zero(), |weapon| {
+            sprite_list.rectangle_to_texcoord(weapon.option_rectangle())
+        });
+        let option_objects = (0..MAX_OPTIONS)
+            .map(|_| {
+                sprite_list.new_sprite_object(
+                    SpriteActorVertex::new(option_rectangle, current_time)
+                        .with_position(position)
+                        .with_scale(Vector::zero()),
+                )
+            })
+            .collect();
+
         Self {
-            sprite_object: sprite_list.new_sprite_object(SpriteActorVertex::new(sprite_rectangle, current_time).with_position(position).with_scale(scale)),
-            scale,
-            sprite_rectangle,
+            sprite_object,
+            hitbox_object,
+            option_objects,
+            option_positions: vec![position; MAX_OPTIONS],
+            bombs: character.bombs_per_life(),
+            character,
+            spawn_position: position,
+            state: PlayerState::Alive,
+            lives: STARTING_LIVES,
+            invulnerable_until: current_time,
+            blinking_until: current_time,
+            bomb_until: current_time,
+            visible: true,
+            focused: false,
+            power: 0.0,
+            level_idx: None,
+            fire_timers: [Vec::new(), Vec::new()],
         }
     }
 
+    pub fn position(&self, sprite_list: &SpriteList, current_time: f64) -> Vec2f {
+        self.sprite_object.position(sprite_list, current_time)
+    }
+
+    pub fn character(&self) -> &PlayerCharacter {
+        &self.character
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn hitbox_radius(&self) -> f32 {
+        self.character.hitbox_radius()
+    }
+
+    pub fn state(&self) -> PlayerState {
+        self.state
+    }
+
+    pub fn lives(&self) -> u32 {
+        self.lives
+    }
+
+    pub fn add_life(&mut self) {
+        self.lives += 1;
+    }
+
+    pub fn bombs(&self) -> u32 {
+        self.bombs
+    }
+
+    pub fn add_bomb(&mut self) {
+        self.bombs += 1;
+    }
+
+    pub fn is_invulnerable(&self, current_time: f64) -> bool {
+        current_time < self.invulnerable_until
+    }
+
+    pub fn is_bombing(&self, current_time: f64) -> bool {
+        current_time < self.bomb_until
+    }
+
+    pub fn collider(&self, sprite_list: &SpriteList, current_time: f64) -> Option<Collider> {
+        // Only a living, vulnerable player can be hit
+        if self.state != PlayerState::Alive || self.is_invulnerable(current_time) {
+            return None;
+        }
+        Some(Collider::new(
+            self.position(sprite_list, current_time),
+            CollisionShape::Circle {
+                radius: self.hitbox_radius(),
+            },
+            CollisionLayer::Player,
+            CollisionMask::none()
+                .with(CollisionLayer::Enemy)
+                .with(CollisionLayer::EnemyShot),
+        ))
+    }
+
+    pub fn hit(&mut self, current_time: f64) -> bool {
+        // Getting hit starts the deathbomb window rather than killing outright
+        if self.state != PlayerState::Alive || self.is_invulnerable(current_time) {
+            return false;
+        }
+        self.state = PlayerState::Dying {
+            hit_time: current_time,
+        };
+        true
+    }
+
+    pub fn power(&self) -> f32 {
+        self.power
+    }
+
+    pub fn set_power(&mut self, power: f32) {
+        self.power = power.max(0.0).min(MAX_POWER);
+    }
+
+    pub fn add_power(&mut self, power: f32) {
+        self.set_power(self.power + power);
+    }
+
     pub fn update(
         &mut self,
         game: &mut Game,
         sprite_list: &mut SpriteList,
+        shot_list: &mut PlayerShotList,
+        playing_field: &PlayingField,
+        delta_time: f64,
+        current_time: f64,
+    ) -> Vec<PlayerEvent> {
+        let mut events = Vec::new();
+        let bomb_pressed = game.input().just_pressed(INPUT_BOMB) && self.bombs > 0;
+        match self.state {
+            PlayerState::GameOver => return events,
+            PlayerState::Dying { hit_time } => {
+                if bomb_pressed {
+                    // Bombing within the window cancels the death
+                    self.state = PlayerState::Alive;
+                    self.bomb(current_time);
+                    events.push(PlayerEvent::Bombed);
+                } else {
+                    if current_time >= hit_time + DEATHBOMB_WINDOW {
+                        self.die(sprite_list, current_time, &mut events);
+                    }
+                    return events;
+                }
+            }
+            PlayerState::Alive => {
+                if bomb_pressed {
+                    self.bomb(current_time);
+                    events.push(PlayerEvent::Bombed);
+                }
+            }
+        }
+        self.update_blink(sprite_list, current_time);
+
+        // Show the hitbox only while focused
+        let focused = game.input().state(INPUT_SLOW);
+        if focused != self.focused {
+            self.focused = focused;
+            let hitbox_scale = if focused {
+                self.character.hitbox_scale()
+            } else {
+                Vector::zero()
+            };
+            self.hitbox_object
+                .set_scale(sprite_list, hitbox_scale, current_time);
+        }
+
+        // Move in 8 directions, at the same speed diagonally as straight
+        let x_axis: f32 = game.input().axis_state(INPUT_RIGHT, INPUT_LEFT);
+        let y_axis: f32 = game.input().axis_state(INPUT_DOWN, INPUT_UP);
+        let direction = if x_axis != 0.0 && y_axis != 0.0 {
+            vector!(x_axis, y_axis) * FRAC_1_SQRT_2
+        } else {
+            vector!(x_axis, y_axis)
+        };
+        let speed = if focused {
+            self.character.focused_speed()
+        } else {
+            self.character.speed()
+        };
+        let position =
+            self.position(sprite_list, current_time) + direction * (speed * delta_time as f32);
+
+        // Keep the whole player sprite inside the playing field
+        let limit = playing_field.size() * 0.5 - self.character.scale() * 0.5;
+        let position = vector!(
+            position[0].max(-limit[0]).min(limit[0]),
+            position[1].max(-limit[1]).min(limit[1])
+        );
+        self.sprite_object
+            .set_position(sprite_list, position, current_time);
+        self.hitbox_object
+            .set_position(sprite_list, position, current_time);
+
+        // Move the options and fire the current power level's shots
+        if let Some(weapon) = self.character.weapon().cloned() {
+            self.update_options(&weapon, sprite_list, position, delta_time, current_time);
+            self.fire_shots(&weapon, game, shot_list, position, current_time);
+        }
+
+        events
+    }
+
+    fn bomb(&mut self, current_time: f64) {
+        self.bombs -= 1;
+        self.bomb_until = current_time + BOMB_DURATION;
+        self.invulnerable_until = self.invulnerable_until.max(self.bomb_until);
+    }
+
+    fn die(
+        &mut self,
+        sprite_list: &mut SpriteList,
+        current_time: f64,
+        events: &mut Vec<PlayerEvent>,
+    ) {
+        events.push(PlayerEvent::Died);
+
+        // Without lives left the player is gone for good
+        if self.lives == 0 {
+            self.state = PlayerState::GameOver;
+            self.sprite_object
+                .set_scale(sprite_list, Vector::zero(), current_time);
+            self.hitbox_object
+                .set_scale(sprite_list, Vector::zero(), current_time);
+            for option_object in self.option_objects.iter_mut() {
+                option_object.set_scale(sprite_list, Vector::zero(), current_time);
+            }
+            events.push(PlayerEvent::GameOver);
+            return;
+        }
+
+        // Respawn at the spawn point, blinking while invulnerable
+        self.lives -= 1;
+        self.bombs = self.character.bombs_per_life();
+        self.add_power(-DEATH_POWER_LOSS);
+        self.state = PlayerState::Alive;
+        self.invulnerable_until = current_time + INVULNERABILITY_DURATION;
+        self.blinking_until = self.invulnerable_until;
+        self.option_positions = vec![self.spawn_position; MAX_OPTIONS];
+        self.sprite_object
+            .set_position(sprite_list, self.spawn_position, current_time);
+        self.hitbox_object
+            .set_position(sprite_list, self.spawn_position, current_time);
+        events.push(PlayerEvent::Respawned);
+    }
+
+    fn update_blink(&mut self, sprite_list: &mut SpriteList, current_time: f64) {
+        // Alternate between shown and hidden until the blinking ends
+        let visible = current_time >= self.blinking_until
+            || ((self.blinking_until - current_time) / BLINK_INTERVAL) as i64 % 2 == 0;
+        if visible != self.visible {
+            self.visible = visible;
+            let scale = if visible {
+                self.character.scale()
+            } else {
+                Vector::zero()
+            };
+            self.sprite_object
+                .set_scale(sprite_list, scale, current_time);
+        }
+    }
+
+    fn update_options(
+        &mut self,
+        weapon: &PlayerWeapon,
+        sprite_list: &mut SpriteList,
+        position: Vec2f,
         delta_time: f64,
         current_time: f64,
     ) {
-        if game.input().just_pressed(INPUT_LEFT) {
-            self.sprite_object.set_velocity(sprite_list, vector!(0.3, 0.0), current_time);
+        // Changing power level shows or hides options and restarts the fire timers
+        let level_idx = weapon.level_idx(self.power);
+        let level = weapon.level(level_idx);
+        if self.level_idx != Some(level_idx) {
+            self.level_idx = Some(level_idx);
+            self.fire_timers = [
+                vec![current_time; level.shots(false).len()],
+                vec![current_time; level.shots(true).len()],
+            ];
+            for (idx, option_object) in self.option_objects.iter_mut().enumerate() {
+                let option_scale = if idx < level.option_count() {
+                    weapon.option_scale()
+                } else {
+                    Vector::zero()
+                };
+                option_object.set_scale(sprite_list, option_scale, current_time);
+            }
         }
-        if game.input().just_pressed(INPUT_RIGHT) {
-            self.sprite_object.set_velocity(sprite_list, vector!(-0.3, 0.0), current_time);
+
+        // Options ease toward their place in the current formation
+        let follow = (weapon.option_follow_rate() * delta_time as f32).min(1.0);
+        for (idx, offset) in level.formation(self.focused).iter().enumerate() {
+            let target = position + *offset;
+            let option_position =
+                self.option_positions[idx] + (target - self.option_positions[idx]) * follow;
+            self.option_positions[idx] = option_position;
+            self.option_objects[idx].set_position(sprite_list, option_position, current_time);
+        }
+    }
+
+    fn fire_shots(
+        &mut self,
+        weapon: &PlayerWeapon,
+        game: &Game,
+        shot_list: &mut PlayerShotList,
+        position: Vec2f,
+        current_time: f64,
+    ) {
+        let level = weapon.level(weapon.level_idx(self.power));
+        let fire_timers = &mut self.fire_timers[self.focused as usize];
+        for (shot, next_fire_time) in level.shots(self.focused).iter().zip(fire_timers.iter_mut()) {
+            // Shots held down fire at their own rate, and fire immediately when first pressed
+            if !game.input().state(shot.input()) {
+                *next_fire_time = (*next_fire_time).min(current_time);
+                continue;
+            }
+            if current_time < *next_fire_time {
+                continue;
+            }
+            *next_fire_time = current_time + shot.fire_interval();
+
+            match shot.source() {
+                ShotSource::Player => shot_list.fire(shot, position, current_time),
+                ShotSource::Options => {
+                    for option_position in self.option_positions[..level.option_count()].iter() {
+                        shot_list.fire(shot, *option_position, current_time);
+                    }
+                }
+            }
         }
     }
-}
\ No newline at end of file
+}
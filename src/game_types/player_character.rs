@@ -0,0 +1,97 @@
+use crate::*;
+use fennec_algebra::*;
+use std::rc::Rc;
+
+#[derive(Clone, Debug)]
+pub struct PlayerCharacter {
+    scale: Vec2f,
+    sprite_rectangle: Vec4f,
+    speed: f32,
+    focused_speed: f32,
+    hitbox_rectangle: Vec4f,
+    hitbox_scale: Vec2f,
+    hitbox_radius: f32,
+    bombs_per_life: u32,
+    weapon: Option<Rc<PlayerWeapon>>,
+}
+
+impl PlayerCharacter {
+    pub fn new(scale: Vec2f, sprite_rectangle: Vec4f) -> Self {
+        Self {
+            scale,
+            sprite_rectangle,
+            speed: 1.2,
+            focused_speed: 0.5,
+            hitbox_rectangle: sprite_rectangle,
+            hitbox_scale: vector!(0.05, 0.05),
+            hitbox_radius: 0.01,
+            bombs_per_life: 3,
+            weapon: None,
+        }
+    }
+
+    pub fn with_speeds(mut self, speed: f32, focused_speed: f32) -> Self {
+        if DEBUG && (speed < 0.0 || focused_speed < 0.0) {
+            panic!("Player speeds must not be negative");
+        }
+        self.speed = speed;
+        self.focused_speed = focused_speed;
+        self
+    }
+
+    pub fn with_hitbox(mut self, rectangle: Vec4f, scale: Vec2f, radius: f32) -> Self {
+        if DEBUG && radius < 0.0 {
+            panic!("Hitbox radius must not be negative");
+        }
+        self.hitbox_rectangle = rectangle;
+        self.hitbox_scale = scale;
+        self.hitbox_radius = radius;
+        self
+    }
+
+    pub fn with_bombs_per_life(mut self, bombs_per_life: u32) -> Self {
+        self.bombs_per_life = bombs_per_life;
+        self
+    }
+
+    pub fn with_weapon(mut self, weapon: Rc<PlayerWeapon>) -> Self {
+        self.weapon = Some(weapon);
+        self
+    }
+
+    pub fn scale(&self) -> Vec2f {
+        self.scale
+    }
+
+    pub fn sprite_rectangle(&self) -> Vec4f {
+        self.sprite_rectangle
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn focused_speed(&self) -> f32 {
+        self.focused_speed
+    }
+
+    pub fn hitbox_rectangle(&self) -> Vec4f {
+        self.hitbox_rectangle
+    }
+
+    pub fn hitbox_scale(&self) -> Vec2f {
+        self.hitbox_scale
+    }
+
+    pub fn hitbox_radius(&self) -> f32 {
+        self.hitbox_radius
+    }
+
+    pub fn bombs_per_life(&self) -> u32 {
+        self.bombs_per_life
+    }
+
+    pub fn weapon(&self) -> Option<&Rc<PlayerWeapon>> {
+        self.weapon.as_ref()
+    }
+}
@@ -6,34 +6,39 @@ pub struct PlayerList {
     max_players: usize,
     players: Vec<Player>,
     sprite_list: SpriteList,
+    shot_list: PlayerShotList,
     start_position: Vec2f,
 }
 
 impl PlayerList {
-    pub fn new(max_players: usize, start_position: Vec2f) -> Self {
+    pub fn new(
+        texture: Rc<Texture<{ TextureType::Texture2D }>>,
+        sprite_pipeline: Rc<Pipeline>,
+        max_players: usize,
+        start_position: Vec2f,
+        max_shots: GLsizeiptr,
+        playing_field: &PlayingField,
+    ) -> Self {
         if DEBUG && max_players == 0 {
             panic!("Max players must be greater than 0");
         }
 
-        let texture = Texture::from_file(
-            path!("Game", "Textures", "pl00.png"),
-            image::ImageFormat::Png,
-        );
-
         Self {
             max_players,
             players: Vec::new(),
             sprite_list: SpriteList::new(
-                SpriteMaterial::new(),
+                SpriteMaterial::new(sprite_pipeline.clone()),
                 SpriteAnimator::new(),
-                Rc::new(texture),
-                max_players as GLsizeiptr,
+                texture.clone(),
+                // Each player has a sprite for itself, one for its hitbox and one for each option
+                max_players as GLsizeiptr * (2 + MAX_OPTIONS as GLsizeiptr),
             ),
+            shot_list: PlayerShotList::new(texture, sprite_pipeline, max_shots, playing_field),
             start_position,
         }
     }
 
-    pub fn add_player(&mut self, scale: Vec2f, sprite_rectangle: Vec4f, current_time: f64) {
+    pub fn add_player(&mut self, character: PlayerCharacter, current_time: f64) {
         if DEBUG && self.players.len() >= self.max_players {
             panic!("Maximum number of players already reached");
         }
@@ -41,20 +46,86 @@ impl PlayerList {
         let player = Player::new(
             &mut self.sprite_list,
             self.start_position,
-            scale,
-            sprite_rectangle,
+            character,
             current_time,
         );
         self.players.push(player);
     }
 
-    pub fn update(&mut self, game: &mut Game, delta_time: f64, current_time: f64) {
-        for player in self.players.iter_mut() {
-            player.update(game, &mut self.sprite_list, delta_time, current_time);
+    pub fn player_position(&self, idx: usize, current_time: f64) -> Option<Vec2f> {
+        self.players
+            .get(idx)
+            .map(|player| player.position(&self.sprite_list, current_time))
+    }
+
+    pub fn player(&self, idx: usize) -> Option<&Player> {
+        self.players.get(idx)
+    }
+
+    pub fn player_mut(&mut self, idx: usize) -> Option<&mut Player> {
+        self.players.get_mut(idx)
+    }
+
+    pub fn shot_list(&self) -> &PlayerShotList {
+        &self.shot_list
+    }
+
+    pub fn shot_list_mut(&mut self) -> &mut PlayerShotList {
+        &mut self.shot_list
+    }
+
+    pub fn update(
+        &mut self,
+        game: &mut Game,
+        playing_field: &PlayingField,
+        delta_time: f64,
+        current_time: f64,
+    ) -> Vec<(usize, PlayerEvent)> {
+        let mut events = Vec::new();
+        for (idx, player) in self.players.iter_mut().enumerate() {
+            let player_events = player.update(
+                game,
+                &mut self.sprite_list,
+                &mut self.shot_list,
+                playing_field,
+                delta_time,
+                current_time,
+            );
+            events.extend(player_events.into_iter().map(|event| (idx, event)));
         }
+        events
+    }
+
+    pub fn player_collider(&self, idx: usize, current_time: f64) -> Option<Collider> {
+        self.players
+            .get(idx)
+            .and_then(|player| player.collider(&self.sprite_list, current_time))
+    }
+
+    pub fn hit_player(&mut self, idx: usize, current_time: f64) -> bool {
+        self.players[idx].hit(current_time)
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn is_bombing(&self, current_time: f64) -> bool {
+        self.players
+            .iter()
+            .any(|player| player.is_bombing(current_time))
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        !self.players.is_empty()
+            && self
+                .players
+                .iter()
+                .all(|player| player.state() == PlayerState::GameOver)
     }
 
-    pub fn draw(&mut self, gfx: &mut GFX, delta_time: f64) {
-        self.sprite_list.draw(gfx, delta_time);
+    pub fn draw(&mut self, gfx: &mut GFX, delta_time: f64, current_time: f64) {
+        self.shot_list.draw(gfx, delta_time, current_time);
+        self.sprite_list.draw(gfx, delta_time, current_time);
     }
 }
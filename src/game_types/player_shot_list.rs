@@ -0,0 +1,111 @@
+use crate::*;
+use fennec_algebra::*;
+use std::rc::Rc;
+
+const SHOT_CULL_MARGIN: f32 = 0.1;
+
+#[derive(Debug)]
+struct FiredShot {
+    sprite: SpriteReference,
+    damage: f32,
+    hitbox_radius: f32,
+}
+
+pub struct PlayerShotList {
+    sprite_list: SpriteList,
+    shots: Vec<FiredShot>,
+}
+
+impl PlayerShotList {
+    pub fn new(
+        texture: Rc<Texture<{ TextureType::Texture2D }>>,
+        sprite_pipeline: Rc<Pipeline>,
+        max_shots: GLsizeiptr,
+        playing_field: &PlayingField,
+    ) -> Self {
+        let mut sprite_list = SpriteList::new(
+            SpriteMaterial::new(sprite_pipeline),
+            SpriteAnimator::new(),
+            texture,
+            max_shots,
+        );
+        sprite_list.set_cull_bounds(playing_field, SHOT_CULL_MARGIN);
+        Self {
+            sprite_list,
+            shots: Vec::new(),
+        }
+    }
+
+    pub fn fire(&mut self, shot: &ShotType, origin: Vec2f, current_time: f64) {
+        let actor = SpriteActorVertex::new(
+            self.sprite_list.rectangle_to_texcoord(shot.rectangle()),
+            current_time,
+        )
+        .with_position(origin + shot.offset())
+        .with_velocity(Vec2f::from_angle(shot.angle()) * shot.speed())
+        .with_scale(shot.scale())
+        .with_rotation(shot.angle())
+        .with_hitbox_radius(shot.hitbox_radius());
+
+        // Shots fired while the pool is full are skipped
+        let sprite = match self.sprite_list.try_add_sprite(actor) {
+            Some(sprite) => sprite,
+            None => return,
+        };
+        self.shots.push(FiredShot {
+            sprite,
+            damage: shot.damage(),
+            hitbox_radius: shot.hitbox_radius(),
+        });
+    }
+
+    pub fn shot_count(&self) -> usize {
+        self.shots.len()
+    }
+
+    pub fn colliders(&self, current_time: f64) -> Vec<(SpriteReference, Collider)> {
+        self.shots
+            .iter()
+            .map(|shot| {
+                let position = self
+                    .sprite_list
+                    .sprite_actor(&shot.sprite)
+                    .expect("Player shot was lost somehow")
+                    .position(current_time);
+                let collider = Collider::new(
+                    position,
+                    CollisionShape::Circle {
+                        radius: shot.hitbox_radius,
+                    },
+                    CollisionLayer::PlayerShot,
+                    CollisionMask::none().with(CollisionLayer::Enemy),
+                );
+                (shot.sprite, collider)
+            })
+            .collect()
+    }
+
+    pub fn take_shot(&mut self, sprite: &SpriteReference) -> Option<f32> {
+        // Remove the shot and return its damage, unless something else already took it
+        let idx = self.shots.iter().position(|shot| shot.sprite == *sprite)?;
+        let shot = self.shots.swap_remove(idx);
+        self.sprite_list.remove_sprite(shot.sprite);
+        Some(shot.damage)
+    }
+
+    pub fn clear(&mut self) {
+        for shot in self.shots.drain(..) {
+            self.sprite_list.remove_sprite(shot.sprite);
+        }
+    }
+
+    pub fn draw(&mut self, gfx: &mut GFX, delta_time: f64, current_time: f64) {
+        self.sprite_list.draw(gfx, delta_time, current_time);
+
+        // Forget the shots that flew off the field
+        let culled = self.sprite_list.take_culled();
+        if !culled.is_empty() {
+            self.shots.retain(|shot| !culled.contains(&shot.sprite));
+        }
+    }
+}
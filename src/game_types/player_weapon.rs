@@ -0,0 +1,261 @@
+use crate::*;
+use fennec_algebra::*;
+use std::f32::consts::FRAC_PI_2;
+
+pub const MAX_POWER: f32 = 4.0;
+pub const MAX_OPTIONS: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ShotSource {
+    Player,
+    Options,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ShotType {
+    source: ShotSource,
+    input: usize,
+    offset: Vec2f,
+    angle: f32,
+    speed: f32,
+    damage: f32,
+    fire_interval: f64,
+    rectangle: Vec4f,
+    scale: Vec2f,
+    hitbox_radius: f32,
+}
+
+impl ShotType {
+    pub fn new(rectangle: Vec4f, scale: Vec2f, hitbox_radius: f32) -> Self {
+        Self {
+            source: ShotSource::Player,
+            input: INPUT_SHOOT1,
+            offset: Vector::zero(),
+            angle: -FRAC_PI_2,
+            speed: 4.0,
+            damage: 1.0,
+            fire_interval: 1.0 / 15.0,
+            rectangle,
+            scale,
+            hitbox_radius,
+        }
+    }
+
+    pub fn with_source(mut self, source: ShotSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn with_input(mut self, input: usize) -> Self {
+        self.input = input;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: Vec2f) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_angle(mut self, angle: f32) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn with_damage(mut self, damage: f32) -> Self {
+        if DEBUG && damage < 0.0 {
+            panic!("Shot damage must not be negative");
+        }
+        self.damage = damage;
+        self
+    }
+
+    pub fn with_fire_interval(mut self, fire_interval: f64) -> Self {
+        if DEBUG && fire_interval <= 0.0 {
+            panic!("Fire interval must be greater than 0");
+        }
+        self.fire_interval = fire_interval;
+        self
+    }
+
+    pub fn source(&self) -> ShotSource {
+        self.source
+    }
+
+    pub fn input(&self) -> usize {
+        self.input
+    }
+
+    pub fn offset(&self) -> Vec2f {
+        self.offset
+    }
+
+    pub fn angle(&self) -> f32 {
+        self.angle
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn damage(&self) -> f32 {
+        self.damage
+    }
+
+    pub fn fire_interval(&self) -> f64 {
+        self.fire_interval
+    }
+
+    pub fn rectangle(&self) -> Vec4f {
+        self.rectangle
+    }
+
+    pub fn scale(&self) -> Vec2f {
+        self.scale
+    }
+
+    pub fn hitbox_radius(&self) -> f32 {
+        self.hitbox_radius
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PowerLevel {
+    min_power: f32,
+    unfocused_shots: Vec<ShotType>,
+    focused_shots: Vec<ShotType>,
+    unfocused_formation: Vec<Vec2f>,
+    focused_formation: Vec<Vec2f>,
+}
+
+impl PowerLevel {
+    pub fn new(min_power: f32) -> Self {
+        Self {
+            min_power,
+            unfocused_shots: Vec::new(),
+            focused_shots: Vec::new(),
+            unfocused_formation: Vec::new(),
+            focused_formation: Vec::new(),
+        }
+    }
+
+    pub fn with_unfocused_shot(mut self, shot: ShotType) -> Self {
+        self.unfocused_shots.push(shot);
+        self
+    }
+
+    pub fn with_focused_shot(mut self, shot: ShotType) -> Self {
+        self.focused_shots.push(shot);
+        self
+    }
+
+    pub fn with_options(
+        mut self,
+        unfocused_formation: Vec<Vec2f>,
+        focused_formation: Vec<Vec2f>,
+    ) -> Self {
+        if DEBUG && unfocused_formation.len() != focused_formation.len() {
+            panic!("Focused and unfocused formations must have the same number of options");
+        }
+        if DEBUG && unfocused_formation.len() > MAX_OPTIONS {
+            panic!("No more than {} options are allowed", MAX_OPTIONS);
+        }
+        self.unfocused_formation = unfocused_formation;
+        self.focused_formation = focused_formation;
+        self
+    }
+
+    pub fn min_power(&self) -> f32 {
+        self.min_power
+    }
+
+    pub fn shots(&self, focused: bool) -> &[ShotType] {
+        if focused {
+            &self.focused_shots
+        } else {
+            &self.unfocused_shots
+        }
+    }
+
+    pub fn formation(&self, focused: bool) -> &[Vec2f] {
+        if focused {
+            &self.focused_formation
+        } else {
+            &self.unfocused_formation
+        }
+    }
+
+    pub fn option_count(&self) -> usize {
+        self.unfocused_formation.len()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayerWeapon {
+    levels: Vec<PowerLevel>,
+    option_rectangle: Vec4f,
+    option_scale: Vec2f,
+    option_follow_rate: f32,
+}
+
+impl PlayerWeapon {
+    pub fn new(option_rectangle: Vec4f, option_scale: Vec2f) -> Self {
+        Self {
+            levels: Vec::new(),
+            option_rectangle,
+            option_scale,
+            option_follow_rate: 12.0,
+        }
+    }
+
+    pub fn with_level(mut self, level: PowerLevel) -> Self {
+        if DEBUG && self.levels.is_empty() && level.min_power() != 0.0 {
+            panic!("The first power level must start at 0 power");
+        }
+        if DEBUG
+            && self
+                .levels
+                .last()
+                .map_or(false, |last| last.min_power() >= level.min_power())
+        {
+            panic!("Power levels must be added in order of increasing power");
+        }
+        self.levels.push(level);
+        self
+    }
+
+    pub fn with_option_follow_rate(mut self, option_follow_rate: f32) -> Self {
+        self.option_follow_rate = option_follow_rate;
+        self
+    }
+
+    pub fn level_idx(&self, power: f32) -> usize {
+        if DEBUG && self.levels.is_empty() {
+            panic!("Weapon has no power levels");
+        }
+        self.levels
+            .iter()
+            .rposition(|level| level.min_power() <= power)
+            .unwrap_or(0)
+    }
+
+    pub fn level(&self, idx: usize) -> &PowerLevel {
+        &self.levels[idx]
+    }
+
+    pub fn option_rectangle(&self) -> Vec4f {
+        self.option_rectangle
+    }
+
+    pub fn option_scale(&self) -> Vec2f {
+        self.option_scale
+    }
+
+    pub fn option_follow_rate(&self) -> f32 {
+        self.option_follow_rate
+    }
+}
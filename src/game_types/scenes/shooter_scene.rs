@@ -1,28 +1,808 @@
 use crate::*;
 use fennec_algebra::*;
 use glfw::Key;
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::FRAC_PI_2;
+use std::rc::Rc;
 
 const STARTING_FIELD_SIZE: Vec2f = vector!(2.0, 2.0);
 const STARTING_FIELD_VIEWPORT: Vec4f = vector!(0.05, 0.05, 0.75, 0.9);
+const HUD_VIEWPORT: Vec4f = vector!(0.82, 0.05, 0.16, 0.9);
 const PLAYER_SPAWN_POINT: Vec2f = vector!(0.0, 0.8);
+const MAX_LASERS: usize = 64;
+const MAX_LASER_POINTS: usize = 64;
+const MAX_ENEMY_BULLETS: GLsizeiptr = 4096;
+const MAX_PLAYER_SHOTS: GLsizeiptr = 512;
+const MAX_ENEMIES: usize = 128;
+const MAX_ITEMS: GLsizeiptr = 1024;
+const AUTO_COLLECT_LINE: f32 = -0.5;
+const POWER_ITEM_VALUE: f32 = 0.05;
+const POINT_ITEM_VALUE: u64 = 10000;
+const BULLET_CULL_MARGIN: f32 = 0.2;
+const BOMB_CLEAR_POINTS: u64 = 10;
+const GRAZE_RADIUS: f32 = 0.06;
+const MAX_BULLET_HITS: usize = 256;
+const COLLISION_CELL_SIZE: f32 = 0.25;
+const EXTEND_THRESHOLDS: [u64; 4] = [10_000_000, 25_000_000, 50_000_000, 80_000_000];
+const HIGH_SCORE_NAME: &str = "Player";
+// Built in copies of the sprite shaders, used when the ones in the assets can't be loaded
+const FALLBACK_SPRITE_VERTEX_SHADER: &str = include_str!("../../../Game/Shaders/sprite.vert");
+const FALLBACK_SPRITE_FRAGMENT_SHADER: &str = include_str!("../../../Game/Shaders/sprite.frag");
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum CollisionOwner {
+    Player(usize),
+    Collector,
+    Laser,
+    Enemy(EnemyId),
+    PlayerShot(SpriteReference),
+    Item(SpriteReference),
+}
+
+#[derive(Default)]
+struct CollisionOutcome {
+    enemy_damage: HashMap<EnemyId, f32>,
+    collected_items: HashSet<SpriteReference>,
+}
 
 pub struct ShooterScene {
     playing_field: PlayingField,
+    sprite_pipeline: Rc<Pipeline>,
+    collision_world: CollisionWorld,
     player_list: PlayerList,
+    laser_list: LaserList,
+    enemy_list: EnemyList,
+    hud: Hud,
+    item_list: ItemList,
+    enemy_bullets: SpriteList,
+    enemy_bullet_sprites: Vec<SpriteReference>,
+    bullet_collider: SpriteCollider,
+    grazed_bullets: HashSet<SpriteReference>,
+    emitters: Vec<BulletEmitter>,
+    next_emitter_seed: u64,
+    patterns: HashMap<String, Rc<BulletPattern>>,
+    stage_cues: Vec<StageCue>,
+    stage_source: Option<(String, FileWatch, f64)>,
+    background: Option<(String, SpriteList)>,
+    music: Option<String>,
+    score: Score,
+    high_scores: HighScoreTable,
+    game_over: bool,
 }
 
 impl ShooterScene {
-    pub fn new(current_time: f64) -> Self {
+    pub fn new(assets: &mut Assets, current_time: f64) -> Self {
         let playing_field = PlayingField::new(STARTING_FIELD_SIZE, STARTING_FIELD_VIEWPORT);
-        let mut player_list = PlayerList::new(1, PLAYER_SPAWN_POINT * playing_field.size() * 0.5);
+
+        // Missing or broken assets are reported and replaced, so the game still starts
+        let laser_texture = assets.texture("pl00").unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            Rc::new(Texture::placeholder())
+        });
+        let sprite_pipeline = assets.pipeline("sprite", "sprite").unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            Rc::new(Pipeline::new(vec![
+                Program::new(ShaderStage::Vertex, FALLBACK_SPRITE_VERTEX_SHADER),
+                Program::new(ShaderStage::Fragment, FALLBACK_SPRITE_FRAGMENT_SHADER),
+            ]))
+        });
+        let mut player_list = PlayerList::new(
+            laser_texture.clone(),
+            sprite_pipeline.clone(),
+            1,
+            PLAYER_SPAWN_POINT * playing_field.size() * 0.5,
+            MAX_PLAYER_SHOTS,
+            &playing_field,
+        );
         player_list.add_player(
-            vector!(0.15, 0.15),
-            vector!(0.0, 0.0, 64.0, 64.0),
+            PlayerCharacter::new(vector!(0.15, 0.15), vector!(0.0, 0.0, 64.0, 64.0))
+                .with_speeds(1.2, 0.5)
+                .with_hitbox(vector!(64.0, 0.0, 64.0, 64.0), vector!(0.05, 0.05), 0.01)
+                .with_weapon(Rc::new(Self::default_weapon())),
             current_time,
         );
-        Self {
+        let laser_list = LaserList::new(laser_texture.clone(), MAX_LASERS, MAX_LASER_POINTS);
+        let enemy_list =
+            EnemyList::new(laser_texture.clone(), sprite_pipeline.clone(), MAX_ENEMIES);
+        let hud = Hud::new(HUD_VIEWPORT, laser_texture.clone(), sprite_pipeline.clone());
+        let item_list = ItemList::new(
+            laser_texture.clone(),
+            sprite_pipeline.clone(),
+            // Power, point, life and bomb items
+            [
+                ItemStyle::new(vector!(0.0, 224.0, 16.0, 16.0), vector!(0.04, 0.04)),
+                ItemStyle::new(vector!(16.0, 224.0, 16.0, 16.0), vector!(0.04, 0.04)),
+                ItemStyle::new(vector!(32.0, 224.0, 16.0, 16.0), vector!(0.06, 0.06)),
+                ItemStyle::new(vector!(48.0, 224.0, 16.0, 16.0), vector!(0.06, 0.06)),
+            ],
+            MAX_ITEMS,
+            &playing_field,
+            AUTO_COLLECT_LINE * playing_field.size()[1] * 0.5,
+        );
+        let mut enemy_bullets = SpriteList::new(
+            SpriteMaterial::new(sprite_pipeline.clone()),
+            SpriteAnimator::new(),
+            laser_texture,
+            MAX_ENEMY_BULLETS,
+        );
+        enemy_bullets.set_cull_bounds(&playing_field, BULLET_CULL_MARGIN);
+        enemy_bullets.enable_readback();
+        let bullet_collider =
+            SpriteCollider::with_backend(enemy_bullets.animator_backend(), MAX_BULLET_HITS);
+        let collision_world = CollisionWorld::new(&playing_field, COLLISION_CELL_SIZE);
+        let mut scene = Self {
             playing_field,
+            sprite_pipeline,
+            collision_world,
             player_list,
+            laser_list,
+            enemy_list,
+            hud,
+            item_list,
+            enemy_bullets,
+            enemy_bullet_sprites: Vec::new(),
+            bullet_collider,
+            grazed_bullets: HashSet::new(),
+            emitters: Vec::new(),
+            next_emitter_seed: 0,
+            patterns: HashMap::new(),
+            stage_cues: Vec::new(),
+            stage_source: None,
+            background: None,
+            music: None,
+            score: Score::new(Difficulty::Normal, EXTEND_THRESHOLDS.to_vec()),
+            high_scores: HighScoreTable::load_or_default(&HighScoreTable::default_path()),
+            game_over: false,
+        };
+
+        // A broken stage file shouldn't stop the game from starting
+        scene.register_default_patterns();
+        if let Err(error) = scene.load_stage(assets, "stage1", current_time) {
+            eprintln!("{}", error);
+        }
+        scene
+    }
+
+    fn register_default_patterns(&mut self) {
+        let small = self.bullet_style(vector!(64.0, 224.0, 16.0, 16.0), vector!(0.04, 0.04), 0.012);
+        let large = self.bullet_style(vector!(80.0, 224.0, 32.0, 32.0), vector!(0.08, 0.08), 0.025);
+        self.register_pattern(
+            "ring",
+            BulletPattern::new(EmitterShape::Ring, small)
+                .with_count(16)
+                .with_speed(0.5, 0.5),
+        );
+        self.register_pattern(
+            "aimed",
+            BulletPattern::new(EmitterShape::Aimed { arc: 0.6 }, small)
+                .with_count(5)
+                .with_speed(0.6, 0.9)
+                .with_waves(3, 0.15),
+        );
+        self.register_pattern(
+            "spiral",
+            BulletPattern::new(EmitterShape::Spiral { turn_per_wave: 0.2 }, large)
+                .with_count(4)
+                .with_speed(0.4, 0.4)
+                .with_waves(12, 0.1),
+        );
+    }
+
+    pub fn register_pattern(&mut self, name: &str, pattern: BulletPattern) -> Rc<BulletPattern> {
+        let pattern = Rc::new(pattern);
+        self.patterns.insert(name.to_string(), pattern.clone());
+        pattern
+    }
+
+    pub fn pattern(&self, name: &str) -> Option<&Rc<BulletPattern>> {
+        self.patterns.get(name)
+    }
+
+    pub fn load_stage(
+        &mut self,
+        assets: &mut Assets,
+        name: &str,
+        current_time: f64,
+    ) -> Result<(), AssetError> {
+        let stage = assets.stage(name, &self.patterns, self.playing_field.size() * 0.5)?;
+        self.start_stage(&stage, current_time);
+        self.stage_source = Some((name.to_string(), FileWatch::new(stage.file()), current_time));
+        Ok(())
+    }
+
+    pub fn start_stage(&mut self, stage: &Stage, current_time: f64) {
+        self.stage_source = None;
+        self.schedule_stage(stage, current_time, current_time);
+    }
+
+    fn schedule_stage(&mut self, stage: &Stage, start_time: f64, current_time: f64) {
+        // The stage replaces whatever was scheduled before, skipping anything already in the past
+        self.enemy_list.clear_script();
+        for wave in stage.waves() {
+            for spawn in wave.spawns(start_time) {
+                if spawn.time() >= current_time {
+                    self.enemy_list.schedule_spawn(spawn);
+                }
+            }
+        }
+        self.stage_cues = stage
+            .cues()
+            .iter()
+            .map(|cue| cue.offset(start_time))
+            .filter(|cue| cue.time() >= current_time)
+            .collect();
+    }
+
+    fn reload_stage(&mut self, assets: &mut Assets, current_time: f64) {
+        let (name, start_time) = match self.stage_source.as_ref() {
+            Some((name, source, start_time)) if source.changed() => (name.clone(), *start_time),
+            _ => return,
+        };
+
+        // Drop the cached stage so the edited file is parsed again. Keep playing the old version of
+        // the stage if the new one has errors
+        assets.forget_stage(&name);
+        match assets.stage(&name, &self.patterns, self.playing_field.size() * 0.5) {
+            Ok(stage) => self.schedule_stage(&stage, start_time, current_time),
+            Err(error) => eprintln!("\x1B[35m{}\x1B[37m", error),
+        }
+    }
+
+    pub fn background(&self) -> Option<&str> {
+        self.background.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    pub fn music(&self) -> Option<&str> {
+        self.music.as_deref()
+    }
+
+    fn show_background(&mut self, assets: &mut Assets, name: &str, current_time: f64) {
+        // A background that can't be loaded is reported and leaves the previous one up
+        let texture = match assets.texture(name) {
+            Ok(texture) => texture,
+            Err(error) => {
+                eprintln!("\x1B[35m{}\x1B[37m", error);
+                return;
+            }
+        };
+
+        // The whole texture is stretched over the playing field
+        let texture_size = texture.size();
+        let mut sprite_list = SpriteList::new(
+            SpriteMaterial::new(self.sprite_pipeline.clone()),
+            SpriteAnimator::new(),
+            texture,
+            1,
+        );
+        let rectangle = vector!(0.0, 0.0, texture_size[0] as f32, texture_size[1] as f32);
+        sprite_list.add_sprite(
+            SpriteActorVertex::new(sprite_list.rectangle_to_texcoord(rectangle), current_time)
+                .with_scale(self.playing_field.size()),
+        );
+        self.background = Some((name.to_string(), sprite_list));
+    }
+
+    fn update_stage_cues(&mut self, assets: &mut Assets, current_time: f64) {
+        // Cues are sorted by time, so only the front of the list can be due
+        let due = self
+            .stage_cues
+            .iter()
+            .take_while(|cue| cue.time() <= current_time)
+            .count();
+        for cue in self.stage_cues.drain(..due).collect::<Vec<_>>() {
+            match cue.kind() {
+                StageCueKind::Background(asset) => {
+                    self.show_background(assets, asset, current_time)
+                }
+                StageCueKind::Music(asset) => self.music = Some(asset.clone()),
+            }
+        }
+    }
+
+    fn default_weapon() -> PlayerWeapon {
+        // A straight main shot and a slower, heavier shot on the second shoot input, with options
+        // adding more shots as power goes up
+        let main_shot = ShotType::new(vector!(0.0, 144.0, 64.0, 16.0), vector!(0.12, 0.03), 0.03)
+            .with_damage(2.0);
+        let heavy_shot = main_shot
+            .with_input(INPUT_SHOOT2)
+            .with_speed(2.5)
+            .with_damage(6.0)
+            .with_fire_interval(1.0 / 4.0);
+        let option_shot = ShotType::new(vector!(0.0, 160.0, 64.0, 16.0), vector!(0.1, 0.025), 0.02)
+            .with_source(ShotSource::Options)
+            .with_fire_interval(1.0 / 10.0);
+        let spread_shot = |angle: f32| option_shot.with_angle(-FRAC_PI_2 + angle);
+        PlayerWeapon::new(vector!(128.0, 0.0, 32.0, 32.0), vector!(0.06, 0.06))
+            .with_level(
+                PowerLevel::new(0.0)
+                    .with_unfocused_shot(main_shot)
+                    .with_unfocused_shot(heavy_shot)
+                    .with_focused_shot(main_shot)
+                    .with_focused_shot(heavy_shot),
+            )
+            .with_level(
+                PowerLevel::new(1.0)
+                    .with_unfocused_shot(main_shot)
+                    .with_unfocused_shot(spread_shot(0.0))
+                    .with_unfocused_shot(heavy_shot)
+                    .with_focused_shot(main_shot)
+                    .with_focused_shot(heavy_shot)
+                    .with_focused_shot(option_shot)
+                    .with_options(vec![vector!(0.0, -0.12)], vec![vector!(0.0, -0.08)]),
+            )
+            .with_level(
+                PowerLevel::new(2.0)
+                    .with_unfocused_shot(main_shot)
+                    .with_unfocused_shot(spread_shot(-0.2))
+                    .with_unfocused_shot(spread_shot(0.2))
+                    .with_unfocused_shot(heavy_shot)
+                    .with_focused_shot(main_shot)
+                    .with_focused_shot(heavy_shot)
+                    .with_focused_shot(option_shot)
+                    .with_options(
+                        vec![vector!(-0.12, 0.0), vector!(0.12, 0.0)],
+                        vec![vector!(-0.05, -0.08), vector!(0.05, -0.08)],
+                    ),
+            )
+            .with_level(
+                PowerLevel::new(3.0)
+                    .with_unfocused_shot(main_shot)
+                    .with_unfocused_shot(spread_shot(-0.3))
+                    .with_unfocused_shot(spread_shot(0.0))
+                    .with_unfocused_shot(spread_shot(0.3))
+                    .with_unfocused_shot(heavy_shot)
+                    .with_focused_shot(main_shot)
+                    .with_focused_shot(heavy_shot)
+                    .with_focused_shot(option_shot)
+                    .with_options(
+                        vec![
+                            vector!(-0.15, 0.02),
+                            vector!(-0.08, -0.06),
+                            vector!(0.08, -0.06),
+                            vector!(0.15, 0.02),
+                        ],
+                        vec![
+                            vector!(-0.08, -0.06),
+                            vector!(-0.03, -0.1),
+                            vector!(0.03, -0.1),
+                            vector!(0.08, -0.06),
+                        ],
+                    ),
+            )
+    }
+
+    pub fn spawn_emitter(
+        &mut self,
+        pattern: Rc<BulletPattern>,
+        origin: Vec2f,
+        base_angle: f32,
+        current_time: f64,
+    ) {
+        // Emitters are seeded in spawn order so a stage plays out the same way every time
+        let seed = self.next_emitter_seed;
+        self.next_emitter_seed += 1;
+        self.emitters.push(BulletEmitter::new(
+            pattern,
+            origin,
+            base_angle,
+            seed,
+            current_time,
+        ));
+    }
+
+    pub fn bullet_style(&self, rectangle: Vec4f, scale: Vec2f, hitbox_radius: f32) -> BulletStyle {
+        BulletStyle::new(
+            self.enemy_bullets.rectangle_to_texcoord(rectangle),
+            scale,
+            hitbox_radius,
+        )
+    }
+
+    pub fn register_motion_program(&mut self, program: &MotionProgram) -> MotionProgramReference {
+        self.enemy_bullets.register_motion_program(program)
+    }
+
+    fn update_emitters(&mut self, current_time: f64) {
+        // Aim at the player, or straight down if there is none
+        let aim_target = self
+            .player_list
+            .player_position(0, current_time)
+            .unwrap_or_else(|| vector!(0.0, self.playing_field.size()[1]));
+
+        // Motion programs aim and home at the same target, and bounce off the edges of the field
+        self.enemy_bullets.set_aim_target(aim_target);
+        self.enemy_bullets
+            .set_motion_bounds(self.playing_field.size() * 0.5);
+
+        // Fire bullets from every emitter and drop the ones that are done
+        for emitter in self.emitters.iter_mut() {
+            let spawned = emitter.update(&mut self.enemy_bullets, aim_target, current_time);
+            self.enemy_bullet_sprites.extend(spawned);
+        }
+        self.emitters.retain(|emitter| !emitter.is_finished());
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn_straight_laser(
+        &mut self,
+        rectangle: Vec4f,
+        width: f32,
+        timing: LaserTiming,
+        origin: Vec2f,
+        angle: f32,
+        length: f32,
+        current_time: f64,
+    ) -> LaserReference {
+        let rectangle = self.laser_list.rectangle_to_texcoord(rectangle);
+        self.laser_list.add_laser(Laser::straight(
+            rectangle,
+            width,
+            timing,
+            origin,
+            angle,
+            length,
+            current_time,
+        ))
+    }
+
+    pub fn spawn_curvy_laser(
+        &mut self,
+        rectangle: Vec4f,
+        width: f32,
+        timing: LaserTiming,
+        head: Vec2f,
+        max_points: usize,
+        current_time: f64,
+    ) -> LaserReference {
+        let rectangle = self.laser_list.rectangle_to_texcoord(rectangle);
+        self.laser_list.add_laser(Laser::curvy(
+            rectangle,
+            width,
+            timing,
+            head,
+            max_points,
+            current_time,
+        ))
+    }
+
+    pub fn aim_laser(&mut self, laser: &LaserReference, origin: Vec2f, angle: f32, length: f32) {
+        self.laser_list
+            .change_laser(laser, |laser| laser.set_straight(origin, angle, length));
+    }
+
+    pub fn steer_laser(&mut self, laser: &LaserReference, head: Vec2f) {
+        self.laser_list
+            .change_laser(laser, |laser| laser.push_head(head));
+    }
+
+    pub fn fade_laser(&mut self, laser: &LaserReference, current_time: f64) {
+        self.laser_list
+            .change_laser(laser, |laser| laser.fade_out(current_time));
+    }
+
+    pub fn remove_laser(&mut self, laser: LaserReference) {
+        self.laser_list.remove_laser(laser);
+    }
+
+    pub fn laser_list(&self) -> &LaserList {
+        &self.laser_list
+    }
+
+    pub fn schedule_enemy(&mut self, time: f64, template: Rc<EnemyTemplate>, path: Rc<EnemyPath>) {
+        self.enemy_list
+            .schedule_spawn(EnemySpawn::new(time, template, path));
+    }
+
+    pub fn spawn_boss(
+        &mut self,
+        template: Rc<EnemyTemplate>,
+        path: Rc<EnemyPath>,
+        phases: Rc<Vec<BossPhase>>,
+        current_time: f64,
+    ) -> Option<EnemyId> {
+        self.enemy_list
+            .spawn_boss(template, path, phases, current_time)
+    }
+
+    pub fn enemy_list(&self) -> &EnemyList {
+        &self.enemy_list
+    }
+
+    pub fn score(&self) -> &Score {
+        &self.score
+    }
+
+    pub fn high_scores(&self) -> &HighScoreTable {
+        &self.high_scores
+    }
+
+    fn award_extends(&mut self, extends: usize) {
+        if let Some(player) = self.player_list.player_mut(0) {
+            for _ in 0..extends {
+                player.add_life();
+            }
+        }
+    }
+
+    fn submit_high_score(&mut self) {
+        let difficulty = self.score.difficulty();
+        if self
+            .high_scores
+            .submit(difficulty, HIGH_SCORE_NAME, self.score.score())
+            .is_some()
+        {
+            if let Err(error) = self.high_scores.save(&HighScoreTable::default_path()) {
+                eprintln!("{}", error);
+            }
+        }
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
+    }
+
+    pub fn clear_enemy_bullets(&mut self) -> usize {
+        let cleared = self.enemy_bullet_sprites.len();
+        self.grazed_bullets.clear();
+        for sprite in self.enemy_bullet_sprites.drain(..) {
+            self.enemy_bullets.remove_sprite(sprite);
+        }
+        cleared
+    }
+
+    fn bullet_target_id(player_idx: usize, graze: bool) -> u32 {
+        // Each player has a hitbox target and a graze target
+        player_idx as u32 * 2 + graze as u32
+    }
+
+    fn check_player_hits(&mut self, gfx: &mut GFX, current_time: f64) {
+        // Apply the bullet hits found by the last collision pass, then start the next one
+        if let Some(hits) = self.bullet_collider.poll_hits() {
+            self.apply_bullet_hits(&hits, current_time);
+        }
+        let targets = (0..self.player_list.player_count())
+            .filter_map(|idx| {
+                self.player_list
+                    .player_collider(idx, current_time)
+                    .map(|collider| (idx, collider))
+            })
+            .flat_map(|(idx, collider)| {
+                // Bullets passing just outside the hitbox are grazed
+                let radius = match collider.shape() {
+                    CollisionShape::Circle { radius } => radius,
+                    _ => 0.0,
+                };
+                vec![
+                    CollisionTarget::new(
+                        collider.position(),
+                        radius,
+                        Self::bullet_target_id(idx, false),
+                    ),
+                    CollisionTarget::new(
+                        collider.position(),
+                        radius + GRAZE_RADIUS,
+                        Self::bullet_target_id(idx, true),
+                    ),
+                ]
+            })
+            .collect::<Vec<_>>();
+        self.bullet_collider
+            .check(gfx, &self.enemy_bullets, &targets);
+    }
+
+    fn detect_collisions(&mut self, current_time: f64) -> CollisionOutcome {
+        // Put everything that can touch a player or a player shot into the collision world
+        self.collision_world.clear();
+        let mut owners = HashMap::new();
+        for idx in 0..self.player_list.player_count() {
+            if let Some(collider) = self.player_list.player_collider(idx, current_time) {
+                owners.insert(
+                    self.collision_world.add_collider(collider),
+                    CollisionOwner::Player(idx),
+                );
+            }
+        }
+        if let Some(collector) = self.item_collector(current_time) {
+            owners.insert(
+                self.collision_world.add_collider(collector.collider()),
+                CollisionOwner::Collector,
+            );
+        }
+        let laser_hitboxes = self.laser_list.hitboxes(
+            current_time,
+            CollisionLayer::EnemyShot,
+            CollisionMask::none().with(CollisionLayer::Player),
+        );
+        for hitbox in laser_hitboxes {
+            owners.insert(
+                self.collision_world.add_collider(hitbox),
+                CollisionOwner::Laser,
+            );
+        }
+        for (id, collider) in self.enemy_list.colliders(current_time) {
+            owners.insert(
+                self.collision_world.add_collider(collider),
+                CollisionOwner::Enemy(id),
+            );
+        }
+        for (sprite, collider) in self.player_list.shot_list().colliders(current_time) {
+            owners.insert(
+                self.collision_world.add_collider(collider),
+                CollisionOwner::PlayerShot(sprite),
+            );
+        }
+        for (sprite, collider) in self.item_list.colliders() {
+            owners.insert(
+                self.collision_world.add_collider(collider),
+                CollisionOwner::Item(sprite),
+            );
+        }
+
+        // Touching a laser or an enemy hits the player, each shot damages the first enemy it touches,
+        // and items touching the collector are picked up
+        let mut outcome = CollisionOutcome::default();
+        for contact in self.collision_world.detect() {
+            match (owners[&contact.first()], owners[&contact.second()]) {
+                (CollisionOwner::Player(idx), CollisionOwner::Laser)
+                | (CollisionOwner::Laser, CollisionOwner::Player(idx))
+                | (CollisionOwner::Player(idx), CollisionOwner::Enemy(_))
+                | (CollisionOwner::Enemy(_), CollisionOwner::Player(idx)) => {
+                    self.player_list.hit_player(idx, current_time);
+                }
+                (CollisionOwner::PlayerShot(shot), CollisionOwner::Enemy(id))
+                | (CollisionOwner::Enemy(id), CollisionOwner::PlayerShot(shot)) => {
+                    if self.enemy_list.is_vulnerable(id) {
+                        if let Some(damage) = self.player_list.shot_list_mut().take_shot(&shot) {
+                            *outcome.enemy_damage.entry(id).or_insert(0.0) += damage;
+                        }
+                    }
+                }
+                (CollisionOwner::Collector, CollisionOwner::Item(item))
+                | (CollisionOwner::Item(item), CollisionOwner::Collector) => {
+                    outcome.collected_items.insert(item);
+                }
+                _ => (),
+            }
+        }
+        outcome
+    }
+
+    fn apply_bullet_hits(&mut self, hits: &[CollisionHit], current_time: f64) {
+        // Hits are a frame old, so skip bullets that have been removed or replaced since
+        let hits = hits
+            .iter()
+            .filter_map(|hit| {
+                self.enemy_bullets
+                    .sprite_at(hit.actor_idx())
+                    .filter(|sprite| hit.hits_sprite(sprite))
+                    .map(|sprite| (sprite, hit.target_id()))
+            })
+            .collect::<Vec<_>>();
+
+        // Bullets touching a hitbox hit that player
+        let hitbox_hits = hits
+            .iter()
+            .filter(|(_, target_id)| target_id % 2 == 0)
+            .collect::<Vec<_>>();
+        let hit_sprites = hitbox_hits
+            .iter()
+            .map(|(sprite, _)| *sprite)
+            .collect::<HashSet<_>>();
+        for idx in 0..self.player_list.player_count() {
+            let target_id = Self::bullet_target_id(idx, false);
+            if hitbox_hits.iter().any(|(_, hit_id)| *hit_id == target_id) {
+                self.player_list.hit_player(idx, current_time);
+            }
+        }
+
+        // The rest only graze, once each
+        let mut grazes = 0;
+        for (sprite, target_id) in hits.iter() {
+            if target_id % 2 == 1
+                && !hit_sprites.contains(sprite)
+                && self.grazed_bullets.insert(*sprite)
+            {
+                grazes += 1;
+            }
+        }
+        let extends = (0..grazes).map(|_| self.score.add_graze()).sum();
+        self.award_extends(extends);
+    }
+
+    fn handle_enemy_events(&mut self, events: Vec<EnemyEvent>, current_time: f64) {
+        for event in events {
+            match event {
+                // Enemies fire straight down unless their pattern aims
+                EnemyEvent::Attacked {
+                    position, pattern, ..
+                } => {
+                    if !self.game_over {
+                        self.spawn_emitter(pattern, position, FRAC_PI_2, current_time);
+                    }
+                }
+                EnemyEvent::Died {
+                    position,
+                    score_value,
+                    drops,
+                    ..
+                } => {
+                    let extends = self.score.add_kill(score_value);
+                    self.award_extends(extends);
+                    self.item_list.spawn_drops(&drops, position, current_time);
+                }
+                // Moving on to the next phase wipes out the last phase's attacks
+                EnemyEvent::BossPhaseEnded { capture_bonus, .. } => {
+                    let extends = self.score.add_points(capture_bonus.unwrap_or(0));
+                    self.award_extends(extends);
+                    self.emitters.clear();
+                    self.clear_enemy_bullets();
+                }
+                EnemyEvent::Escaped { .. } | EnemyEvent::BossPhaseStarted { .. } => (),
+            }
+        }
+    }
+
+    fn item_collector(&self, current_time: f64) -> Option<ItemCollector> {
+        // Only a living player collects items
+        self.player_list.player(0).and_then(|player| {
+            if player.state() == PlayerState::Alive {
+                self.player_list
+                    .player_position(0, current_time)
+                    .map(|position| ItemCollector::new(position, player.is_focused()))
+            } else {
+                None
+            }
+        })
+    }
+
+    fn update_items(
+        &mut self,
+        collected: &HashSet<SpriteReference>,
+        delta_time: f64,
+        current_time: f64,
+    ) {
+        let collector = self.item_collector(current_time);
+        let events = self
+            .item_list
+            .update(collector, collected, delta_time, current_time);
+
+        for event in events {
+            match event {
+                ItemEvent::Collected { kind, value, .. } => {
+                    let player = self.player_list.player_mut(0).unwrap();
+                    match kind {
+                        ItemKind::Power => player.add_power(POWER_ITEM_VALUE),
+                        ItemKind::Point => {
+                            let extends = self.score.add_item(POINT_ITEM_VALUE, value);
+                            self.award_extends(extends);
+                        }
+                        ItemKind::Life => player.add_life(),
+                        ItemKind::Bomb => player.add_bomb(),
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_player_events(&mut self, events: Vec<(usize, PlayerEvent)>) {
+        for (_, event) in events {
+            match event {
+                // Dying wipes the screen of bullets, but without the points a bomb would give
+                PlayerEvent::Died => {
+                    self.enemy_list.fail_boss_capture();
+                    self.clear_enemy_bullets();
+                }
+                PlayerEvent::Bombed => self.enemy_list.fail_boss_capture(),
+                PlayerEvent::GameOver => {
+                    if self.player_list.is_game_over() {
+                        self.game_over = true;
+                        self.emitters.clear();
+                        self.submit_high_score();
+                    }
+                }
+                PlayerEvent::Respawned => (),
+            }
         }
     }
 }
@@ -32,10 +812,57 @@ impl Scene for ShooterScene {
 
     fn event_update(&mut self, game: &mut Game, delta_time: f64, current_time: f64) {
         // Update player
-        self.player_list.update(game, delta_time, current_time);
+        let events = self
+            .player_list
+            .update(game, &self.playing_field, delta_time, current_time);
+        self.handle_player_events(events);
+
+        // Run the stage's background and music cues, picking up any edits to the stage file
+        if DEBUG {
+            self.reload_stage(game.assets_mut(), current_time);
+        }
+        self.update_stage_cues(game.assets_mut(), current_time);
+
+        // Find out what touched what
+        let collisions = self.detect_collisions(current_time);
+
+        // Update enemies
+        let events =
+            self.enemy_list
+                .update(&self.playing_field, &collisions.enemy_damage, current_time);
+        self.handle_enemy_events(events, current_time);
+
+        // Update items
+        self.update_items(&collisions.collected_items, delta_time, current_time);
+
+        // Update bullet emitters
+        if !self.game_over {
+            self.update_emitters(current_time);
+        }
+
+        // Bombs turn every enemy bullet into points for as long as they last
+        if self.player_list.is_bombing(current_time) {
+            let cleared = self.clear_enemy_bullets() as u64;
+            let extends = self.score.add_points(cleared * BOMB_CLEAR_POINTS);
+            self.award_extends(extends);
+        }
+
+        // Catch up with the animated bullets and forget the ones culled off the field
+        self.enemy_bullets.apply_readback();
+        let culled = self.enemy_bullets.take_culled();
+        if !culled.is_empty() {
+            self.enemy_bullet_sprites
+                .retain(|sprite| !culled.contains(sprite));
+            for sprite in culled.iter() {
+                self.grazed_bullets.remove(sprite);
+            }
+        }
+
+        // Check whether the player was hit by a bullet
+        self.check_player_hits(game.gfx_mut(), current_time);
     }
 
-    fn event_draw(&mut self, game: &mut Game, delta_time: f64, _current_time: f64) {
+    fn event_draw(&mut self, game: &mut Game, delta_time: f64, current_time: f64) {
         let window_size = game.window().size();
 
         // Clear buffer
@@ -72,8 +899,42 @@ impl Scene for ShooterScene {
             1.0,
         ));
 
+        // Draw the stage's background behind everything else
+        if let Some((_, background)) = self.background.as_mut() {
+            background.draw(game.gfx_mut(), delta_time, current_time);
+        }
+
+        // Draw lasers
+        self.laser_list.draw(game.gfx_mut(), current_time);
+
+        // Draw enemies
+        self.enemy_list
+            .draw(game.gfx_mut(), delta_time, current_time);
+
+        // Draw items
+        self.item_list
+            .draw(game.gfx_mut(), delta_time, current_time);
+
+        // Draw enemy bullets
+        self.enemy_bullets
+            .draw(game.gfx_mut(), delta_time, current_time);
+
         // Draw player
-        self.player_list.draw(game.gfx_mut(), delta_time);
+        self.player_list
+            .draw(game.gfx_mut(), delta_time, current_time);
+
+        // Draw the HUD beside the playing field
+        let boss_status = self
+            .enemy_list
+            .boss()
+            .and_then(|boss| boss.status(current_time));
+        self.hud.draw(
+            game.gfx_mut(),
+            window_size,
+            boss_status,
+            delta_time,
+            current_time,
+        );
     }
 
     fn event_key(&mut self, _game: &mut Game, key: Key, pressed: bool, current_time: f64) {}
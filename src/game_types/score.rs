@@ -0,0 +1,401 @@
+use crate::*;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const GRAZE_POINTS: u64 = 500;
+pub const MAX_HIGH_SCORES: usize = 10;
+const HIGH_SCORE_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Lunatic,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Lunatic,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::Lunatic => "lunatic",
+        }
+    }
+
+    pub fn score_multiplier(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.2,
+            Difficulty::Lunatic => 1.5,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Score {
+    difficulty: Difficulty,
+    score: u64,
+    bonus_multiplier: f32,
+    graze: u64,
+    extend_thresholds: Vec<u64>,
+    extends_awarded: usize,
+}
+
+impl Score {
+    pub fn new(difficulty: Difficulty, extend_thresholds: Vec<u64>) -> Self {
+        if DEBUG && extend_thresholds.windows(2).any(|pair| pair[0] >= pair[1]) {
+            panic!("Extend thresholds must be in increasing order");
+        }
+        Self {
+            difficulty,
+            score: 0,
+            bonus_multiplier: 1.0,
+            graze: 0,
+            extend_thresholds,
+            extends_awarded: 0,
+        }
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    pub fn score(&self) -> u64 {
+        self.score
+    }
+
+    pub fn graze(&self) -> u64 {
+        self.graze
+    }
+
+    pub fn multiplier(&self) -> f32 {
+        self.difficulty.score_multiplier() * self.bonus_multiplier
+    }
+
+    pub fn set_bonus_multiplier(&mut self, bonus_multiplier: f32) {
+        if DEBUG && bonus_multiplier < 0.0 {
+            panic!("Score multiplier must not be negative");
+        }
+        self.bonus_multiplier = bonus_multiplier;
+    }
+
+    pub fn add_kill(&mut self, score_value: u64) -> usize {
+        self.add_points(score_value)
+    }
+
+    pub fn add_item(&mut self, item_value: u64, value_fraction: f32) -> usize {
+        self.add_points((item_value as f32 * value_fraction) as u64)
+    }
+
+    pub fn add_graze(&mut self) -> usize {
+        self.graze += 1;
+        self.add_points(GRAZE_POINTS)
+    }
+
+    pub fn add_points(&mut self, points: u64) -> usize {
+        self.score += (points as f64 * self.multiplier() as f64) as u64;
+
+        // Count the extend thresholds that were just passed
+        let reached = self
+            .extend_thresholds
+            .iter()
+            .take_while(|&&threshold| self.score >= threshold)
+            .count();
+        let extends = reached - self.extends_awarded.min(reached);
+        self.extends_awarded = self.extends_awarded.max(reached);
+        extends
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HighScoreEntry {
+    name: String,
+    score: u64,
+}
+
+impl HighScoreEntry {
+    pub fn new(name: &str, score: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            score,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn score(&self) -> u64 {
+        self.score
+    }
+}
+
+#[derive(Debug)]
+pub enum HighScoreError {
+    Io(std::io::Error),
+    Corrupted(String),
+}
+
+impl fmt::Display for HighScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HighScoreError::Io(error) => {
+                write!(f, "Could not access the high score file: {}", error)
+            }
+            HighScoreError::Corrupted(reason) => {
+                write!(f, "High score file is corrupted: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HighScoreError {}
+
+impl From<std::io::Error> for HighScoreError {
+    fn from(error: std::io::Error) -> Self {
+        HighScoreError::Io(error)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct HighScoreTable {
+    tables: [Vec<HighScoreEntry>; 4],
+}
+
+impl HighScoreTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn default_path() -> PathBuf {
+        user_data_dir().join("high_scores.json")
+    }
+
+    fn table_idx(difficulty: Difficulty) -> usize {
+        match difficulty {
+            Difficulty::Easy => 0,
+            Difficulty::Normal => 1,
+            Difficulty::Hard => 2,
+            Difficulty::Lunatic => 3,
+        }
+    }
+
+    pub fn entries(&self, difficulty: Difficulty) -> &[HighScoreEntry] {
+        &self.tables[Self::table_idx(difficulty)]
+    }
+
+    pub fn best(&self, difficulty: Difficulty) -> Option<u64> {
+        self.entries(difficulty).first().map(|entry| entry.score())
+    }
+
+    pub fn submit(&mut self, difficulty: Difficulty, name: &str, score: u64) -> Option<usize> {
+        // Entries are kept best first, with earlier entries winning ties
+        let table = &mut self.tables[Self::table_idx(difficulty)];
+        let rank = table
+            .iter()
+            .position(|entry| entry.score() < score)
+            .unwrap_or_else(|| table.len());
+        if rank >= MAX_HIGH_SCORES {
+            return None;
+        }
+        table.insert(rank, HighScoreEntry::new(name, score));
+        table.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+
+    fn entries_json(&self) -> json::JsonValue {
+        // Scores are stored as strings since JSON numbers can't hold every u64 exactly
+        let mut tables = json::JsonValue::new_object();
+        for &difficulty in Difficulty::ALL.iter() {
+            let entries = self
+                .entries(difficulty)
+                .iter()
+                .map(|entry| json::object! { "name" => entry.name(), "score" => entry.score().to_string() })
+                .collect::<Vec<_>>();
+            tables[difficulty.name()] = json::JsonValue::Array(entries);
+        }
+        tables
+    }
+
+    pub fn to_json(&self) -> String {
+        // The checksum covers the serialized tables so any edit or truncation is caught on load
+        let tables = self.entries_json().dump();
+        let checksum = format!("{:016x}", fnv1a_hash(tables.as_bytes()));
+        json::object! {
+            "version" => HIGH_SCORE_VERSION,
+            "tables" => tables,
+            "checksum" => checksum,
+        }
+        .pretty(4)
+    }
+
+    pub fn from_json(source: &str) -> Result<Self, HighScoreError> {
+        let corrupted = |reason: &str| HighScoreError::Corrupted(reason.to_string());
+        let root =
+            json::parse(source).map_err(|error| HighScoreError::Corrupted(error.to_string()))?;
+        if root["version"].as_u32() != Some(HIGH_SCORE_VERSION) {
+            return Err(corrupted("unknown version"));
+        }
+        let tables_source = root["tables"]
+            .as_str()
+            .ok_or_else(|| corrupted("missing tables"))?;
+        let checksum = root["checksum"]
+            .as_str()
+            .ok_or_else(|| corrupted("missing checksum"))?;
+        if checksum != format!("{:016x}", fnv1a_hash(tables_source.as_bytes())) {
+            return Err(corrupted("checksum does not match"));
+        }
+
+        // Read every difficulty's entries back in
+        let tables = json::parse(tables_source)
+            .map_err(|error| HighScoreError::Corrupted(error.to_string()))?;
+        let mut table = Self::new();
+        for &difficulty in Difficulty::ALL.iter() {
+            let entries = &tables[difficulty.name()];
+            if !entries.is_array() {
+                return Err(corrupted("missing difficulty table"));
+            }
+            for entry in entries.members() {
+                let name = entry["name"]
+                    .as_str()
+                    .ok_or_else(|| corrupted("entry has no name"))?;
+                let score = entry["score"]
+                    .as_str()
+                    .and_then(|score| score.parse::<u64>().ok())
+                    .ok_or_else(|| corrupted("entry has no score"))?;
+                table.submit(difficulty, name, score);
+            }
+        }
+        Ok(table)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, HighScoreError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn load_or_default(path: &Path) -> Self {
+        match Self::load(path) {
+            Ok(table) => table,
+            Err(HighScoreError::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => {
+                Self::new()
+            }
+            Err(error) => {
+                // Keep the bad file around for inspection rather than overwriting it
+                eprintln!("{}; starting a new high score table", error);
+                let _ = fs::rename(path, path.with_extension("json.corrupt"));
+                Self::new()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), HighScoreError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Write to a temporary file first so a crash can't leave a half-written table
+        let temporary_path = path.with_extension("json.tmp");
+        fs::write(&temporary_path, self.to_json())?;
+        fs::rename(&temporary_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_table() -> HighScoreTable {
+        let mut table = HighScoreTable::new();
+        table.submit(Difficulty::Easy, "Reimu", 1_000);
+        table.submit(Difficulty::Normal, "Marisa \"Kirisame\"", 250_000);
+        table.submit(Difficulty::Normal, "Sakuya", 500_000);
+        table.submit(Difficulty::Lunatic, "Sanae ☆", u64::MAX);
+        table
+    }
+
+    fn assert_corrupted(result: Result<HighScoreTable, HighScoreError>, expected_reason: &str) {
+        match result {
+            Err(HighScoreError::Corrupted(reason)) => assert_eq!(reason, expected_reason),
+            result => panic!("Expected a corrupted table, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let table = sample_table();
+        assert_eq!(HighScoreTable::from_json(&table.to_json()).unwrap(), table);
+        assert_eq!(
+            HighScoreTable::from_json(&HighScoreTable::new().to_json()).unwrap(),
+            HighScoreTable::new()
+        );
+    }
+
+    #[test]
+    fn rejects_edited_tables() {
+        // Raise a score without updating the checksum
+        let mut root = json::parse(&sample_table().to_json()).unwrap();
+        let tables = root["tables"]
+            .as_str()
+            .unwrap()
+            .replace("\"1000\"", "\"9000\"");
+        root["tables"] = tables.into();
+        assert_corrupted(
+            HighScoreTable::from_json(&root.dump()),
+            "checksum does not match",
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let mut root = json::parse(&sample_table().to_json()).unwrap();
+        root["version"] = (HIGH_SCORE_VERSION + 1).into();
+        assert_corrupted(HighScoreTable::from_json(&root.dump()), "unknown version");
+        root.remove("version");
+        assert_corrupted(HighScoreTable::from_json(&root.dump()), "unknown version");
+    }
+
+    #[test]
+    fn earlier_entries_win_ties() {
+        let mut table = HighScoreTable::new();
+        assert_eq!(table.submit(Difficulty::Hard, "first", 100), Some(0));
+        assert_eq!(table.submit(Difficulty::Hard, "second", 100), Some(1));
+        assert_eq!(table.submit(Difficulty::Hard, "better", 101), Some(0));
+        let names = table
+            .entries(Difficulty::Hard)
+            .iter()
+            .map(HighScoreEntry::name)
+            .collect::<Vec<&str>>();
+        assert_eq!(names, vec!["better", "first", "second"]);
+
+        // A full table doesn't take a score that only ties its last entry
+        for idx in 3..MAX_HIGH_SCORES {
+            table.submit(Difficulty::Hard, &format!("filler {}", idx), 50);
+        }
+        assert_eq!(table.submit(Difficulty::Hard, "late", 50), None);
+        assert_eq!(table.submit(Difficulty::Hard, "just in", 51), Some(3));
+        assert_eq!(table.entries(Difficulty::Hard).len(), MAX_HIGH_SCORES);
+    }
+
+    #[test]
+    fn awards_every_extend_passed_at_once() {
+        let mut score = Score::new(Difficulty::Normal, vec![100, 200, 300, 1_000]);
+        assert_eq!(score.add_points(350), 3);
+        assert_eq!(score.add_points(0), 0);
+        assert_eq!(score.add_points(649), 0);
+        assert_eq!(score.add_points(1), 1);
+        assert_eq!(score.add_points(1_000_000), 0);
+        assert_eq!(score.score(), 1_001_000);
+    }
+}
@@ -0,0 +1,849 @@
+use crate::*;
+use fennec_algebra::*;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
+
+// Stage files are line based. Blank lines and anything after a '#' are ignored, and
+// indented lines add properties to the enemy or path block above them:
+//
+//     background 0.0 "Textures/stage1.png"
+//     music 0.0 "Music/stage1.ogg"
+//
+//     enemy fairy
+//         sprite 0 64 32 32
+//         scale 0.1 0.1
+//         health 8
+//         hitbox 0.03
+//         score 1000
+//         attack ring 1.0
+//         drop power 2 0.5
+//
+//     path swoop_left -0.8 -1.2
+//         line -0.4 -0.5 1.5
+//         wait 2.0
+//         bezier -0.4 0.0 0.4 0.0 1.2 0.2 3.0
+//
+//     wave 2.0 fairy swoop_left 5 0.3
+//
+// Positions are fractions of the field's half size, so (-1, -1) is the top left corner.
+
+#[derive(Debug)]
+pub enum StageError {
+    Io {
+        file: PathBuf,
+        error: std::io::Error,
+    },
+    Invalid {
+        file: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl StageError {
+    fn invalid(file: &Path, line: usize, message: String) -> Self {
+        StageError::Invalid {
+            file: file.to_path_buf(),
+            line,
+            message,
+        }
+    }
+
+    pub fn file(&self) -> &Path {
+        match self {
+            StageError::Io { file, .. } => file,
+            StageError::Invalid { file, .. } => file,
+        }
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            StageError::Io { .. } => None,
+            StageError::Invalid { line, .. } => Some(*line),
+        }
+    }
+}
+
+impl fmt::Display for StageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StageError::Io { file, error } => {
+                write!(f, "{}: could not read stage: {}", file.display(), error)
+            }
+            StageError::Invalid {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", file.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for StageError {}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum StageCueKind {
+    Background(String),
+    Music(String),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct StageCue {
+    time: f64,
+    kind: StageCueKind,
+}
+
+impl StageCue {
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn kind(&self) -> &StageCueKind {
+        &self.kind
+    }
+
+    pub fn offset(&self, start_time: f64) -> Self {
+        Self {
+            time: start_time + self.time,
+            kind: self.kind.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StageWave {
+    time: f64,
+    template: Rc<EnemyTemplate>,
+    path: Rc<EnemyPath>,
+    count: usize,
+    interval: f64,
+}
+
+impl StageWave {
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn template(&self) -> &Rc<EnemyTemplate> {
+        &self.template
+    }
+
+    pub fn path(&self) -> &Rc<EnemyPath> {
+        &self.path
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn interval(&self) -> f64 {
+        self.interval
+    }
+
+    pub fn spawns(&self, start_time: f64) -> Vec<EnemySpawn> {
+        (0..self.count)
+            .map(|idx| {
+                EnemySpawn::new(
+                    start_time + self.time + self.interval * idx as f64,
+                    self.template.clone(),
+                    self.path.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Stage {
+    file: PathBuf,
+    waves: Vec<StageWave>,
+    cues: Vec<StageCue>,
+}
+
+impl Stage {
+    pub fn load(
+        file: &Path,
+        patterns: &HashMap<String, Rc<BulletPattern>>,
+        field_half_size: Vec2f,
+    ) -> Result<Self, StageError> {
+        let source = fs::read_to_string(file).map_err(|error| StageError::Io {
+            file: file.to_path_buf(),
+            error,
+        })?;
+        Self::parse(file, &source, patterns, field_half_size)
+    }
+
+    pub fn parse(
+        file: &Path,
+        source: &str,
+        patterns: &HashMap<String, Rc<BulletPattern>>,
+        field_half_size: Vec2f,
+    ) -> Result<Self, StageError> {
+        StageParser::new(file, patterns, field_half_size).parse(source)
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    pub fn waves(&self) -> &[StageWave] {
+        &self.waves
+    }
+
+    pub fn cues(&self) -> &[StageCue] {
+        &self.cues
+    }
+
+    pub fn duration(&self) -> f64 {
+        let last_wave = self
+            .waves
+            .iter()
+            .map(|wave| wave.time + wave.interval * (wave.count - 1) as f64)
+            .fold(0.0, f64::max);
+        let last_cue = self.cues.iter().map(|cue| cue.time).fold(0.0, f64::max);
+        last_wave.max(last_cue)
+    }
+}
+
+struct StageLine {
+    number: usize,
+    indented: bool,
+    words: Vec<String>,
+}
+
+struct EnemyBlock {
+    name: String,
+    line: usize,
+    rectangle: Option<Vec4f>,
+    scale: Option<Vec2f>,
+    health: Option<f32>,
+    hitbox_radius: Option<f32>,
+    score_value: u64,
+    attack: Option<(Rc<BulletPattern>, f64)>,
+    drop_table: DropTable,
+}
+
+struct PathBlock {
+    name: String,
+    path: EnemyPath,
+}
+
+struct WaveLine {
+    line: usize,
+    time: f64,
+    enemy: String,
+    path: String,
+    count: usize,
+    interval: f64,
+}
+
+enum Block {
+    None,
+    Enemy(EnemyBlock),
+    Path(PathBlock),
+}
+
+struct StageParser<'a> {
+    file: &'a Path,
+    patterns: &'a HashMap<String, Rc<BulletPattern>>,
+    field_half_size: Vec2f,
+    block: Block,
+    enemies: HashMap<String, Rc<EnemyTemplate>>,
+    paths: HashMap<String, Rc<EnemyPath>>,
+    waves: Vec<WaveLine>,
+    cues: Vec<StageCue>,
+}
+
+impl<'a> StageParser<'a> {
+    fn new(
+        file: &'a Path,
+        patterns: &'a HashMap<String, Rc<BulletPattern>>,
+        field_half_size: Vec2f,
+    ) -> Self {
+        Self {
+            file,
+            patterns,
+            field_half_size,
+            block: Block::None,
+            enemies: HashMap::new(),
+            paths: HashMap::new(),
+            waves: Vec::new(),
+            cues: Vec::new(),
+        }
+    }
+
+    fn error<T>(&self, line: usize, message: String) -> Result<T, StageError> {
+        Err(StageError::invalid(self.file, line, message))
+    }
+
+    fn parse(mut self, source: &str) -> Result<Stage, StageError> {
+        for (idx, text) in source.lines().enumerate() {
+            let line = self.split_line(idx + 1, text)?;
+            if line.words.is_empty() {
+                continue;
+            }
+            if line.indented {
+                self.parse_property(&line)?;
+            } else {
+                self.finish_block()?;
+                self.parse_statement(&line)?;
+            }
+        }
+        self.finish_block()?;
+
+        // Waves may use enemies and paths defined further down, so they are resolved last
+        let mut waves = Vec::new();
+        for wave in self.waves.iter() {
+            let template = match self.enemies.get(&wave.enemy) {
+                Some(template) => template.clone(),
+                None => return self.error(wave.line, format!("Unknown enemy '{}'", wave.enemy)),
+            };
+            let path = match self.paths.get(&wave.path) {
+                Some(path) => path.clone(),
+                None => return self.error(wave.line, format!("Unknown path '{}'", wave.path)),
+            };
+            waves.push(StageWave {
+                time: wave.time,
+                template,
+                path,
+                count: wave.count,
+                interval: wave.interval,
+            });
+        }
+        // Times are always finite, so they never compare as unordered
+        let by_time = |a: f64, b: f64| a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+        waves.sort_by(|a, b| by_time(a.time, b.time));
+        self.cues.sort_by(|a, b| by_time(a.time, b.time));
+
+        Ok(Stage {
+            file: self.file.to_path_buf(),
+            waves,
+            cues: self.cues,
+        })
+    }
+
+    fn split_line(&self, number: usize, text: &str) -> Result<StageLine, StageError> {
+        let indented = text.starts_with(' ') || text.starts_with('\t');
+        let mut words = Vec::new();
+        let mut chars = text.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c == '#' {
+                break;
+            } else if c.is_whitespace() {
+                chars.next();
+            } else if c == '"' {
+                // Quoted words may contain spaces
+                chars.next();
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => word.push(c),
+                        None => return self.error(number, "Unterminated quote".to_string()),
+                    }
+                }
+                words.push(word);
+            } else {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '#' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                words.push(word);
+            }
+        }
+        Ok(StageLine {
+            number,
+            indented,
+            words,
+        })
+    }
+
+    fn expect_words(
+        &self,
+        line: &StageLine,
+        min: usize,
+        max: usize,
+        usage: &str,
+    ) -> Result<(), StageError> {
+        let count = line.words.len() - 1;
+        if count < min || count > max {
+            return self.error(line.number, format!("Expected '{}'", usage));
+        }
+        Ok(())
+    }
+
+    fn number<T: FromStr>(&self, line: &StageLine, idx: usize) -> Result<T, StageError> {
+        let word = &line.words[idx];
+        match word.parse::<T>() {
+            Ok(value) => Ok(value),
+            Err(_) => self.error(line.number, format!("'{}' is not a valid number", word)),
+        }
+    }
+
+    fn float(&self, line: &StageLine, idx: usize) -> Result<f32, StageError> {
+        // "inf" and "nan" parse as floats, but nothing in a stage can use them
+        let value = self.number::<f32>(line, idx)?;
+        if !value.is_finite() {
+            return self.error(
+                line.number,
+                format!("'{}' is not a finite number", line.words[idx]),
+            );
+        }
+        Ok(value)
+    }
+
+    fn time(&self, line: &StageLine, idx: usize) -> Result<f64, StageError> {
+        let time = self.number::<f64>(line, idx)?;
+        if !time.is_finite() {
+            return self.error(
+                line.number,
+                format!("'{}' is not a finite number", line.words[idx]),
+            );
+        }
+        if time < 0.0 {
+            return self.error(line.number, format!("Time {} must not be negative", time));
+        }
+        Ok(time)
+    }
+
+    fn position(&self, line: &StageLine, idx: usize) -> Result<Vec2f, StageError> {
+        let x = self.float(line, idx)?;
+        let y = self.float(line, idx + 1)?;
+        Ok(vector!(
+            x * self.field_half_size[0],
+            y * self.field_half_size[1]
+        ))
+    }
+
+    fn parse_statement(&mut self, line: &StageLine) -> Result<(), StageError> {
+        match line.words[0].as_str() {
+            "background" | "music" => {
+                self.expect_words(line, 2, 2, &format!("{} <time> <asset>", line.words[0]))?;
+                let time = self.time(line, 1)?;
+                let asset = line.words[2].clone();
+                let kind = if line.words[0] == "background" {
+                    StageCueKind::Background(asset)
+                } else {
+                    StageCueKind::Music(asset)
+                };
+                self.cues.push(StageCue { time, kind });
+            }
+            "enemy" => {
+                self.expect_words(line, 1, 1, "enemy <name>")?;
+                let name = line.words[1].clone();
+                if self.enemies.contains_key(&name) {
+                    return self.error(line.number, format!("Enemy '{}' is already defined", name));
+                }
+                self.block = Block::Enemy(EnemyBlock {
+                    name,
+                    line: line.number,
+                    rectangle: None,
+                    scale: None,
+                    health: None,
+                    hitbox_radius: None,
+                    score_value: 0,
+                    attack: None,
+                    drop_table: DropTable::new(),
+                });
+            }
+            "path" => {
+                self.expect_words(line, 3, 3, "path <name> <start x> <start y>")?;
+                let name = line.words[1].clone();
+                if self.paths.contains_key(&name) {
+                    return self.error(line.number, format!("Path '{}' is already defined", name));
+                }
+                let start = self.position(line, 2)?;
+                self.block = Block::Path(PathBlock {
+                    name,
+                    path: EnemyPath::new(start),
+                });
+            }
+            "wave" => {
+                self.expect_words(
+                    line,
+                    3,
+                    5,
+                    "wave <time> <enemy> <path> [<count> <interval>]",
+                )?;
+                let time = self.time(line, 1)?;
+                let count = if line.words.len() > 4 {
+                    self.number::<usize>(line, 4)?
+                } else {
+                    1
+                };
+                if count == 0 {
+                    return self
+                        .error(line.number, "Wave count must be greater than 0".to_string());
+                }
+                let interval = if line.words.len() > 5 {
+                    self.time(line, 5)?
+                } else {
+                    0.0
+                };
+                self.waves.push(WaveLine {
+                    line: line.number,
+                    time,
+                    enemy: line.words[2].clone(),
+                    path: line.words[3].clone(),
+                    count,
+                    interval,
+                });
+            }
+            keyword => return self.error(line.number, format!("Unknown statement '{}'", keyword)),
+        }
+        Ok(())
+    }
+
+    fn parse_property(&mut self, line: &StageLine) -> Result<(), StageError> {
+        // Take the block out while it is changed so the helpers can still borrow the parser
+        let block = std::mem::replace(&mut self.block, Block::None);
+        let block = match block {
+            Block::None => {
+                return self.error(
+                    line.number,
+                    "Indented line is not inside an enemy or path".to_string(),
+                )
+            }
+            Block::Enemy(enemy) => Block::Enemy(self.parse_enemy_property(enemy, line)?),
+            Block::Path(path) => Block::Path(self.parse_path_property(path, line)?),
+        };
+        self.block = block;
+        Ok(())
+    }
+
+    fn parse_enemy_property(
+        &self,
+        mut enemy: EnemyBlock,
+        line: &StageLine,
+    ) -> Result<EnemyBlock, StageError> {
+        match line.words[0].as_str() {
+            "sprite" => {
+                self.expect_words(line, 4, 4, "sprite <x> <y> <width> <height>")?;
+                enemy.rectangle = Some(vector!(
+                    self.float(line, 1)?,
+                    self.float(line, 2)?,
+                    self.float(line, 3)?,
+                    self.float(line, 4)?
+                ));
+            }
+            "scale" => {
+                self.expect_words(line, 2, 2, "scale <x> <y>")?;
+                enemy.scale = Some(vector!(self.float(line, 1)?, self.float(line, 2)?));
+            }
+            "health" => {
+                self.expect_words(line, 1, 1, "health <amount>")?;
+                let health = self.float(line, 1)?;
+                if health <= 0.0 {
+                    return self.error(
+                        line.number,
+                        "Enemy health must be greater than 0".to_string(),
+                    );
+                }
+                enemy.health = Some(health);
+            }
+            "hitbox" => {
+                self.expect_words(line, 1, 1, "hitbox <radius>")?;
+                enemy.hitbox_radius = Some(self.float(line, 1)?);
+            }
+            "score" => {
+                self.expect_words(line, 1, 1, "score <points>")?;
+                enemy.score_value = self.number::<u64>(line, 1)?;
+            }
+            "attack" => {
+                self.expect_words(line, 2, 2, "attack <pattern> <delay>")?;
+                let pattern = match self.patterns.get(&line.words[1]) {
+                    Some(pattern) => pattern.clone(),
+                    None => {
+                        return self.error(
+                            line.number,
+                            format!("Unknown bullet pattern '{}'", line.words[1]),
+                        )
+                    }
+                };
+                enemy.attack = Some((pattern, self.time(line, 2)?));
+            }
+            "drop" => {
+                self.expect_words(line, 3, 3, "drop <power|point|life|bomb> <count> <chance>")?;
+                let kind = match line.words[1].as_str() {
+                    "power" => ItemKind::Power,
+                    "point" => ItemKind::Point,
+                    "life" => ItemKind::Life,
+                    "bomb" => ItemKind::Bomb,
+                    kind => return self.error(line.number, format!("Unknown item '{}'", kind)),
+                };
+                let count = self.number::<usize>(line, 2)?;
+                let chance = self.float(line, 3)?;
+                if !(0.0..=1.0).contains(&chance) {
+                    return self.error(
+                        line.number,
+                        format!("Drop chance {} must be between 0 and 1", chance),
+                    );
+                }
+                enemy.drop_table = enemy.drop_table.with_drop(kind, count, chance);
+            }
+            property => {
+                return self.error(
+                    line.number,
+                    format!("Unknown enemy property '{}'", property),
+                )
+            }
+        }
+        Ok(enemy)
+    }
+
+    fn parse_path_property(
+        &self,
+        mut path: PathBlock,
+        line: &StageLine,
+    ) -> Result<PathBlock, StageError> {
+        match line.words[0].as_str() {
+            "line" => {
+                self.expect_words(line, 3, 3, "line <x> <y> <duration>")?;
+                let to = self.position(line, 1)?;
+                path.path = path.path.line_to(to, self.time(line, 3)?);
+            }
+            "bezier" => {
+                self.expect_words(
+                    line,
+                    7,
+                    7,
+                    "bezier <control x> <control y> <control x> <control y> <x> <y> <duration>",
+                )?;
+                let control0 = self.position(line, 1)?;
+                let control1 = self.position(line, 3)?;
+                let to = self.position(line, 5)?;
+                path.path = path
+                    .path
+                    .bezier_to(control0, control1, to, self.time(line, 7)?);
+            }
+            "wait" => {
+                self.expect_words(line, 1, 1, "wait <duration>")?;
+                path.path = path.path.wait(self.time(line, 1)?);
+            }
+            segment => {
+                return self.error(line.number, format!("Unknown path segment '{}'", segment))
+            }
+        }
+        Ok(path)
+    }
+
+    fn finish_block(&mut self) -> Result<(), StageError> {
+        match std::mem::replace(&mut self.block, Block::None) {
+            Block::None => (),
+            Block::Enemy(enemy) => {
+                // Report missing properties against the line that started the enemy
+                let missing = |property: &str| {
+                    StageError::invalid(
+                        self.file,
+                        enemy.line,
+                        format!("Enemy '{}' has no '{}'", enemy.name, property),
+                    )
+                };
+                let rectangle = enemy.rectangle.ok_or_else(|| missing("sprite"))?;
+                let scale = enemy.scale.ok_or_else(|| missing("scale"))?;
+                let health = enemy.health.ok_or_else(|| missing("health"))?;
+                let hitbox_radius = enemy.hitbox_radius.ok_or_else(|| missing("hitbox"))?;
+                let mut template = EnemyTemplate::new(rectangle, scale, health, hitbox_radius)
+                    .with_score_value(enemy.score_value)
+                    .with_drop_table(enemy.drop_table);
+                if let Some((pattern, delay)) = enemy.attack {
+                    template = template.with_attack(pattern, delay);
+                }
+                self.enemies.insert(enemy.name, Rc::new(template));
+            }
+            Block::Path(path) => {
+                self.paths.insert(path.name, Rc::new(path.path));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENEMY: &str =
+        "enemy fairy\n    sprite 0 64 32 32\n    scale 0.1 0.1\n    health 8\n    hitbox 0.03\n";
+    const PATH: &str = "path down 0 -1\n    line 0 1 2.0\n";
+
+    fn parse(source: &str) -> Result<Stage, StageError> {
+        Stage::parse(
+            Path::new("test.stage"),
+            source,
+            &HashMap::new(),
+            vector!(1.0, 1.0),
+        )
+    }
+
+    fn assert_error(source: &str, expected_line: usize, expected_message: &str) {
+        match parse(source) {
+            Err(StageError::Invalid { line, message, .. }) => {
+                assert_eq!(
+                    (line, message.as_str()),
+                    (expected_line, expected_message),
+                    "for source {:?}",
+                    source
+                );
+            }
+            Err(error) => panic!("Expected an invalid stage error, got {}", error),
+            Ok(_) => panic!("Expected {:?} to fail to parse", source),
+        }
+    }
+
+    #[test]
+    fn parses_and_sorts_waves_and_cues() {
+        let source = format!(
+            "{}{}# Comment\nwave 4.0 fairy down 3 0.5\nwave 1.0 fairy down\nmusic 2.0 \"Music/a b.ogg\"\nbackground 0.0 bg\n",
+            ENEMY, PATH
+        );
+        let stage = parse(&source).unwrap();
+        let wave_times = stage
+            .waves()
+            .iter()
+            .map(StageWave::time)
+            .collect::<Vec<_>>();
+        assert_eq!(wave_times, vec![1.0, 4.0]);
+        assert_eq!(stage.waves()[1].count(), 3);
+        assert_eq!(
+            stage
+                .cues()
+                .iter()
+                .map(|cue| cue.kind().clone())
+                .collect::<Vec<_>>(),
+            vec![
+                StageCueKind::Background("bg".to_string()),
+                StageCueKind::Music("Music/a b.ogg".to_string()),
+            ]
+        );
+        assert_eq!(stage.duration(), 5.0);
+    }
+
+    #[test]
+    fn reports_malformed_lines() {
+        assert_error("music 0.0 \"unterminated", 1, "Unterminated quote");
+        assert_error("\n\nmusic 0.0", 3, "Expected 'music <time> <asset>'");
+        assert_error("explode 1.0", 1, "Unknown statement 'explode'");
+        assert_error(
+            "    health 8",
+            1,
+            "Indented line is not inside an enemy or path",
+        );
+    }
+
+    #[test]
+    fn reports_invalid_numbers() {
+        assert_error("music soon a", 1, "'soon' is not a valid number");
+        assert_error("music -1.0 a", 1, "Time -1 must not be negative");
+        assert_error("music inf a", 1, "'inf' is not a finite number");
+        assert_error("music NaN a", 1, "'NaN' is not a finite number");
+        assert_error(
+            "enemy fairy\n    scale NaN 0.1",
+            2,
+            "'NaN' is not a finite number",
+        );
+        assert_error("path down inf 0", 1, "'inf' is not a finite number");
+    }
+
+    #[test]
+    fn reports_invalid_enemy_properties() {
+        assert_error(
+            "enemy fairy\n    health 0",
+            2,
+            "Enemy health must be greater than 0",
+        );
+        assert_error(
+            "enemy fairy\n    attack ring 1.0",
+            2,
+            "Unknown bullet pattern 'ring'",
+        );
+        assert_error("enemy fairy\n    drop gold 1 0.5", 2, "Unknown item 'gold'");
+        assert_error(
+            "enemy fairy\n    drop power 1 1.5",
+            2,
+            "Drop chance 1.5 must be between 0 and 1",
+        );
+        assert_error(
+            "enemy fairy\n    speed 2",
+            2,
+            "Unknown enemy property 'speed'",
+        );
+        assert_error(
+            "\nenemy fairy\n    sprite 0 0 1 1\n    scale 1 1\n    health 1\n",
+            2,
+            "Enemy 'fairy' has no 'hitbox'",
+        );
+        assert_error(
+            &format!("{}enemy fairy\n", ENEMY),
+            6,
+            "Enemy 'fairy' is already defined",
+        );
+    }
+
+    #[test]
+    fn reports_invalid_paths() {
+        assert_error(
+            "path down 0 0\n    jump 1 1",
+            2,
+            "Unknown path segment 'jump'",
+        );
+        assert_error(
+            &format!("{}path down 0 0\n", PATH),
+            3,
+            "Path 'down' is already defined",
+        );
+    }
+
+    #[test]
+    fn reports_invalid_waves() {
+        let header = format!("{}{}", ENEMY, PATH);
+        assert_error(
+            &format!("{}wave 1.0 fairy down 0 0.5", header),
+            8,
+            "Wave count must be greater than 0",
+        );
+        assert_error(
+            &format!("{}wave 1.0 fairy down 2 inf", header),
+            8,
+            "'inf' is not a finite number",
+        );
+        assert_error(
+            &format!("wave 1.0 ghost down\n{}", header),
+            1,
+            "Unknown enemy 'ghost'",
+        );
+        assert_error(
+            &format!("{}\nwave 1.0 fairy up", header),
+            9,
+            "Unknown path 'up'",
+        );
+    }
+
+    #[test]
+    fn errors_name_the_file_and_line() {
+        let error = parse("explode").unwrap_err();
+        assert_eq!(error.file(), Path::new("test.stage"));
+        assert_eq!(error.line(), Some(1));
+        assert_eq!(
+            error.to_string(),
+            "test.stage:1: Unknown statement 'explode'"
+        );
+
+        let error = Stage::load(
+            Path::new("does/not/exist.stage"),
+            &HashMap::new(),
+            vector!(1.0, 1.0),
+        )
+        .unwrap_err();
+        assert_eq!(error.line(), None);
+    }
+}
@@ -0,0 +1,34 @@
+use crate::*;
+
+const VERTEX_ATTRIBUTE_BINDINGS: [VertexAttributeBinding; 4] = [
+    VertexAttributeBinding::Float2,
+    VertexAttributeBinding::Float2,
+    VertexAttributeBinding::Float4,
+    VertexAttributeBinding::Float,
+];
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LaserVertex {
+    position: Vec2f,
+    tex_coord: Vec2f,
+    rectangle: Vec4f,
+    alpha: f32,
+}
+
+impl LaserVertex {
+    pub fn new(position: Vec2f, tex_coord: Vec2f, rectangle: Vec4f, alpha: f32) -> Self {
+        Self {
+            position,
+            tex_coord,
+            rectangle,
+            alpha,
+        }
+    }
+}
+
+impl Vertex for LaserVertex {
+    fn vertex_attribute_bindings() -> &'static [VertexAttributeBinding] {
+        &VERTEX_ATTRIBUTE_BINDINGS
+    }
+}
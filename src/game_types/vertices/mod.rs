@@ -1 +1,2 @@
-
+mod laser_vertex;
+pub use laser_vertex::*;
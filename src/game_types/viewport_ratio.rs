@@ -0,0 +1,43 @@
+use crate::*;
+use fennec_algebra::*;
+
+// The part of the window something is drawn into, as fractions of the window's x, y, width and
+// height, so it keeps its place when the window is resized
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ViewportRatio {
+    ratio: Vec4f,
+}
+
+impl ViewportRatio {
+    pub fn new(ratio: Vec4f) -> Self {
+        Self { ratio }
+    }
+
+    pub fn ratio(&self) -> Vec4f {
+        self.ratio
+    }
+
+    pub fn viewport(&self, window_size: Vec2u) -> Vec4f {
+        vector!(
+            (window_size[0] as f32 * self.ratio[0]),
+            (window_size[1] as f32 * self.ratio[1]),
+            (window_size[0] as f32 * self.ratio[2]),
+            (window_size[1] as f32 * self.ratio[3]),
+        )
+    }
+
+    pub fn viewport_pixels(&self, window_size: Vec2u) -> Vec4i {
+        let viewport = self.viewport(window_size);
+        vector!(
+            viewport[0] as i32,
+            viewport[1] as i32,
+            viewport[2] as i32,
+            viewport[3] as i32,
+        )
+    }
+
+    pub fn aspect_ratio(&self, window_size: Vec2u) -> f32 {
+        let viewport = self.viewport(window_size);
+        viewport[2] / viewport[3]
+    }
+}
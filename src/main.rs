@@ -10,6 +10,9 @@ pub use types::*;
 mod util;
 pub use util::*;
 
+mod error;
+pub use error::*;
+
 mod game_types;
 pub use game_types::*;
 
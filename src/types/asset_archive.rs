@@ -0,0 +1,288 @@
+// This file is also compiled into the pack_assets tool, so it only uses std and miniz_oxide
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+// Archives start with a header and an index of every entry, followed by the compressed blobs:
+//
+//     magic "BLMA", version u32, entry count u32
+//     per entry: name length u16, name (UTF-8), blob offset u64, compressed size u64, size u64
+//     blobs, each compressed with deflate
+//
+// All integers are little endian, and offsets are from the start of the file.
+pub const ARCHIVE_EXTENSION: &str = "pak";
+const ARCHIVE_MAGIC: &[u8; 4] = b"BLMA";
+const ARCHIVE_VERSION: u32 = 1;
+const COMPRESSION_LEVEL: u8 = 6;
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ArchiveEntry {
+    offset: u64,
+    compressed_size: u64,
+    size: u64,
+}
+
+impl ArchiveEntry {
+    pub fn compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+#[derive(Debug)]
+pub struct AssetArchive {
+    path: PathBuf,
+    file_size: u64,
+    entries: BTreeMap<String, ArchiveEntry>,
+}
+
+impl AssetArchive {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)?;
+        let file_size = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        // Check the header
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != ARCHIVE_MAGIC {
+            return Err(invalid_data("Not an asset archive"));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != ARCHIVE_VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported asset archive version {}",
+                version
+            )));
+        }
+
+        // Read the index, making sure every blob lies inside the file
+        let count = read_u32(&mut reader)?;
+        let mut entries = BTreeMap::new();
+        for _ in 0..count {
+            let name_length = read_u16(&mut reader)? as usize;
+            let mut name = vec![0; name_length];
+            reader.read_exact(&mut name)?;
+            let name = String::from_utf8(name)
+                .map_err(|_| invalid_data("Asset archive entry name is not UTF-8"))?;
+            let entry = ArchiveEntry {
+                offset: read_u64(&mut reader)?,
+                compressed_size: read_u64(&mut reader)?,
+                size: read_u64(&mut reader)?,
+            };
+            let end = entry.offset.checked_add(entry.compressed_size);
+            if end.map_or(true, |end| end > file_size) {
+                return Err(invalid_data(format!(
+                    "Asset archive entry {:?} is truncated",
+                    name
+                )));
+            }
+            entries.insert(name, entry);
+        }
+
+        Ok(Self {
+            path,
+            file_size,
+            entries,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &ArchiveEntry)> {
+        self.entries
+            .iter()
+            .map(|(name, entry)| (name.as_str(), entry))
+    }
+
+    pub fn read(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let entry = match self.entries.get(name) {
+            Some(entry) => *entry,
+            None => return Ok(None),
+        };
+
+        // Read the compressed blob
+        let mut file = File::open(&self.path)?;
+        if file.metadata()?.len() != self.file_size {
+            return Err(invalid_data("Asset archive changed since it was opened"));
+        }
+        file.seek(SeekFrom::Start(entry.offset))?;
+        let mut compressed = vec![0; entry.compressed_size as usize];
+        file.read_exact(&mut compressed)?;
+
+        // Decompress it, never past the size the index promises, and check that nothing was lost
+        let bytes =
+            miniz_oxide::inflate::decompress_to_vec_with_limit(&compressed, entry.size as usize)
+                .map_err(|status| {
+                    invalid_data(format!("Could not decompress {:?}: {:?}", name, status))
+                })?;
+        if bytes.len() as u64 != entry.size {
+            return Err(invalid_data(format!("Asset {:?} is corrupted", name)));
+        }
+        Ok(Some(bytes))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AssetArchiveWriter {
+    blobs: BTreeMap<String, (Vec<u8>, u64)>,
+}
+
+impl AssetArchiveWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: &str, bytes: &[u8]) {
+        if name.len() > u16::MAX as usize {
+            panic!("Asset name {:?} is too long", name);
+        }
+        let compressed = miniz_oxide::deflate::compress_to_vec(bytes, COMPRESSION_LEVEL);
+        self.blobs
+            .insert(name.to_string(), (compressed, bytes.len() as u64));
+    }
+
+    pub fn len(&self) -> usize {
+        self.blobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blobs.is_empty()
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        // Blobs start right after the index
+        let index_size = self
+            .blobs
+            .keys()
+            .map(|name| 2 + name.len() as u64 + 8 * 3)
+            .sum::<u64>();
+        let mut offset = ARCHIVE_MAGIC.len() as u64 + 4 + 4 + index_size;
+
+        // Write the header and index
+        writer.write_all(ARCHIVE_MAGIC)?;
+        writer.write_all(&ARCHIVE_VERSION.to_le_bytes())?;
+        writer.write_all(&(self.blobs.len() as u32).to_le_bytes())?;
+        for (name, (compressed, size)) in self.blobs.iter() {
+            writer.write_all(&(name.len() as u16).to_le_bytes())?;
+            writer.write_all(name.as_bytes())?;
+            writer.write_all(&offset.to_le_bytes())?;
+            writer.write_all(&(compressed.len() as u64).to_le_bytes())?;
+            writer.write_all(&size.to_le_bytes())?;
+            offset += compressed.len() as u64;
+        }
+
+        // Write the blobs in the same order as the index
+        for (compressed, _) in self.blobs.values() {
+            writer.write_all(compressed)?;
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, OpenOptions};
+
+    fn temporary_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "bloom_asset_archive_{}_{}.{}",
+            name,
+            std::process::id(),
+            ARCHIVE_EXTENSION
+        ))
+    }
+
+    fn write_archive(path: &Path) {
+        let mut writer = AssetArchiveWriter::new();
+        writer.add("Shaders/sprite.frag", b"void main() {}");
+        writer.add("Stages/empty.stage", b"");
+        writer.add("Textures/big.bin", &[7; 4096]);
+        writer.write(path).unwrap();
+    }
+
+    #[test]
+    fn round_trips_entries() {
+        let path = temporary_path("round_trip");
+        write_archive(&path);
+        let archive = AssetArchive::open(&path).unwrap();
+
+        assert_eq!(archive.entries().count(), 3);
+        assert!(archive.contains("Textures/big.bin"));
+        assert!(!archive.contains("Textures/missing.png"));
+        assert_eq!(
+            archive.read("Shaders/sprite.frag").unwrap(),
+            Some(b"void main() {}".to_vec())
+        );
+        assert_eq!(
+            archive.read("Stages/empty.stage").unwrap(),
+            Some(Vec::new())
+        );
+        assert_eq!(
+            archive.read("Textures/big.bin").unwrap(),
+            Some(vec![7; 4096])
+        );
+        assert_eq!(archive.read("Textures/missing.png").unwrap(), None);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_truncated_archives() {
+        let path = temporary_path("truncated");
+        write_archive(&path);
+
+        // Cut into the last blob, so the index still reads but points past the end of the file
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        let file_size = file.metadata().unwrap().len();
+        file.set_len(file_size - 1).unwrap();
+        drop(file);
+        let error = AssetArchive::open(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // Cut into the index itself
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(16).unwrap();
+        drop(file);
+        let error = AssetArchive::open(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
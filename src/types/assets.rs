@@ -0,0 +1,425 @@
+use crate::*;
+use fennec_algebra::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+pub const DEFAULT_ASSET_ROOT: &str = "Game";
+const TEXTURE_DIRECTORY: &str = "Textures";
+const SHADER_DIRECTORY: &str = "Shaders";
+const STAGE_DIRECTORY: &str = "Stages";
+
+#[derive(Debug)]
+pub enum AssetError {
+    NotFound {
+        name: String,
+        path: PathBuf,
+    },
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Invalid {
+        name: String,
+        message: String,
+    },
+    Load {
+        name: String,
+        error: BloomError,
+    },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetError::NotFound { name, path } => {
+                write!(f, "Asset {:?} not found at {}", name, path.display())
+            }
+            AssetError::Io { path, error } => {
+                write!(f, "Could not read asset {}: {}", path.display(), error)
+            }
+            AssetError::Invalid { name, message } => {
+                write!(f, "Asset {:?} is invalid: {}", name, message)
+            }
+            AssetError::Load { name, error } => {
+                write!(f, "Could not load asset {:?}: {}", name, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AssetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AssetError::Io { error, .. } => Some(error),
+            AssetError::Load { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<StageError> for AssetError {
+    fn from(error: StageError) -> Self {
+        match error {
+            StageError::Io { file, error } => AssetError::Io { path: file, error },
+            error => AssetError::Invalid {
+                name: error.file().display().to_string(),
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+// Stages are parsed against a set of bullet patterns and a field size, so the same stage file can
+// be cached more than once
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+struct StageKey {
+    name: String,
+    field_half_size: [u32; 2],
+    patterns: Vec<(String, usize)>,
+}
+
+impl StageKey {
+    fn new(
+        name: &str,
+        patterns: &HashMap<String, Rc<BulletPattern>>,
+        field_half_size: Vec2f,
+    ) -> Self {
+        // Patterns are told apart by their address, since the same Rc always holds the same pattern
+        let mut patterns = patterns
+            .iter()
+            .map(|(pattern_name, pattern)| (pattern_name.clone(), Rc::as_ptr(pattern) as usize))
+            .collect::<Vec<_>>();
+        patterns.sort();
+        Self {
+            name: name.to_string(),
+            field_half_size: [field_half_size[0].to_bits(), field_half_size[1].to_bits()],
+            patterns,
+        }
+    }
+}
+
+enum AssetSource {
+    Loose(PathBuf),
+    Packed(PathBuf, Vec<u8>),
+}
+
+impl AssetSource {
+    fn path(&self) -> &Path {
+        match self {
+            AssetSource::Loose(path) => path,
+            AssetSource::Packed(path, _) => path,
+        }
+    }
+}
+
+pub struct Assets {
+    root: PathBuf,
+    archive: Option<AssetArchive>,
+    textures: HashMap<String, Rc<Texture<{ TextureType::Texture2D }>>>,
+    pipelines: HashMap<(String, String, ShaderDefines), Rc<Pipeline>>,
+    stages: HashMap<StageKey, Rc<Stage>>,
+}
+
+impl Assets {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        let mut assets = Self {
+            root: root.as_ref().to_path_buf(),
+            archive: None,
+            textures: HashMap::new(),
+            pipelines: HashMap::new(),
+            stages: HashMap::new(),
+        };
+        assets.open_default_archive();
+        assets
+    }
+
+    fn open_default_archive(&mut self) {
+        // A packed copy of the root sits beside it; without one everything is loaded from loose files
+        self.archive = None;
+        let archive_path = self.root.with_extension(ARCHIVE_EXTENSION);
+        if archive_path.is_file() {
+            if let Err(error) = self.open_archive(&archive_path) {
+                eprintln!("{}; using loose files instead", error);
+            }
+        }
+    }
+
+    pub fn open_archive(&mut self, path: impl AsRef<Path>) -> Result<(), AssetError> {
+        let path = path.as_ref();
+        let archive = AssetArchive::open(path).map_err(|error| AssetError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        self.archive = Some(archive);
+        self.clear();
+        Ok(())
+    }
+
+    pub fn archive(&self) -> Option<&AssetArchive> {
+        self.archive.as_ref()
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn set_root(&mut self, root: impl AsRef<Path>) {
+        // Cached assets came from the old root, so they can't be reused
+        self.root = root.as_ref().to_path_buf();
+        self.open_default_archive();
+        self.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.textures.clear();
+        self.pipelines.clear();
+        self.stages.clear();
+    }
+
+    pub fn resolve(&self, directory: &str, name: &str, extension: &str) -> PathBuf {
+        let mut path = self.root.join(directory).join(name);
+        path.set_extension(extension);
+        path
+    }
+
+    fn find(
+        &self,
+        directory: &str,
+        name: &str,
+        extension: &str,
+    ) -> Result<AssetSource, AssetError> {
+        let path = self.resolve(directory, name, extension);
+
+        // Packed assets win, with loose files filling in anything the archive doesn't have
+        if let Some(archive) = self.archive.as_ref() {
+            let packed_name = format!("{}/{}.{}", directory, name, extension);
+            let bytes = archive.read(&packed_name).map_err(|error| AssetError::Io {
+                path: archive.path().to_path_buf(),
+                error,
+            })?;
+            if let Some(bytes) = bytes {
+                return Ok(AssetSource::Packed(path, bytes));
+            }
+        }
+        if path.is_file() {
+            Ok(AssetSource::Loose(path))
+        } else {
+            Err(AssetError::NotFound {
+                name: name.to_string(),
+                path,
+            })
+        }
+    }
+
+    fn read_to_string(source: AssetSource) -> Result<String, AssetError> {
+        match source {
+            AssetSource::Loose(path) => {
+                fs::read_to_string(&path).map_err(|error| AssetError::Io { path, error })
+            }
+            AssetSource::Packed(path, bytes) => {
+                String::from_utf8(bytes).map_err(|_| AssetError::Invalid {
+                    name: path.display().to_string(),
+                    message: "not valid UTF-8".to_string(),
+                })
+            }
+        }
+    }
+
+    pub fn texture(
+        &mut self,
+        name: &str,
+    ) -> Result<Rc<Texture<{ TextureType::Texture2D }>>, AssetError> {
+        if let Some(texture) = self.textures.get(name) {
+            return Ok(texture.clone());
+        }
+
+        // Only loose textures can be hot reloaded
+        let mut texture = match self.find(TEXTURE_DIRECTORY, name, "png")? {
+            AssetSource::Loose(path) => Texture::try_from_file(&path, image::ImageFormat::Png),
+            AssetSource::Packed(_, bytes) => {
+                Texture::try_from_bytes(&bytes, image::ImageFormat::Png)
+            }
+        }
+        .map_err(|error| AssetError::Load {
+            name: name.to_string(),
+            error,
+        })?;
+
+        // Sprite frames are optional for plain textures
+        match self.find(TEXTURE_DIRECTORY, name, "sheet") {
+            Ok(sheet) => Self::load_sprite_frames(name, sheet, &mut texture)?,
+            Err(AssetError::NotFound { .. }) => (),
+            Err(error) => return Err(error),
+        }
+
+        let texture = Rc::new(texture);
+        watch_for_changes(&texture);
+        self.textures.insert(name.to_string(), texture.clone());
+        Ok(texture)
+    }
+
+    pub fn sprite_sheet(
+        &mut self,
+        name: &str,
+    ) -> Result<Rc<Texture<{ TextureType::Texture2D }>>, AssetError> {
+        // Only check that the sheet exists, since texture() reads it anyway
+        let packed_name = format!("{}/{}.sheet", TEXTURE_DIRECTORY, name);
+        let packed = self
+            .archive
+            .as_ref()
+            .map_or(false, |archive| archive.contains(&packed_name));
+        let path = self.resolve(TEXTURE_DIRECTORY, name, "sheet");
+        if !packed && !path.is_file() {
+            return Err(AssetError::NotFound {
+                name: name.to_string(),
+                path,
+            });
+        }
+        self.texture(name)
+    }
+
+    fn load_sprite_frames(
+        name: &str,
+        sheet: AssetSource,
+        texture: &mut Texture<{ TextureType::Texture2D }>,
+    ) -> Result<(), AssetError> {
+        // Each line names a sprite followed by the x, y, width and height of each of its frames
+        let path = sheet.path().to_path_buf();
+        let source = Self::read_to_string(sheet)?;
+        for (idx, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |message: &str| AssetError::Invalid {
+                name: name.to_string(),
+                message: format!("{}:{}: {}", path.display(), idx + 1, message),
+            };
+            let mut words = line.split_whitespace();
+            let sprite_name = words.next().unwrap();
+            let numbers = words
+                .map(|word| word.parse::<f32>())
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|_| invalid("frame coordinates must be numbers"))?;
+            if numbers.is_empty() || numbers.len() % 4 != 0 {
+                return Err(invalid("expected x, y, width and height for each frame"));
+            }
+            let frames = numbers
+                .chunks(4)
+                .map(|frame| vector!(frame[0], frame[1], frame[2], frame[3]))
+                .collect::<Vec<Vec4f>>();
+            texture.add_sprite_frames(sprite_name, frames);
+        }
+        Ok(())
+    }
+
+    fn shader_extension(stage: ShaderStage) -> &'static str {
+        match stage {
+            ShaderStage::Compute => "comp",
+            ShaderStage::Vertex => "vert",
+            ShaderStage::Fragment => "frag",
+        }
+    }
+
+    pub fn shader_source(&self, name: &str, stage: ShaderStage) -> Result<String, AssetError> {
+        Self::read_to_string(self.find(SHADER_DIRECTORY, name, Self::shader_extension(stage))?)
+    }
+
+    pub fn pipeline(
+        &mut self,
+        vertex_name: &str,
+        fragment_name: &str,
+    ) -> Result<Rc<Pipeline>, AssetError> {
+        self.pipeline_variant(vertex_name, fragment_name, &ShaderDefines::new())
+    }
+
+    pub fn pipeline_variant(
+        &mut self,
+        vertex_name: &str,
+        fragment_name: &str,
+        defines: &ShaderDefines,
+    ) -> Result<Rc<Pipeline>, AssetError> {
+        // Each define set is its own pipeline, compiled the first time it's asked for
+        let key = (
+            vertex_name.to_string(),
+            fragment_name.to_string(),
+            defines.clone(),
+        );
+        if let Some(pipeline) = self.pipelines.get(&key) {
+            return Ok(pipeline.clone());
+        }
+
+        let pipeline = Pipeline::try_new(vec![
+            self.program(vertex_name, ShaderStage::Vertex, defines)?,
+            self.program(fragment_name, ShaderStage::Fragment, defines)?,
+        ])
+        .map_err(|error| AssetError::Load {
+            name: format!("{}+{}", vertex_name, fragment_name),
+            error,
+        })?;
+        let pipeline = Rc::new(pipeline);
+        watch_for_changes(&pipeline);
+        self.pipelines.insert(key, pipeline.clone());
+        Ok(pipeline)
+    }
+
+    fn program(
+        &self,
+        name: &str,
+        stage: ShaderStage,
+        defines: &ShaderDefines,
+    ) -> Result<Program, AssetError> {
+        // Loose programs are built from their files so that they can be hot reloaded
+        let extension = Self::shader_extension(stage);
+        let program = match self.find(SHADER_DIRECTORY, name, extension)? {
+            AssetSource::Loose(path) => Program::try_from_file_variant(stage, path, defines),
+            packed => Program::try_new_variant(stage, Self::read_to_string(packed)?, defines),
+        };
+        program.map_err(|error| AssetError::Load {
+            name: format!("{}.{}", name, extension),
+            error,
+        })
+    }
+
+    pub fn stage_path(&self, name: &str) -> PathBuf {
+        self.resolve(STAGE_DIRECTORY, name, "stage")
+    }
+
+    pub fn stage(
+        &mut self,
+        name: &str,
+        patterns: &HashMap<String, Rc<BulletPattern>>,
+        field_half_size: Vec2f,
+    ) -> Result<Rc<Stage>, AssetError> {
+        let key = StageKey::new(name, patterns, field_half_size);
+        if let Some(stage) = self.stages.get(&key) {
+            return Ok(stage.clone());
+        }
+
+        let stage = match self.find(STAGE_DIRECTORY, name, "stage")? {
+            AssetSource::Loose(path) => Stage::load(&path, patterns, field_half_size)?,
+            packed => {
+                let path = packed.path().to_path_buf();
+                let source = Self::read_to_string(packed)?;
+                Stage::parse(&path, &source, patterns, field_half_size)?
+            }
+        };
+        let stage = Rc::new(stage);
+        self.stages.insert(key, stage.clone());
+        Ok(stage)
+    }
+
+    pub fn forget_stage(&mut self, name: &str) {
+        // The next stage() call reads the file again, whatever it's parsed with
+        self.stages.retain(|key, _| key.name != name);
+    }
+}
+
+impl Default for Assets {
+    fn default() -> Self {
+        Self::new(DEFAULT_ASSET_ROOT)
+    }
+}
@@ -4,6 +4,7 @@ use std::marker::PhantomData;
 use std::mem::size_of;
 use std::ops::{Bound, Index, IndexMut, Range, RangeBounds};
 
+#[derive(Debug)]
 pub struct BufferMap<T: Sized> {
     _phantom_data: PhantomData<T>,
     buffer: IntHandle,
@@ -86,29 +87,16 @@ pub struct Buffer {
 
 impl Buffer {
     pub fn new<T: Sized>(length: GLsizeiptr, allow_map_read: bool, allow_map_write: bool) -> Self {
-        // Choose access flags for what we need
-        let access_flags = choose_access_flags(allow_map_read, allow_map_write);
-
-        // We will receive the buffer's handle in gl_handle
-        let mut gl_handle: IntHandle = 0;
-        unsafe {
-            // Create buffer
-            gl::CreateBuffers(1, &mut gl_handle as *mut _);
-            // Give it sufficient storage for the capacity of T that we need
-            gl::NamedBufferStorage(
-                gl_handle,
-                length * size_of::<T>() as GLsizeiptr,
-                std::ptr::null(),
-                access_flags,
-            );
-        }
+        Self::try_new::<T>(length, allow_map_read, allow_map_write)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        Self {
-            gl_handle,
-            access_flags,
-            length,
-            element_size: size_of::<T>(),
-        }
+    pub fn try_new<T: Sized>(
+        length: GLsizeiptr,
+        allow_map_read: bool,
+        allow_map_write: bool,
+    ) -> BloomResult<Self> {
+        Self::create::<T>(length, std::ptr::null(), allow_map_read, allow_map_write)
     }
 
     pub fn from_slice<T: Sized>(
@@ -116,42 +104,78 @@ impl Buffer {
         allow_map_read: bool,
         allow_map_write: bool,
     ) -> Self {
+        Self::try_from_slice(initial_data, allow_map_read, allow_map_write)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_from_slice<T: Sized>(
+        initial_data: &[T],
+        allow_map_read: bool,
+        allow_map_write: bool,
+    ) -> BloomResult<Self> {
+        Self::create::<T>(
+            initial_data.len() as GLsizeiptr,
+            initial_data.as_ptr() as *const _,
+            allow_map_read,
+            allow_map_write,
+        )
+    }
+
+    pub fn from_iterator<T: Sized>(
+        initial_data: impl IntoIterator<Item = T>,
+        allow_map_read: bool,
+        allow_map_write: bool,
+    ) -> Self {
+        Self::try_from_iterator(initial_data, allow_map_read, allow_map_write)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_from_iterator<T: Sized>(
+        initial_data: impl IntoIterator<Item = T>,
+        allow_map_read: bool,
+        allow_map_write: bool,
+    ) -> BloomResult<Self> {
+        // Make vector from contents of initial_data
+        let data = initial_data.into_iter().collect::<Vec<T>>();
+        // Create a buffer from a full slice of the vector
+        Self::try_from_slice(&data, allow_map_read, allow_map_write)
+    }
+
+    fn create<T: Sized>(
+        length: GLsizeiptr,
+        initial_data: *const std::ffi::c_void,
+        allow_map_read: bool,
+        allow_map_write: bool,
+    ) -> BloomResult<Self> {
         // Choose access flags for what we need
         let access_flags = choose_access_flags(allow_map_read, allow_map_write);
 
+        // Clear out errors from earlier calls so they aren't blamed on this buffer
+        take_gl_error();
+
         // We will receive the buffer's handle in gl_handle
+        let size = length * size_of::<T>() as GLsizeiptr;
         let mut gl_handle: IntHandle = 0;
         unsafe {
             // Create buffer
             gl::CreateBuffers(1, &mut gl_handle as *mut _);
             // Give it sufficient storage for the capacity of T that we need
-            gl::NamedBufferStorage(
-                gl_handle,
-                (initial_data.len() * size_of::<T>()) as GLsizeiptr,
-                initial_data.as_ptr() as *const _,
-                access_flags,
-            );
+            gl::NamedBufferStorage(gl_handle, size, initial_data, access_flags);
         }
 
-        Self {
+        // Wrap the handle first so it gets deleted if the storage couldn't be allocated
+        let buffer = Self {
             gl_handle,
             access_flags,
-            length: initial_data.len() as GLsizeiptr,
+            length,
             element_size: size_of::<T>(),
+        };
+        match take_gl_error() {
+            Some(gl_error) => Err(BloomError::BufferCreation { size, gl_error }),
+            None => Ok(buffer),
         }
     }
 
-    pub fn from_iterator<T: Sized>(
-        initial_data: impl IntoIterator<Item = T>,
-        allow_map_read: bool,
-        allow_map_write: bool,
-    ) -> Self {
-        // Make vector from contents of initial_data
-        let data = initial_data.into_iter().collect::<Vec<T>>();
-        // Create a buffer from a full slice of the vector
-        Self::from_slice(&data, allow_map_read, allow_map_write)
-    }
-
     pub fn length(&self) -> GLsizeiptr {
         self.length
     }
@@ -34,13 +34,17 @@ impl ComputePipeline {
         &mut self.program
     }
 
-    pub fn shader_features(&self) -> &[ShaderFeature] {
+    pub fn shader_features(&self) -> Vec<ShaderFeature> {
         self.program.shader_features()
     }
 
     pub fn has_shader_feature(&self, feature: ShaderFeature) -> bool {
         self.program.shader_features().iter().any(|&e| e == feature)
     }
+
+    pub fn apply_custom_features(&self) {
+        self.program.apply_custom_features();
+    }
 }
 
 impl GLHandle for ComputePipeline {
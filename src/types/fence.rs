@@ -0,0 +1,43 @@
+use crate::*;
+
+#[derive(Debug)]
+pub struct Fence {
+    gl_sync: gl::types::GLsync,
+}
+
+impl Fence {
+    pub fn new() -> Self {
+        // Insert a fence after all GL commands issued so far
+        let gl_sync = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+        if DEBUG && gl_sync.is_null() {
+            panic!("Could not create fence sync object");
+        }
+
+        Self { gl_sync }
+    }
+
+    pub fn is_signaled(&self) -> bool {
+        // Check the fence without waiting, flushing so that it is guaranteed to signal eventually
+        let status =
+            unsafe { gl::ClientWaitSync(self.gl_sync, gl::SYNC_FLUSH_COMMANDS_BIT, 0) };
+        match status {
+            gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => true,
+            gl::TIMEOUT_EXPIRED => false,
+            _ => panic!("Waiting on fence failed with status {}", status),
+        }
+    }
+}
+
+impl Default for Fence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        if !self.gl_sync.is_null() {
+            unsafe { gl::DeleteSync(self.gl_sync) };
+        }
+    }
+}
@@ -12,6 +12,7 @@ pub struct Game {
     window: Window,
     gfx: GFX,
     input: Input,
+    assets: Assets,
     start_instant: Instant,
     previous_frame_instant: Instant,
     smoothed_framerate: f64,
@@ -25,7 +26,13 @@ impl Game {
         let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
 
         // Create window
-        let mut window = Window::new(&mut glfw, vector!(2560, 1440), "Hello, world!");
+        let mut window = match Window::try_new(&mut glfw, vector!(2560, 1440), "Hello, world!") {
+            Ok(window) => window,
+            Err(error) => {
+                eprintln!("{}", error);
+                return;
+            }
+        };
 
         // Create GFX object
         let gfx = GFX::new(&mut window);
@@ -47,13 +54,18 @@ impl Game {
             window,
             gfx,
             input,
+            assets: Assets::default(),
             start_instant,
             previous_frame_instant: start_instant,
             smoothed_framerate: 0.0,
             task_schedule: Some(task_schedule),
-            current_scene: Some(Box::new(ShooterScene::new(0.0))),
+            current_scene: None,
         };
 
+        // Create the first scene
+        let scene = ShooterScene::new(&mut game.assets, 0.0);
+        game.current_scene = Some(Box::new(scene));
+
         // Start the update loop
         game.update_loop();
     }
@@ -114,6 +126,11 @@ impl Game {
             self.window
                 .set_title(format!("FPS: {:.1}", self.smoothed_framerate));
 
+            // Reload any watched assets whose files changed
+            if DEBUG {
+                poll_hot_reload();
+            }
+
             // Do tasks
             // Swap out task schedule so that we can borrow this game object while executing tasks
             let mut temp_ts = None;
@@ -156,6 +173,14 @@ impl Game {
         &mut self.input
     }
 
+    pub fn assets(&self) -> &Assets {
+        &self.assets
+    }
+
+    pub fn assets_mut(&mut self) -> &mut Assets {
+        &mut self.assets
+    }
+
     pub fn gfx(&self) -> &GFX {
         &self.gfx
     }
@@ -146,6 +146,9 @@ impl GFX {
                     .set_uniform_mat4f(transform_uniform, &mats);
             }
 
+            // Let features registered by the application set their own uniforms
+            material.pipeline().apply_custom_features();
+
             // Draw using the attached buffers
             gl::DrawElementsInstanced(
                 primitive_type.gl_primitive_mode(),
@@ -221,6 +224,9 @@ impl GFX {
                     .set_uniform_mat4f(transform_uniform, &mats);
             }
 
+            // Let features registered by the application set their own uniforms
+            material.pipeline().apply_custom_features();
+
             // Draw using the attached buffers
             gl::DrawElementsInstanced(
                 primitive_type.gl_primitive_mode(),
@@ -309,6 +315,31 @@ impl GFX {
         }
     }
 
+    pub fn blend(&mut self, enabled: bool) {
+        if enabled {
+            unsafe {
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            }
+        } else {
+            unsafe {
+                gl::Disable(gl::BLEND);
+            }
+        }
+    }
+
+    pub fn primitive_restart(&mut self, enabled: bool) {
+        if enabled {
+            unsafe {
+                gl::Enable(gl::PRIMITIVE_RESTART_FIXED_INDEX);
+            }
+        } else {
+            unsafe {
+                gl::Disable(gl::PRIMITIVE_RESTART_FIXED_INDEX);
+            }
+        }
+    }
+
     pub fn dispatch_compute_1d(
         &mut self,
         mut compute_pipeline: impl std::ops::DerefMut<Target = ComputePipeline>,
@@ -325,6 +356,7 @@ impl GFX {
         unsafe {
             gl::BindProgramPipeline(compute_pipeline.handle());
         }
+        compute_pipeline.apply_custom_features();
         let batch_offset_location = compute_pipeline
             .program()
             .uniform_location(FEATURE_BATCH_UNIFORM_NAME);
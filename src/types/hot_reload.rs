@@ -0,0 +1,88 @@
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use std::time::{Duration, Instant, SystemTime};
+
+pub const HOT_RELOAD_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+pub struct FileWatch {
+    path: PathBuf,
+    modified: Cell<Option<SystemTime>>,
+    next_poll: Cell<Instant>,
+}
+
+impl FileWatch {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let modified = Self::modified_time(&path);
+        Self {
+            path,
+            modified: Cell::new(modified),
+            next_poll: Cell::new(Instant::now() + HOT_RELOAD_POLL_INTERVAL),
+        }
+    }
+
+    fn modified_time(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn changed(&self) -> bool {
+        // Only touch the file system every so often, however often this is called
+        let now = Instant::now();
+        if now < self.next_poll.get() {
+            return false;
+        }
+        self.next_poll.set(now + HOT_RELOAD_POLL_INTERVAL);
+
+        // A file that is missing (for example half way through being saved) isn't a change
+        let modified = Self::modified_time(&self.path);
+        if modified.is_some() && modified != self.modified.get() {
+            self.modified.set(modified);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+pub trait HotReload {
+    // Reloads the asset if its file changed, returning whether it did. On error the asset
+    // must keep using the last version that loaded successfully.
+    fn hot_reload(&self) -> Result<bool, String>;
+}
+
+thread_local! {
+    static WATCHED_ASSETS: RefCell<Vec<Weak<dyn HotReload>>> = RefCell::new(Vec::new());
+}
+
+pub fn watch_for_changes<T: HotReload + 'static>(asset: &Rc<T>) {
+    let asset: Rc<dyn HotReload> = asset.clone();
+    WATCHED_ASSETS.with(|watched| watched.borrow_mut().push(Rc::downgrade(&asset)));
+}
+
+pub fn poll_hot_reload() {
+    // Take the list out so assets can be watched while others are reloading
+    let mut assets = WATCHED_ASSETS.with(|watched| watched.replace(Vec::new()));
+    assets.retain(|asset| match asset.upgrade() {
+        Some(asset) => {
+            if let Err(error) = asset.hot_reload() {
+                eprintln!("\x1B[35m{}\x1B[37m", error);
+            }
+            true
+        }
+        None => false,
+    });
+    WATCHED_ASSETS.with(|watched| {
+        let mut watched = watched.borrow_mut();
+        assets.append(&mut watched);
+        *watched = assets;
+    });
+}
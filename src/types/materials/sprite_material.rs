@@ -3,39 +3,6 @@ use fennec_algebra::*;
 use std::cell::RefCell;
 use std::rc::Rc;
 
-const VERTEX_SHADER: &str = "
-#[feature(camera)]
-layout(location = 0) in mat4 i_matrix;
-layout(location = 4) in vec4 i_rectangle;
-layout(location = 5) in vec2 v_position;
-layout(location = 6) in vec2 v_texCoord;
-
-layout(location = 0) out vec2 f_texCoord;
-layout(location = 1) out vec4 f_rectangle;
-
-out gl_PerVertex { vec4 gl_Position; };
-
-void main()
-{
-    f_texCoord = v_texCoord;
-    f_rectangle = i_rectangle;
-    gl_Position = applyProjection(applyView(i_matrix * vec4(v_position, 0.0, 1.0)));
-}";
-
-const FRAGMENT_SHADER: &str = "
-layout(location = 0) in vec2 f_texCoord;
-layout(location = 1) in vec4 f_rectangle;
-
-layout(location = 0) out vec4 out_color;
-
-uniform sampler2D u_texture;
-
-void main()
-{
-    vec2 texCoord = vec2(0.0, 1.0) + (f_rectangle.xy + f_texCoord * f_rectangle.zw) * vec2(1.0, -1.0);
-    out_color = texture(u_texture, texCoord);
-}";
-
 #[derive(Clone, Debug)]
 pub struct SpriteMaterial {
     pipeline: Rc<Pipeline>,
@@ -47,14 +14,7 @@ pub struct SpriteMaterial {
 }
 
 impl SpriteMaterial {
-    pub fn new() -> Self {
-        let stages = vec![
-            Program::new(ShaderStage::Vertex, VERTEX_SHADER),
-            Program::new(ShaderStage::Fragment, FRAGMENT_SHADER),
-        ];
-
-        let pipeline = Rc::new(Pipeline::new(stages));
-
+    pub fn new(pipeline: Rc<Pipeline>) -> Self {
         let vertices = [
             Pos2TexVertex::new(vector!(-0.5, -0.5), vector!(0.0, 0.0)),
             Pos2TexVertex::new(vector!(0.5, -0.5), vector!(1.0, 0.0)),
@@ -122,9 +82,3 @@ impl Material for SpriteMaterial {
         }
     }
 }
-
-impl Default for SpriteMaterial {
-    fn default() -> Self {
-        Self::new()
-    }
-}
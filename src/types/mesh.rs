@@ -6,6 +6,7 @@ use crate::*;
 #[derive(Clone, Copy, Debug, Hash, PartialEq)]
 pub enum PrimitiveType {
     TriangleList,
+    TriangleStrip,
     PointList,
     LineList,
 }
@@ -14,6 +15,7 @@ impl PrimitiveType {
     pub fn gl_primitive_mode(self) -> GLenum {
         match self {
             PrimitiveType::TriangleList => gl::TRIANGLES,
+            PrimitiveType::TriangleStrip => gl::TRIANGLE_STRIP,
             PrimitiveType::PointList => gl::POINTS,
             PrimitiveType::LineList => gl::LINES,
         }
@@ -16,6 +16,9 @@ pub use framebuffer::*;
 mod buffer;
 pub use buffer::*;
 
+mod fence;
+pub use fence::*;
+
 mod vertex_array;
 pub use vertex_array::*;
 
@@ -25,12 +28,31 @@ pub use materials::*;
 mod vertices;
 pub use vertices::*;
 
+mod hot_reload;
+pub use hot_reload::*;
+
+mod asset_archive;
+pub use asset_archive::*;
+
+mod assets;
+pub use assets::*;
+
+mod shader_library;
+pub use shader_library::*;
+
 mod program;
 pub use program::*;
 
+mod program_cache;
+pub use program_cache::*;
+
+mod uniform;
+pub use uniform::*;
+
 mod pipeline;
 pub use pipeline::*;
 
+
 mod compute_pipeline;
 pub use compute_pipeline::*;
 
@@ -52,5 +74,11 @@ pub use sprite_list::*;
 mod sprite_animator;
 pub use sprite_animator::*;
 
+mod motion_program;
+pub use motion_program::*;
+
 mod sprite_object;
-pub use sprite_object::*;
\ No newline at end of file
+pub use sprite_object::*;
+
+mod sprite_collider;
+pub use sprite_collider::*;
\ No newline at end of file
@@ -0,0 +1,320 @@
+use crate::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub const MAX_MOTION_PROGRAMS: usize = 256;
+pub const MAX_MOTION_STEPS: usize = 4096;
+const FOREVER: f32 = -1.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MotionKind {
+    Wait,
+    Accelerate,
+    Turn,
+    Stop,
+    Aim,
+    Home,
+    Bounce,
+    Orbit,
+}
+
+impl MotionKind {
+    pub fn gl_id(self) -> u32 {
+        match self {
+            MotionKind::Wait => 0,
+            MotionKind::Accelerate => 1,
+            MotionKind::Turn => 2,
+            MotionKind::Stop => 3,
+            MotionKind::Aim => 4,
+            MotionKind::Home => 5,
+            MotionKind::Bounce => 6,
+            MotionKind::Orbit => 7,
+        }
+    }
+
+    pub fn from_gl_id(id: u32) -> Self {
+        match id {
+            0 => MotionKind::Wait,
+            1 => MotionKind::Accelerate,
+            2 => MotionKind::Turn,
+            3 => MotionKind::Stop,
+            4 => MotionKind::Aim,
+            5 => MotionKind::Home,
+            6 => MotionKind::Bounce,
+            7 => MotionKind::Orbit,
+            _ => panic!("Unknown motion kind {}", id),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MotionStep {
+    kind: u32,
+    duration: f32,
+    param0: f32,
+    param1: f32,
+}
+
+impl MotionStep {
+    fn new(kind: MotionKind, duration: Option<f32>, param0: f32, param1: f32) -> Self {
+        if DEBUG && duration.map_or(false, |duration| duration < 0.0) {
+            panic!("Motion step duration must not be negative");
+        }
+        Self {
+            kind: kind.gl_id(),
+            duration: duration.unwrap_or(FOREVER),
+            param0,
+            param1,
+        }
+    }
+
+    pub fn wait(duration: f32) -> Self {
+        Self::new(MotionKind::Wait, Some(duration), 0.0, 0.0)
+    }
+
+    pub fn accelerate(duration: Option<f32>, acceleration: f32, target_speed: f32) -> Self {
+        if DEBUG && target_speed < 0.0 {
+            panic!("Target speed must not be negative");
+        }
+        Self::new(MotionKind::Accelerate, duration, acceleration, target_speed)
+    }
+
+    pub fn turn(duration: Option<f32>, angular_speed: f32) -> Self {
+        Self::new(MotionKind::Turn, duration, angular_speed, 0.0)
+    }
+
+    pub fn stop() -> Self {
+        Self::new(MotionKind::Stop, Some(0.0), 0.0, 0.0)
+    }
+
+    pub fn aim(speed: Option<f32>) -> Self {
+        if DEBUG && speed.map_or(false, |speed| speed < 0.0) {
+            panic!("Aim speed must not be negative");
+        }
+        Self::new(MotionKind::Aim, Some(0.0), speed.unwrap_or(-1.0), 0.0)
+    }
+
+    pub fn home(duration: Option<f32>, max_angular_speed: f32) -> Self {
+        if DEBUG && max_angular_speed < 0.0 {
+            panic!("Maximum angular speed must not be negative");
+        }
+        Self::new(MotionKind::Home, duration, max_angular_speed, 0.0)
+    }
+
+    pub fn bounce(duration: Option<f32>) -> Self {
+        Self::new(MotionKind::Bounce, duration, 0.0, 0.0)
+    }
+
+    pub fn orbit(duration: Option<f32>, angular_speed: f32) -> Self {
+        Self::new(MotionKind::Orbit, duration, angular_speed, 0.0)
+    }
+
+    pub fn kind(&self) -> MotionKind {
+        MotionKind::from_gl_id(self.kind)
+    }
+
+    pub fn duration(&self) -> Option<f32> {
+        if self.duration < 0.0 {
+            None
+        } else {
+            Some(self.duration)
+        }
+    }
+
+    pub fn param0(&self) -> f32 {
+        self.param0
+    }
+
+    pub fn param1(&self) -> f32 {
+        self.param1
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct MotionProgram {
+    steps: Vec<MotionStep>,
+}
+
+impl MotionProgram {
+    pub fn new() -> Self {
+        Self { steps: Vec::new() }
+    }
+
+    pub fn then(mut self, step: MotionStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn steps(&self) -> &[MotionStep] {
+        &self.steps
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MotionProgramReference {
+    idx: u32,
+}
+
+impl MotionProgramReference {
+    pub fn from_idx(idx: u32) -> Self {
+        Self { idx }
+    }
+
+    pub fn idx(&self) -> u32 {
+        self.idx
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct MotionProgramRange {
+    start: u32,
+    count: u32,
+}
+
+#[derive(Debug)]
+pub struct MotionProgramTable {
+    steps: Vec<MotionStep>,
+    ranges: Vec<MotionProgramRange>,
+    buffers: Option<MotionProgramBuffers>,
+}
+
+#[derive(Debug)]
+struct MotionProgramBuffers {
+    step_buffer: Rc<RefCell<Buffer>>,
+    range_buffer: Rc<RefCell<Buffer>>,
+}
+
+impl MotionProgramTable {
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            ranges: Vec::new(),
+            buffers: Some(MotionProgramBuffers {
+                step_buffer: Rc::new(RefCell::new(Buffer::new::<MotionStep>(
+                    MAX_MOTION_STEPS as GLsizeiptr,
+                    false,
+                    true,
+                ))),
+                range_buffer: Rc::new(RefCell::new(Buffer::new::<MotionProgramRange>(
+                    MAX_MOTION_PROGRAMS as GLsizeiptr,
+                    false,
+                    true,
+                ))),
+            }),
+        }
+    }
+
+    pub fn without_buffers() -> Self {
+        // Enough for integrating on the CPU, which never reads the programs from the GPU
+        Self {
+            steps: Vec::new(),
+            ranges: Vec::new(),
+            buffers: None,
+        }
+    }
+
+    pub fn register(&mut self, program: &MotionProgram) -> MotionProgramReference {
+        if program.steps().is_empty() {
+            panic!("Motion program must have at least one step");
+        }
+        if self.ranges.len() >= MAX_MOTION_PROGRAMS {
+            panic!(
+                "No more than {} motion programs can be registered",
+                MAX_MOTION_PROGRAMS
+            );
+        }
+        if self.steps.len() + program.steps().len() > MAX_MOTION_STEPS {
+            panic!(
+                "No more than {} motion steps can be registered",
+                MAX_MOTION_STEPS
+            );
+        }
+
+        // Append the program's steps and its range
+        let range = MotionProgramRange {
+            start: self.steps.len() as u32,
+            count: program.steps().len() as u32,
+        };
+        self.steps.extend_from_slice(program.steps());
+        self.ranges.push(range);
+
+        // Upload the new steps and range
+        if let Some(buffers) = self.buffers.as_ref() {
+            let step_buffer = buffers.step_buffer.borrow();
+            let mut mapped = step_buffer
+                .map(range.start as GLsizeiptr..(range.start + range.count) as GLsizeiptr);
+            program
+                .steps()
+                .iter()
+                .enumerate()
+                .for_each(|(idx, &step)| mapped[idx] = step);
+
+            let range_idx = self.ranges.len() as GLsizeiptr - 1;
+            let range_buffer = buffers.range_buffer.borrow();
+            let mut mapped = range_buffer.map(range_idx..range_idx + 1);
+            mapped[0] = range;
+        }
+
+        MotionProgramReference {
+            idx: self.ranges.len() as u32 - 1,
+        }
+    }
+
+    pub fn program_steps(&self, program: MotionProgramReference) -> &[MotionStep] {
+        let range = self.ranges[program.idx as usize];
+        &self.steps[range.start as usize..(range.start + range.count) as usize]
+    }
+
+    pub fn step_buffer(&self) -> &Rc<RefCell<Buffer>> {
+        &self
+            .buffers
+            .as_ref()
+            .expect("Motion program table has no buffers")
+            .step_buffer
+    }
+
+    pub fn range_buffer(&self) -> &Rc<RefCell<Buffer>> {
+        &self
+            .buffers
+            .as_ref()
+            .expect("Motion program table has no buffers")
+            .range_buffer
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MotionEnvironment<'a> {
+    table: &'a MotionProgramTable,
+    aim_target: Vec2f,
+    bounds_half_size: Vec2f,
+}
+
+impl<'a> MotionEnvironment<'a> {
+    pub fn new(table: &'a MotionProgramTable, aim_target: Vec2f, bounds_half_size: Vec2f) -> Self {
+        Self {
+            table,
+            aim_target,
+            bounds_half_size,
+        }
+    }
+
+    pub fn table(&self) -> &'a MotionProgramTable {
+        self.table
+    }
+
+    pub fn aim_target(&self) -> Vec2f {
+        self.aim_target
+    }
+
+    pub fn bounds_half_size(&self) -> Vec2f {
+        self.bounds_half_size
+    }
+}
+
+impl Default for MotionProgramTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
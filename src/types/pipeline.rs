@@ -8,6 +8,10 @@ pub struct Pipeline {
 
 impl Pipeline {
     pub fn new(stages: impl IntoIterator<Item = Program>) -> Self {
+        Self::try_new(stages).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_new(stages: impl IntoIterator<Item = Program>) -> BloomResult<Self> {
         // Gather the stages (programs) into a vector for storing later
         let stages = stages.into_iter().collect::<Vec<Program>>();
 
@@ -20,7 +24,42 @@ impl Pipeline {
             unsafe { gl::UseProgramStages(gl_handle, stage.stage().stage_bit(), stage.handle()) };
         }
 
-        Self { gl_handle, stages }
+        // Validation depends on the current GL state and is slow, so only debug builds check that the
+        // stages fit together. Dropping the pipeline deletes the handle if they don't
+        let pipeline = Self { gl_handle, stages };
+        if DEBUG {
+            pipeline.validate()?;
+        }
+        Ok(pipeline)
+    }
+
+    fn validate(&self) -> BloomResult<()> {
+        let mut status = 0;
+        let mut length = 0;
+        unsafe {
+            gl::ValidateProgramPipeline(self.gl_handle);
+            gl::GetProgramPipelineiv(self.gl_handle, gl::VALIDATE_STATUS, &mut status);
+            gl::GetProgramPipelineiv(self.gl_handle, gl::INFO_LOG_LENGTH, &mut length);
+        }
+        if status == gl::TRUE as GLint {
+            return Ok(());
+        }
+
+        // Fetch the reason from the info log
+        let mut info_log = vec![0u8; length.max(1) as usize];
+        let mut written = 0;
+        unsafe {
+            gl::GetProgramPipelineInfoLog(
+                self.gl_handle,
+                info_log.len() as GLsizei,
+                &mut written,
+                info_log.as_mut_ptr() as *mut GLchar,
+            )
+        };
+        info_log.truncate(written.max(0) as usize);
+        Err(BloomError::PipelineValidation(
+            String::from_utf8_lossy(&info_log).into_owned(),
+        ))
     }
 
     pub fn vertex_program(&self) -> &Program {
@@ -72,20 +111,78 @@ impl Pipeline {
     pub fn shader_features(&self) -> Vec<ShaderFeature> {
         self.stages
             .iter()
-            .map(|stage| stage.shader_features().iter().cloned())
+            .map(|stage| stage.shader_features())
             .flatten()
             .collect()
     }
 
+    pub fn set_uniform<T: UniformValue + ?Sized>(
+        &self,
+        name: impl AsRef<str>,
+        value: &T,
+    ) -> BloomResult<()> {
+        // Set the uniform in every stage that declares it
+        let name = name.as_ref();
+        let mut found = false;
+        for stage in self
+            .stages
+            .iter()
+            .filter(|stage| stage.uniform(name).is_some())
+        {
+            stage.set_uniform(name, value)?;
+            found = true;
+        }
+        if found {
+            Ok(())
+        } else {
+            Err(BloomError::UnknownUniform(name.to_string()))
+        }
+    }
+
+    pub fn apply_custom_features(&self) {
+        for stage in self.stages.iter() {
+            stage.apply_custom_features();
+        }
+    }
+
     pub fn has_shader_feature(&self, feature: ShaderFeature) -> bool {
         self.stages
             .iter()
-            .map(|stage| stage.shader_features().iter().cloned())
+            .map(|stage| stage.shader_features())
             .flatten()
             .any(|e| e == feature)
     }
 }
 
+impl HotReload for Pipeline {
+    fn hot_reload(&self) -> Result<bool, String> {
+        // Point the pipeline at whichever stages were replaced
+        let mut reloaded = false;
+        let mut errors = Vec::new();
+        for stage in self.stages.iter() {
+            match stage.hot_reload() {
+                Ok(true) => {
+                    unsafe {
+                        gl::UseProgramStages(
+                            self.gl_handle,
+                            stage.stage().stage_bit(),
+                            stage.handle(),
+                        )
+                    };
+                    reloaded = true;
+                }
+                Ok(false) => (),
+                Err(error) => errors.push(error),
+            }
+        }
+        if errors.is_empty() {
+            Ok(reloaded)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
 impl GLHandle for Pipeline {
     fn handle(&self) -> IntHandle {
         self.gl_handle
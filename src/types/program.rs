@@ -1,9 +1,14 @@
 use crate::*;
 use lazy_static::*;
 use regex::*;
-use std::ffi::{CStr, CString};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::CString;
+use std::path::Path;
+use std::rc::Rc;
 
 const MAX_PROGRAM_INFO_LOG_SIZE: usize = 1024;
+const ERROR_CONTEXT_LINES: usize = 2;
 const VERSION_NUMBER: &str = "#version 450";
 pub const FEATURE_CAMERA_VIEW_UNIFORM_NAME: &str = "_u_view";
 pub const FEATURE_CAMERA_PROJECTION_UNIFORM_NAME: &str = "_u_projection";
@@ -18,23 +23,42 @@ pub enum ShaderFeature {
     Noise,
     Batch,
     DeltaTime,
+    Custom(CustomShaderFeatureId),
 }
 
 impl ShaderFeature {
-    fn from_name(name: impl AsRef<str>) -> Self {
-        let name = name.as_ref();
-        match name {
-            "camera" => ShaderFeature::Camera,
-            "transform" => ShaderFeature::Transform,
-            "noise" => ShaderFeature::Noise,
-            "batch" => ShaderFeature::Batch,
-            "deltaTime" => ShaderFeature::DeltaTime,
-            _ => panic!("Unknown shader feature {:?}", name),
+    pub fn built_in_from_name(name: impl AsRef<str>) -> Option<Self> {
+        match name.as_ref() {
+            "camera" => Some(ShaderFeature::Camera),
+            "transform" => Some(ShaderFeature::Transform),
+            "noise" => Some(ShaderFeature::Noise),
+            "batch" => Some(ShaderFeature::Batch),
+            "deltaTime" => Some(ShaderFeature::DeltaTime),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            ShaderFeature::Camera => "camera".to_string(),
+            ShaderFeature::Transform => "transform".to_string(),
+            ShaderFeature::Noise => "noise".to_string(),
+            ShaderFeature::Batch => "batch".to_string(),
+            ShaderFeature::DeltaTime => "deltaTime".to_string(),
+            ShaderFeature::Custom(id) => custom_shader_feature(id).name().to_string(),
         }
     }
 
+    fn try_from_name(name: impl AsRef<str>) -> BloomResult<Self> {
+        let name = name.as_ref();
+        Self::built_in_from_name(name)
+            .or_else(|| find_shader_feature(name))
+            .ok_or_else(|| BloomError::UnknownShaderFeature(name.to_string()))
+    }
+
     fn inserted_code(self) -> String {
         match self {
+            ShaderFeature::Custom(id) => custom_shader_feature(id).code().to_string(),
             ShaderFeature::Camera => format!(
                 "
 uniform mat4 {0};
@@ -166,42 +190,418 @@ float deltaTime() {{
     }
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ShaderDefines {
+    values: BTreeMap<String, String>,
+}
+
+impl ShaderDefines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_define(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    pub fn with_flag(self, name: impl Into<String>) -> Self {
+        self.with_define(name, 1)
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: impl ToString) {
+        self.values.insert(name.into(), value.to_string());
+    }
+
+    pub fn get(&self, name: impl AsRef<str>) -> Option<&str> {
+        self.values.get(name.as_ref()).map(String::as_str)
+    }
+
+    pub fn is_enabled(&self, name: impl AsRef<str>) -> bool {
+        match self.get(name) {
+            Some(value) => value != "0" && value != "false",
+            None => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Hash)]
 pub enum ShaderDirective {
     Features(Vec<String>),
+    Include(String),
+    Define(String, String),
+    If(String),
+    Else,
+    EndIf,
 }
 
 impl ShaderDirective {
-    #[allow(clippy::match_single_binding)]
-    fn from_name(name: impl AsRef<str>) -> Self {
+    fn parse(directive: &str) -> BloomResult<Self> {
+        // Compiled regexes
+        lazy_static! {
+            static ref RE_NAME_ARGS: Regex = Regex::new(r"#\[(.*?)\((.*?)\)").unwrap();
+            static ref RE_NAME: Regex = Regex::new(r"#\[(.*?)\]").unwrap();
+        }
+
+        // Check the formatting of the directive
+        if let Some(name_args) = RE_NAME_ARGS.captures(directive) {
+            // DIRECTIVENAME(ARGS)
+            // Get the name
+            let name = &directive[name_args.get(1).unwrap().range()];
+
+            // Get the args in the parentheses, separated by commas
+            let args = directive[name_args.get(2).unwrap().range()]
+                .split(',')
+                .map(|arg| arg.trim())
+                .collect();
+
+            // Create a ShaderDirective object for this directive
+            Self::try_from_name_args(name, args)
+        } else {
+            // DIRECTIVENAME or other
+            // Create a ShaderDirective object for this directive
+            let name = match RE_NAME.captures(directive) {
+                Some(name) => &directive[name.get(1).unwrap().range()],
+                None => directive,
+            };
+            Self::try_from_name(name)
+        }
+    }
+
+    fn try_from_name(name: impl AsRef<str>) -> BloomResult<Self> {
         let name = name.as_ref().trim();
+        let mut words = name.split_whitespace();
 
-        match name {
-            _ => panic!("Unknown shader compiler directive {:?}", name),
+        match (words.next(), words.next(), words.next()) {
+            (Some("if"), Some(condition), None) => Ok(ShaderDirective::If(condition.to_string())),
+            (Some("else"), None, None) => Ok(ShaderDirective::Else),
+            (Some("endif"), None, None) => Ok(ShaderDirective::EndIf),
+            _ => Err(BloomError::UnknownShaderDirective(name.to_string())),
         }
     }
 
-    fn from_name_args(name: impl AsRef<str>, mut args: Vec<impl Into<String>>) -> Self {
+    fn try_from_name_args(
+        name: impl AsRef<str>,
+        mut args: Vec<impl Into<String>>,
+    ) -> BloomResult<Self> {
         let name = name.as_ref().trim();
         let args = args.drain(..).map(|a| a.into()).collect::<Vec<String>>();
 
         match name {
-            "feature" => ShaderDirective::Features(args),
-            _ => panic!("Unknown shader compiler directive {:?}", name),
+            "feature" => Ok(ShaderDirective::Features(args)),
+            "include" => match args.as_slice() {
+                [include] if !include.is_empty() => Ok(ShaderDirective::Include(include.clone())),
+                _ => Err(BloomError::InvalidShaderDirective(format!(
+                    "#[include] takes one snippet name but was given {:?}",
+                    args
+                ))),
+            },
+            "define" => match args.as_slice() {
+                [define] if !define.is_empty() => {
+                    Ok(ShaderDirective::Define(define.clone(), "1".to_string()))
+                }
+                [define, value] if !define.is_empty() => {
+                    Ok(ShaderDirective::Define(define.clone(), value.clone()))
+                }
+                _ => Err(BloomError::InvalidShaderDirective(format!(
+                    "#[define] takes a name and an optional value but was given {:?}",
+                    args
+                ))),
+            },
+            _ => Err(BloomError::UnknownShaderDirective(name.to_string())),
         }
     }
+}
 
-    fn inserted_code(&self) -> Option<String> {
-        match self {
-            ShaderDirective::Features(features) => {
-                let mut inserted = String::new();
-                for feature in features {
-                    inserted.push_str(&ShaderFeature::from_name(feature).inserted_code());
-                    inserted.push('\n');
+#[derive(Clone, Debug)]
+struct ShaderLineOrigin {
+    source: Rc<str>,
+    line: usize,
+}
+
+// Expanded shader code along with the file (or snippet) and line each of its lines came from
+#[derive(Default)]
+struct MappedShaderCode {
+    code: String,
+    line_count: usize,
+    origins: Vec<ShaderLineOrigin>,
+}
+
+impl MappedShaderCode {
+    fn as_str(&self) -> &str {
+        &self.code
+    }
+
+    fn push(&mut self, text: &str, source: &Rc<str>, first_line: usize) {
+        for (idx, segment) in text.split('\n').enumerate() {
+            if idx > 0 {
+                self.claim_line(source, first_line + idx - 1);
+                self.code.push('\n');
+                self.line_count += 1;
+            }
+            if !segment.is_empty() {
+                self.claim_line(source, first_line + idx);
+                self.code.push_str(segment);
+            }
+        }
+    }
+
+    fn append(&mut self, other: MappedShaderCode) {
+        // Only called at the start of a line, so the other code's lines carry straight over
+        self.code.push_str(&other.code);
+        self.line_count += other.line_count;
+        self.origins.extend(other.origins);
+    }
+
+    fn claim_line(&mut self, source: &Rc<str>, line: usize) {
+        // A line belongs to whatever put the first thing on it
+        if self.origins.len() == self.line_count {
+            self.origins.push(ShaderLineOrigin {
+                source: source.clone(),
+                line,
+            });
+        }
+    }
+
+    fn origin(&self, line: usize) -> Option<&ShaderLineOrigin> {
+        self.origins.get(line.checked_sub(1)?)
+    }
+
+    fn annotate_log(&self, log: &str) -> String {
+        // Compiled regexes
+        lazy_static! {
+            // Matches the location at the start of messages from the common drivers, for example
+            // "0(12) : error", "0:12(5): error" and "ERROR: 0:12: error"
+            static ref RE_LOG_LOCATION: Regex =
+                Regex::new(r"^((?:ERROR|WARNING): )?\d+[:(](\d+)\)?(?:\(\d+\))?").unwrap();
+        }
+
+        // Point each message at the original line, followed by the code around it
+        let code_lines = self.code.split('\n').collect::<Vec<&str>>();
+        let mut annotated = String::new();
+        for message in log.lines() {
+            let location = RE_LOG_LOCATION.captures(message).and_then(|location| {
+                let line = location[2].parse::<usize>().ok()?;
+                Some((location, self.origin(line)?, line))
+            });
+            let (location, origin, line) = match location {
+                Some(location) => location,
+                None => {
+                    annotated.push_str(message);
+                    annotated.push('\n');
+                    continue;
                 }
-                Some(inserted)
-            } //_ => None,
+            };
+            annotated.push_str(&format!(
+                "{}{}:{}{}\n",
+                location.get(1).map_or("", |prefix| prefix.as_str()),
+                origin.source,
+                origin.line,
+                &message[location.get(0).unwrap().end()..]
+            ));
+            let first = line.saturating_sub(ERROR_CONTEXT_LINES).max(1);
+            let last = (line + ERROR_CONTEXT_LINES).min(code_lines.len());
+            for context_line in first..=last {
+                let original_line = match self.origin(context_line) {
+                    Some(context) if context.source == origin.source => context.line.to_string(),
+                    _ => String::new(),
+                };
+                annotated.push_str(&format!(
+                    "{} {:>5} | {}\n",
+                    if context_line == line { '>' } else { ' ' },
+                    original_line,
+                    code_lines[context_line - 1]
+                ));
+            }
         }
+        annotated
+    }
+}
+
+struct ShaderCondition {
+    active: bool,
+    parent_active: bool,
+    has_else: bool,
+    line: usize,
+}
+
+#[derive(Default)]
+struct ShaderPreprocessor {
+    features: Vec<ShaderFeature>,
+    defines: ShaderDefines,
+    conditions: Vec<ShaderCondition>,
+    included: HashSet<String>,
+    include_stack: Vec<String>,
+}
+
+impl ShaderPreprocessor {
+    fn new(defines: &ShaderDefines) -> Self {
+        Self {
+            defines: defines.clone(),
+            ..Self::default()
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.conditions
+            .last()
+            .map_or(true, |condition| condition.active)
+    }
+
+    fn evaluate(&self, condition: &str) -> bool {
+        match condition.strip_prefix('!') {
+            Some(name) => !self.defines.is_enabled(name.trim()),
+            None => self.defines.is_enabled(condition),
+        }
+    }
+
+    fn expand(
+        &mut self,
+        code: &str,
+        source: &Rc<str>,
+        expanded: &mut MappedShaderCode,
+    ) -> BloomResult<()> {
+        // Compiled regexes
+        lazy_static! {
+            static ref RE_DIRECTIVE: Regex = Regex::new(r"#\[(.*?)\]").unwrap();
+        }
+
+        // Remove carriage returns and version number
+        let code = code.replace("\r", "").replace(VERSION_NUMBER, "");
+
+        // Conditions have to be closed in the same file or snippet that opened them
+        let base_depth = self.conditions.len();
+
+        // Copy the code over, replacing each directive with what it expands to
+        let mut copied_to = 0;
+        let mut line = 1;
+        for mat in RE_DIRECTIVE.find_iter(&code) {
+            let skipped = &code[copied_to..mat.start()];
+            if self.is_active() {
+                expanded.push(skipped, source, line);
+            }
+            line += skipped.matches('\n').count();
+            copied_to = mat.end();
+            self.apply_directive(mat.as_str(), base_depth, source, line, expanded)
+                .map_err(|error| match error {
+                    error @ BloomError::ShaderSource { .. } => error,
+                    error => BloomError::ShaderSource {
+                        file: source.to_string(),
+                        line,
+                        error: Box::new(error),
+                    },
+                })?;
+        }
+        if let Some(condition) = self.conditions.get(base_depth) {
+            return Err(BloomError::ShaderSource {
+                file: source.to_string(),
+                line: condition.line,
+                error: Box::new(BloomError::InvalidShaderDirective(
+                    "#[if] without a matching #[endif]".to_string(),
+                )),
+            });
+        }
+        expanded.push(&code[copied_to..], source, line);
+        Ok(())
+    }
+
+    fn apply_directive(
+        &mut self,
+        directive: &str,
+        base_depth: usize,
+        source: &Rc<str>,
+        line: usize,
+        expanded: &mut MappedShaderCode,
+    ) -> BloomResult<()> {
+        match ShaderDirective::parse(directive)? {
+            ShaderDirective::If(condition) => {
+                let parent_active = self.is_active();
+                self.conditions.push(ShaderCondition {
+                    active: parent_active && self.evaluate(&condition),
+                    parent_active,
+                    has_else: false,
+                    line,
+                });
+            }
+            ShaderDirective::Else => {
+                if self.conditions.len() <= base_depth {
+                    return Err(BloomError::InvalidShaderDirective(
+                        "#[else] without a matching #[if]".to_string(),
+                    ));
+                }
+                let condition = self.conditions.last_mut().unwrap();
+                if condition.has_else {
+                    return Err(BloomError::InvalidShaderDirective(
+                        "#[if] has more than one #[else]".to_string(),
+                    ));
+                }
+                condition.has_else = true;
+                condition.active = condition.parent_active && !condition.active;
+            }
+            ShaderDirective::EndIf => {
+                if self.conditions.len() <= base_depth {
+                    return Err(BloomError::InvalidShaderDirective(
+                        "#[endif] without a matching #[if]".to_string(),
+                    ));
+                }
+                self.conditions.pop();
+            }
+            // Everything else is skipped inside a condition that isn't met
+            _ if !self.is_active() => (),
+            // Defines that are already set (for example by the variant) take precedence
+            ShaderDirective::Define(name, value) => {
+                if self.defines.get(&name).is_none() {
+                    expanded.push(&format!("#define {} {}", name, value), source, line);
+                    self.defines.set(name, value);
+                }
+            }
+            // Features are gathered up and inserted once at the top
+            ShaderDirective::Features(names) => {
+                for name in names {
+                    let feature = ShaderFeature::try_from_name(name)?;
+                    if !self.features.contains(&feature) {
+                        self.features.push(feature);
+                    }
+                }
+            }
+            ShaderDirective::Include(name) => self.include(&name, expanded)?,
+        }
+        Ok(())
+    }
+
+    fn include(&mut self, name: &str, expanded: &mut MappedShaderCode) -> BloomResult<()> {
+        // A snippet that includes itself (even indirectly) would never finish expanding
+        if self.include_stack.iter().any(|e| e == name) {
+            let mut chain = self.include_stack.clone();
+            chain.push(name.to_string());
+            return Err(BloomError::ShaderIncludeCycle(chain));
+        }
+
+        // Each snippet only goes into a program once, however many times it's included
+        if !self.included.insert(name.to_string()) {
+            return Ok(());
+        }
+
+        let snippet = shader_snippet(name)
+            .ok_or_else(|| BloomError::UnknownShaderInclude(name.to_string()))?;
+        let source: Rc<str> = match &snippet {
+            ShaderSnippet::File(path) => path.display().to_string().into(),
+            ShaderSnippet::Code(_) => format!("<snippet {}>", name).into(),
+        };
+        self.include_stack.push(name.to_string());
+        let result = self.expand(&snippet.code()?, &source, expanded);
+        self.include_stack.pop();
+        result
     }
 }
 
@@ -221,6 +621,10 @@ impl ShaderStage {
         }
     }
 
+    fn source_name(&self) -> String {
+        format!("<{:?} shader>", self)
+    }
+
     pub fn stage_bit(&self) -> GLenum {
         match self {
             ShaderStage::Compute => gl::COMPUTE_SHADER_BIT,
@@ -229,144 +633,232 @@ impl ShaderStage {
         }
     }
 
-    fn apply_postprocess(&self, code: &mut String) -> Vec<ShaderFeature> {
-        // Compiled regexes
-        lazy_static! {
-            static ref RE_DIRECTIVE: Regex = Regex::new(r"#\[(.*?)\]").unwrap();
-            static ref RE_NAME_ARGS: Regex = Regex::new(r"#\[(.*?)\((.*?)\)").unwrap();
-        }
+    fn apply_postprocess(
+        &self,
+        code: &str,
+        source: &str,
+        defines: &ShaderDefines,
+    ) -> BloomResult<(MappedShaderCode, Vec<ShaderFeature>)> {
+        // Expand directives and includes
+        let mut preprocessor = ShaderPreprocessor::new(defines);
+        let mut body = MappedShaderCode::default();
+        preprocessor.expand(code, &source.into(), &mut body)?;
 
-        // Remove carriage returns and version number
-        *code = code.replace("\r", "").replace(VERSION_NUMBER, "");
-
-        // Search for directives
-        let mut directives = RE_DIRECTIVE
-            .find_iter(&code)
-            .map(|mat| {
-                // Isolate just the directive string
-                let directive = &code[mat.range()];
-
-                // Check the formatting of the directive
-                if let Some(name_args) = RE_NAME_ARGS.captures(directive) {
-                    // DIRECTIVENAME(ARGS)
-                    // Get the name
-                    let name = &directive[name_args.get(1).unwrap().range()];
-
-                    // Get the args in the parentheses, separated by commas
-                    let args = directive[name_args.get(2).unwrap().range()]
-                        .split(',')
-                        .map(|arg| arg.trim())
-                        .collect();
-
-                    // Create a ShaderDirective object for this directive
-                    ShaderDirective::from_name_args(name, args)
-                } else {
-                    // DIRECTIVENAME or other
-                    // Create a ShaderDirective object for this directive
-                    ShaderDirective::from_name(directive)
-                }
-            })
-            .collect::<Vec<ShaderDirective>>();
-
-        // Remove directives from code
-        *code = RE_DIRECTIVE.replace(&code, "").into_owned();
-
-        // Work on directives
-        // First do inserts
-        let mut final_insert = String::new();
-        for inserted in directives.iter().map(ShaderDirective::inserted_code) {
-            if let Some(inserted) = inserted {
-                final_insert.insert_str(0, &inserted);
-            }
+        // Insert version number and the variant's defines, then the code for every feature so the
+        // rest can use it
+        let mut expanded = MappedShaderCode::default();
+        let generated: Rc<str> = "<generated>".into();
+        expanded.push(&format!("{}\n", VERSION_NUMBER), &generated, 1);
+        for (idx, (name, value)) in defines.iter().enumerate() {
+            expanded.push(
+                &format!("#define {} {}\n", name, value),
+                &generated,
+                idx + 2,
+            );
         }
-        final_insert.insert_str(0, "\n");
-        code.insert_str(0, &final_insert);
-
-        // Insert version number
-        code.insert_str(0, &format!("{}\n", VERSION_NUMBER));
+        for feature in preprocessor.features.iter() {
+            let feature_source: Rc<str> = format!("<feature {}>", feature.name()).into();
+            expanded.push(
+                &format!("{}\n", feature.inserted_code()),
+                &feature_source,
+                1,
+            );
+        }
+        expanded.push("\n", &generated, 1);
+        expanded.append(body);
 
         // Return enabled features
-        #[allow(irrefutable_let_patterns)]
-        directives
-            .drain(..)
-            .filter_map(|directive| {
-                if let ShaderDirective::Features(features) = directive {
-                    Some(features)
-                } else {
-                    None
-                }
-            })
-            .flatten()
-            .map(ShaderFeature::from_name)
-            .collect()
+        Ok((expanded, preprocessor.features))
     }
 }
 
 #[derive(Debug)]
 pub struct Program {
-    gl_handle: IntHandle,
+    gl_handle: Cell<IntHandle>,
     stage: ShaderStage,
+    features: RefCell<Vec<ShaderFeature>>,
+    uniforms: RefCell<HashMap<String, UniformInfo>>,
+    defines: ShaderDefines,
+    source: Option<FileWatch>,
+}
+
+struct CompiledProgram {
+    gl_handle: IntHandle,
     features: Vec<ShaderFeature>,
+    uniforms: HashMap<String, UniformInfo>,
+    linked: bool,
+    info_log: Option<String>,
 }
 
 impl Program {
     pub fn new(stage: ShaderStage, code: impl Into<String>) -> Self {
-        // Convert code to an owned string
-        let mut code = code.into();
+        let code = code.into();
+        let compiled = Self::compile(stage, &code, &stage.source_name(), &ShaderDefines::new())
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        // Print the info log if it's not empty
+        if DEBUG {
+            if let Some(info_log) = compiled.info_log.as_ref() {
+                println!("\x1B[35m{}\x1B[37m", info_log);
+            }
+        }
+
+        Self {
+            gl_handle: Cell::new(compiled.gl_handle),
+            stage,
+            features: RefCell::new(compiled.features),
+            uniforms: RefCell::new(compiled.uniforms),
+            defines: ShaderDefines::new(),
+            source: None,
+        }
+    }
+
+    pub fn try_new(stage: ShaderStage, code: impl Into<String>) -> BloomResult<Self> {
+        Self::try_new_variant(stage, code, &ShaderDefines::new())
+    }
+
+    pub fn try_new_variant(
+        stage: ShaderStage,
+        code: impl Into<String>,
+        defines: &ShaderDefines,
+    ) -> BloomResult<Self> {
+        Self::try_compile_new(stage, &code.into(), &stage.source_name(), defines)
+    }
+
+    fn try_compile_new(
+        stage: ShaderStage,
+        code: &str,
+        source_name: &str,
+        defines: &ShaderDefines,
+    ) -> BloomResult<Self> {
+        let compiled = Self::compile(stage, code, source_name, defines)?;
+        if !compiled.linked {
+            unsafe { gl::DeleteProgram(compiled.gl_handle) };
+            return Err(BloomError::ShaderCompile {
+                stage,
+                log: compiled.info_log.unwrap_or_default(),
+            });
+        }
+
+        Ok(Self {
+            gl_handle: Cell::new(compiled.gl_handle),
+            stage,
+            features: RefCell::new(compiled.features),
+            uniforms: RefCell::new(compiled.uniforms),
+            defines: defines.clone(),
+            source: None,
+        })
+    }
+
+    pub fn from_file(stage: ShaderStage, path: impl AsRef<Path>) -> Self {
+        Self::try_from_file(stage, path).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_from_file(stage: ShaderStage, path: impl AsRef<Path>) -> BloomResult<Self> {
+        Self::try_from_file_variant(stage, path, &ShaderDefines::new())
+    }
 
+    pub fn try_from_file_variant(
+        stage: ShaderStage,
+        path: impl AsRef<Path>,
+        defines: &ShaderDefines,
+    ) -> BloomResult<Self> {
+        let path = path.as_ref();
+        let code = std::fs::read_to_string(path).map_err(|error| BloomError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let source_name = path.display().to_string();
+        let mut program = Self::try_compile_new(stage, &code, &source_name, defines)?;
+        program.source = Some(FileWatch::new(path));
+        Ok(program)
+    }
+
+    fn compile(
+        stage: ShaderStage,
+        code: &str,
+        source_name: &str,
+        defines: &ShaderDefines,
+    ) -> BloomResult<CompiledProgram> {
         // Apply post-processing
-        let features = stage.apply_postprocess(&mut code);
+        let (expanded, features) = stage.apply_postprocess(code, source_name, defines)?;
 
-        // Convert code to a C-string
-        let code = CString::new(code).unwrap();
+        // Reuse the binary the driver compiled on an earlier run if there is one
+        let cache_key = program_cache_key(stage, expanded.as_str());
+        if let Some(gl_handle) = cache_key.and_then(load_program_binary) {
+            return Ok(CompiledProgram {
+                gl_handle,
+                features,
+                uniforms: reflect_uniforms(gl_handle),
+                linked: true,
+                info_log: None,
+            });
+        }
 
-        // Store pointer to code in an array so we can create a pointer to the pointer safely
-        let code_ptrs = [code.as_ptr() as *const _];
+        // Convert code to a C-string
+        let code = CString::new(expanded.as_str()).unwrap();
 
         // Create the shader program object
-        let gl_handle = unsafe { gl::CreateShaderProgramv(stage.gl_enum(), 1, code_ptrs.as_ptr()) };
-
-        if DEBUG {
-            // Get the info log for the program
-            let mut length: GLsizei = 0;
-            let mut info_log: [GLchar; MAX_PROGRAM_INFO_LOG_SIZE] = [0; MAX_PROGRAM_INFO_LOG_SIZE];
-            unsafe {
-                gl::GetProgramInfoLog(
-                    gl_handle,
-                    MAX_PROGRAM_INFO_LOG_SIZE as GLsizei,
-                    &mut length as *mut _,
-                    info_log.as_mut_ptr(),
-                )
-            };
+        let (gl_handle, shader_info_log) = create_shader_program(stage, &code);
 
-            // Print the info log if it's not empty
-            if length > 0 {
-                let message_slice = unsafe {
-                    std::slice::from_raw_parts(info_log.as_ptr() as *const u8, length as usize)
-                };
-                let message_vec = message_slice.to_owned();
-                let message = CString::new(message_vec).unwrap();
-                println!(
-                    "\x1B[35m{}\nShader code:\n{}\n\x1B[37m",
-                    message.to_str().unwrap(),
-                    generate_numbered_code(&code)
-                );
+        // Check whether the program compiled and linked
+        let mut link_status: GLint = 0;
+        unsafe { gl::GetProgramiv(gl_handle, gl::LINK_STATUS, &mut link_status as *mut _) };
+        let linked = link_status == gl::TRUE as GLint;
+        if linked {
+            if let Some(cache_key) = cache_key {
+                store_program_binary(cache_key, gl_handle);
             }
         }
 
-        Self {
+        // Look up every uniform once now rather than asking the driver each time one is set
+        let uniforms = if linked {
+            reflect_uniforms(gl_handle)
+        } else {
+            HashMap::new()
+        };
+
+        // Get the info log for the program, referring back to the original files rather than the
+        // expanded code
+        let info_log = shader_info_log + &read_info_log(gl_handle, gl::GetProgramInfoLog);
+        let info_log = if info_log.is_empty() {
+            None
+        } else {
+            Some(expanded.annotate_log(&info_log))
+        };
+
+        Ok(CompiledProgram {
             gl_handle,
-            stage,
             features,
-        }
+            uniforms,
+            linked,
+            info_log,
+        })
+    }
+
+    pub fn source_path(&self) -> Option<&Path> {
+        self.source.as_ref().map(FileWatch::path)
     }
 
     pub fn stage(&self) -> ShaderStage {
         self.stage
     }
 
+    pub fn defines(&self) -> &ShaderDefines {
+        &self.defines
+    }
+
     pub fn uniform_location(&self, name: impl AsRef<str>) -> Option<GLuint> {
-        let name = CString::new(name.as_ref()).unwrap();
+        let name = name.as_ref();
+        if let Some(uniform) = self.uniforms.borrow().get(name) {
+            return Some(uniform.location() as GLuint);
+        }
+
+        // Only whole uniforms are reflected, so elements and members still have to be looked up
+        if !name.contains('[') {
+            return None;
+        }
+        let name = CString::new(name).unwrap();
         let location = unsafe { gl::GetUniformLocation(self.handle(), name.as_ref().as_ptr()) };
         if location < 0 {
             None
@@ -375,6 +867,50 @@ impl Program {
         }
     }
 
+    pub fn uniform(&self, name: impl AsRef<str>) -> Option<UniformInfo> {
+        self.uniforms.borrow().get(name.as_ref()).cloned()
+    }
+
+    pub fn uniforms(&self) -> Vec<UniformInfo> {
+        let mut uniforms = self
+            .uniforms
+            .borrow()
+            .values()
+            .cloned()
+            .collect::<Vec<UniformInfo>>();
+        uniforms.sort_by(|a, b| a.name().cmp(b.name()));
+        uniforms
+    }
+
+    pub fn set_uniform<T: UniformValue + ?Sized>(
+        &self,
+        name: impl AsRef<str>,
+        value: &T,
+    ) -> BloomResult<()> {
+        let name = name.as_ref();
+        let uniform = self
+            .uniform(name)
+            .ok_or_else(|| BloomError::UnknownUniform(name.to_string()))?;
+
+        // Check the value against what the shader declared before handing it to the driver
+        if !uniform.accepts(T::GL_TYPE) {
+            return Err(BloomError::UniformType {
+                name: name.to_string(),
+                expected: uniform.gl_type(),
+                given: T::GL_TYPE,
+            });
+        }
+        if value.element_count() > uniform.size() as usize {
+            return Err(BloomError::UniformArrayLength {
+                name: name.to_string(),
+                size: uniform.size(),
+                given: value.element_count(),
+            });
+        }
+        unsafe { value.upload(self.handle(), uniform.location()) };
+        Ok(())
+    }
+
     pub fn set_uniform_uint(&self, location: GLuint, unit: GLuint) {
         unsafe { gl::ProgramUniform1ui(self.handle(), location as GLint, unit) };
     }
@@ -399,30 +935,349 @@ impl Program {
         unsafe { gl::ProgramUniform1f(self.handle(), location as GLint, f) };
     }
 
-    pub fn shader_features(&self) -> &[ShaderFeature] {
-        &self.features
+    pub fn set_uniform_vec2f(&self, location: GLuint, v: Vec2f) {
+        unsafe { gl::ProgramUniform2f(self.handle(), location as GLint, v[0], v[1]) };
+    }
+
+    pub fn shader_features(&self) -> Vec<ShaderFeature> {
+        self.features.borrow().clone()
+    }
+
+    pub fn apply_custom_features(&self) {
+        for feature in self.shader_features() {
+            if let ShaderFeature::Custom(id) = feature {
+                custom_shader_feature(id).apply(self);
+            }
+        }
+    }
+}
+
+impl HotReload for Program {
+    fn hot_reload(&self) -> Result<bool, String> {
+        let source = match self.source.as_ref() {
+            Some(source) if source.changed() => source,
+            _ => return Ok(false),
+        };
+        let code = std::fs::read_to_string(source.path())
+            .map_err(|error| format!("Could not reload shader {:?}: {}", source.path(), error))?;
+
+        // Keep the old program running unless the new one links
+        let source_name = source.path().display().to_string();
+        let compiled = Self::compile(self.stage, &code, &source_name, &self.defines)
+            .map_err(|error| format!("Could not reload shader {:?}: {}", source.path(), error))?;
+        if !compiled.linked {
+            unsafe { gl::DeleteProgram(compiled.gl_handle) };
+            return Err(format!(
+                "Could not reload shader {:?}:\n{}",
+                source.path(),
+                compiled.info_log.unwrap_or_default()
+            ));
+        }
+        unsafe { gl::DeleteProgram(self.gl_handle.get()) };
+        self.gl_handle.set(compiled.gl_handle);
+        *self.features.borrow_mut() = compiled.features;
+        *self.uniforms.borrow_mut() = compiled.uniforms;
+        Ok(true)
     }
 }
 
 impl GLHandle for Program {
     fn handle(&self) -> IntHandle {
-        self.gl_handle
+        self.gl_handle.get()
     }
 }
 
 impl Drop for Program {
     fn drop(&mut self) {
-        if self.gl_handle != 0 {
-            unsafe { gl::DeleteProgram(self.gl_handle) };
+        if self.gl_handle.get() != 0 {
+            unsafe { gl::DeleteProgram(self.gl_handle.get()) };
+        }
+    }
+}
+
+fn create_shader_program(stage: ShaderStage, code: &CString) -> (IntHandle, String) {
+    // Does the same as glCreateShaderProgramv, except that the driver is told up front that the
+    // binary will be fetched for the program cache
+    let code_ptrs = [code.as_ptr() as *const GLchar];
+    let mut compile_status: GLint = 0;
+    unsafe {
+        let shader = gl::CreateShader(stage.gl_enum());
+        gl::ShaderSource(shader, 1, code_ptrs.as_ptr(), std::ptr::null());
+        gl::CompileShader(shader);
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut compile_status);
+        let shader_info_log = read_info_log(shader, gl::GetShaderInfoLog);
+
+        let gl_handle = gl::CreateProgram();
+        gl::ProgramParameteri(gl_handle, gl::PROGRAM_SEPARABLE, gl::TRUE as GLint);
+        gl::ProgramParameteri(
+            gl_handle,
+            gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+            gl::TRUE as GLint,
+        );
+        if compile_status == gl::TRUE as GLint {
+            gl::AttachShader(gl_handle, shader);
+            gl::LinkProgram(gl_handle);
+            gl::DetachShader(gl_handle, shader);
         }
+        gl::DeleteShader(shader);
+        (gl_handle, shader_info_log)
     }
 }
 
-fn generate_numbered_code(code: &CStr) -> String {
-    let code = code.to_str().unwrap().split('\n');
-    let mut new_code = String::new();
-    for (line_number, line) in code.enumerate() {
-        new_code.push_str(&format!("{:03} {}\n", line_number + 1, line));
+fn read_info_log(
+    gl_handle: IntHandle,
+    get_info_log: unsafe fn(GLuint, GLsizei, *mut GLsizei, *mut GLchar),
+) -> String {
+    let mut length: GLsizei = 0;
+    let mut info_log: [GLchar; MAX_PROGRAM_INFO_LOG_SIZE] = [0; MAX_PROGRAM_INFO_LOG_SIZE];
+    unsafe {
+        get_info_log(
+            gl_handle,
+            MAX_PROGRAM_INFO_LOG_SIZE as GLsizei,
+            &mut length as *mut _,
+            info_log.as_mut_ptr(),
+        )
+    };
+    let message_slice = unsafe {
+        std::slice::from_raw_parts(info_log.as_ptr() as *const u8, length.max(0) as usize)
+    };
+    String::from_utf8_lossy(message_slice).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(code: &str, defines: &ShaderDefines) -> BloomResult<Vec<String>> {
+        // Only the lines that made it through, without the blanks left by directives
+        let mut expanded = MappedShaderCode::default();
+        ShaderPreprocessor::new(defines).expand(code, &"test.vert".into(), &mut expanded)?;
+        Ok(expanded
+            .as_str()
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn assert_directive_error(code: &str, expected_line: usize, expected_message: &str) {
+        match expand(code, &ShaderDefines::new()) {
+            Err(BloomError::ShaderSource { file, line, error }) => {
+                assert_eq!(file, "test.vert");
+                assert_eq!(line, expected_line, "for code {:?}", code);
+                match *error {
+                    BloomError::InvalidShaderDirective(message) => {
+                        assert_eq!(message, expected_message)
+                    }
+                    error => panic!("Expected an invalid directive, got {}", error),
+                }
+            }
+            Err(error) => panic!("Expected a shader source error, got {}", error),
+            Ok(lines) => panic!("Expected {:?} to fail, got {:?}", code, lines),
+        }
+    }
+
+    #[test]
+    fn picks_branches_of_nested_conditions() {
+        let code = "\
+#[if OUTER]
+outer
+#[if INNER]
+both
+#[else]
+outer only
+#[endif]
+#[else]
+#[if INNER]
+inner only
+#[else]
+neither
+#[endif]
+#[endif]
+after";
+        let variant = |outer: bool, inner: bool| {
+            let defines = ShaderDefines::new()
+                .with_define("OUTER", outer as u8)
+                .with_define("INNER", inner as u8);
+            expand(code, &defines).unwrap()
+        };
+        assert_eq!(variant(true, true), vec!["outer", "both", "after"]);
+        assert_eq!(variant(true, false), vec!["outer", "outer only", "after"]);
+        assert_eq!(variant(false, true), vec!["inner only", "after"]);
+        assert_eq!(variant(false, false), vec!["neither", "after"]);
+    }
+
+    #[test]
+    fn negated_conditions_and_skipped_directives() {
+        let code = "\
+#[if !FAST]
+#[define(QUALITY, 2)]
+slow
+#[endif]";
+        assert_eq!(
+            expand(code, &ShaderDefines::new()).unwrap(),
+            vec!["#define QUALITY 2", "slow"]
+        );
+        assert!(expand(code, &ShaderDefines::new().with_flag("FAST"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn rejects_unmatched_directives() {
+        assert_directive_error("a\nb\n#[endif]", 3, "#[endif] without a matching #[if]");
+        assert_directive_error("a\n#[else]", 2, "#[else] without a matching #[if]");
+        assert_directive_error(
+            "#[if A]\n#[else]\n#[else]\n#[endif]",
+            3,
+            "#[if] has more than one #[else]",
+        );
+        assert_directive_error(
+            "a\n#[if A]\n#[if B]\n#[endif]",
+            2,
+            "#[if] without a matching #[endif]",
+        );
+    }
+
+    #[test]
+    fn conditions_close_in_the_snippet_that_opened_them() {
+        register_shader_snippet("test_unclosed_if", "#[if A]\nsnippet");
+        match expand(
+            "#[if !B]\n#[include(test_unclosed_if)]\n#[endif]",
+            &ShaderDefines::new(),
+        ) {
+            Err(BloomError::ShaderSource { file, line, .. }) => {
+                assert_eq!((file.as_str(), line), ("<snippet test_unclosed_if>", 1))
+            }
+            result => panic!(
+                "Expected the snippet's #[if] to be unmatched, got {:?}",
+                result
+            ),
+        }
+    }
+
+    #[test]
+    fn variant_defines_override_shader_defines() {
+        let code = "#[define(ALPHA_CUTOFF, 0.5)]\n#[define(TINT)]\nmain";
+        assert_eq!(
+            expand(code, &ShaderDefines::new()).unwrap(),
+            vec!["#define ALPHA_CUTOFF 0.5", "#define TINT 1", "main"]
+        );
+
+        // The variant's value is defined at the top, and the shader's own define is dropped
+        let defines = ShaderDefines::new().with_define("ALPHA_CUTOFF", 0.25);
+        assert_eq!(
+            expand(code, &defines).unwrap(),
+            vec!["#define TINT 1", "main"]
+        );
+        let (expanded, _) = ShaderStage::Fragment
+            .apply_postprocess(code, "test.frag", &defines)
+            .unwrap();
+        let lines = expanded.as_str().lines().collect::<Vec<&str>>();
+        assert_eq!(lines[0], VERSION_NUMBER);
+        assert_eq!(lines[1], "#define ALPHA_CUTOFF 0.25");
+        assert!(!expanded.as_str().contains("ALPHA_CUTOFF 0.5"));
+    }
+
+    fn annotated_test_shader(log_line: impl Fn(usize) -> String, marker: &str) -> Vec<String> {
+        register_shader_snippet(
+            "test_log_helpers",
+            "float helper()\n{\n    return 1.0;\n}\n",
+        );
+        let code = "\
+#[feature(deltaTime)]
+#[include(test_log_helpers)]
+void main()
+{
+    broken;
+    gl_Position = vec4(deltaTime());
+}
+";
+        let (expanded, _) = ShaderStage::Vertex
+            .apply_postprocess(code, "test.vert", &ShaderDefines::new())
+            .unwrap();
+
+        // Drivers report lines of the expanded code, after the version, feature and snippet
+        let line = expanded
+            .as_str()
+            .split('\n')
+            .position(|line| line.contains(marker))
+            .unwrap()
+            + 1;
+        expanded
+            .annotate_log(&format!("{}\nLink failed.", log_line(line)))
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn annotates_driver_logs_with_original_lines() {
+        let logs: [(fn(usize) -> String, &str); 3] = [
+            // NVIDIA
+            (
+                |line| format!("0({}) : error C1008: undefined variable \"broken\"", line),
+                "test.vert:5 : error C1008: undefined variable \"broken\"",
+            ),
+            // Mesa
+            (
+                |line| format!("0:{}(5): error: `broken' undeclared", line),
+                "test.vert:5: error: `broken' undeclared",
+            ),
+            // AMD
+            (
+                |line| format!("ERROR: 0:{}: 'broken' : undeclared identifier", line),
+                "ERROR: test.vert:5: 'broken' : undeclared identifier",
+            ),
+        ];
+        for (log_line, message) in logs.iter() {
+            assert_eq!(
+                annotated_test_shader(log_line, "broken;"),
+                vec![
+                    message.to_string(),
+                    "      3 | void main()".to_string(),
+                    "      4 | {".to_string(),
+                    ">     5 |     broken;".to_string(),
+                    "      6 |     gl_Position = vec4(deltaTime());".to_string(),
+                    "      7 | }".to_string(),
+                    "Link failed.".to_string(),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn annotates_errors_inside_snippets() {
+        let annotated = annotated_test_shader(
+            |line| format!("0:{}(12): error: type mismatch", line),
+            "return 1.0;",
+        );
+        assert_eq!(
+            annotated,
+            vec![
+                "<snippet test_log_helpers>:3: error: type mismatch",
+                "      1 | float helper()",
+                "      2 | {",
+                ">     3 |     return 1.0;",
+                "      4 | }",
+                // Lines from other files are shown without a line number
+                "        | ",
+                "Link failed.",
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_unknown_locations_alone() {
+        // Line 0 and lines past the end of the code don't map to anything
+        let annotated = annotated_test_shader(|_| "0:0(1): error: oops".to_string(), "broken;");
+        assert_eq!(annotated, vec!["0:0(1): error: oops", "Link failed."]);
+        let annotated =
+            annotated_test_shader(|_| "0(9999) : error C0000: oops".to_string(), "broken;");
+        assert_eq!(
+            annotated,
+            vec!["0(9999) : error C0000: oops", "Link failed."]
+        );
     }
-    new_code
 }
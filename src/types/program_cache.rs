@@ -0,0 +1,174 @@
+use crate::*;
+use std::cell::{Cell, RefCell};
+use std::ffi::CStr;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+// Cached binaries are stored one per file, named after their key:
+//
+//     magic "BLPB", version u32, binary format u32, then the binary itself
+//
+// All integers are little endian. The key covers the driver, so binaries from another driver (or
+// an older version of this one) are simply never looked up.
+const PROGRAM_CACHE_MAGIC: &[u8; 4] = b"BLPB";
+const PROGRAM_CACHE_VERSION: u32 = 1;
+const PROGRAM_CACHE_HEADER_SIZE: usize = 12;
+
+thread_local! {
+    static PROGRAM_CACHE_ENABLED: Cell<bool> = Cell::new(true);
+    static DRIVER_ID: RefCell<Option<Option<String>>> = RefCell::new(None);
+}
+
+pub fn program_cache_dir() -> PathBuf {
+    user_data_dir().join("shader_cache")
+}
+
+pub fn set_program_cache_enabled(enabled: bool) {
+    PROGRAM_CACHE_ENABLED.with(|cache_enabled| cache_enabled.set(enabled));
+}
+
+pub fn program_cache_enabled() -> bool {
+    PROGRAM_CACHE_ENABLED.with(Cell::get)
+}
+
+pub fn clear_program_cache() -> io::Result<()> {
+    match fs::remove_dir_all(program_cache_dir()) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+fn driver_id() -> Option<String> {
+    // The driver can't change while the game is running, so only ask once
+    DRIVER_ID.with(|driver_id| {
+        driver_id
+            .borrow_mut()
+            .get_or_insert_with(query_driver_id)
+            .clone()
+    })
+}
+
+fn query_driver_id() -> Option<String> {
+    // Without any binary formats the driver can't hand programs back to us
+    let mut format_count: GLint = 0;
+    unsafe { gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut format_count) };
+    if format_count < 1 {
+        return None;
+    }
+
+    let strings = [gl::VENDOR, gl::RENDERER, gl::VERSION]
+        .iter()
+        .map(|&name| {
+            let string = unsafe { gl::GetString(name) };
+            if string.is_null() {
+                String::new()
+            } else {
+                unsafe { CStr::from_ptr(string as *const _) }
+                    .to_string_lossy()
+                    .into_owned()
+            }
+        })
+        .collect::<Vec<String>>();
+    Some(strings.join("\n"))
+}
+
+pub fn program_cache_key(stage: ShaderStage, code: &str) -> Option<u64> {
+    if !program_cache_enabled() {
+        return None;
+    }
+    let driver_id = driver_id()?;
+    Some(fnv1a_hash(
+        format!("{}\n{:?}\n{}", driver_id, stage, code).as_bytes(),
+    ))
+}
+
+fn program_cache_path(key: u64) -> PathBuf {
+    program_cache_dir().join(format!("{:016x}.bin", key))
+}
+
+pub fn load_program_binary(key: u64) -> Option<IntHandle> {
+    let path = program_cache_path(key);
+    let bytes = fs::read(&path).ok()?;
+
+    // Check the header
+    let header_valid = bytes.len() > PROGRAM_CACHE_HEADER_SIZE
+        && &bytes[0..4] == PROGRAM_CACHE_MAGIC
+        && bytes[4..8] == PROGRAM_CACHE_VERSION.to_le_bytes();
+    if !header_valid {
+        fs::remove_file(&path).ok();
+        return None;
+    }
+    let mut format = [0; 4];
+    format.copy_from_slice(&bytes[8..12]);
+    let format = u32::from_le_bytes(format);
+    let binary = &bytes[PROGRAM_CACHE_HEADER_SIZE..];
+
+    // Hand the binary to the driver
+    let gl_handle = unsafe { gl::CreateProgram() };
+    let mut link_status: GLint = 0;
+    unsafe {
+        gl::ProgramParameteri(gl_handle, gl::PROGRAM_SEPARABLE, gl::TRUE as GLint);
+        gl::ProgramBinary(
+            gl_handle,
+            format,
+            binary.as_ptr() as *const _,
+            binary.len() as GLsizei,
+        );
+        gl::GetProgramiv(gl_handle, gl::LINK_STATUS, &mut link_status);
+    }
+
+    // Drivers may reject binaries (after an update, for example), in which case the program gets
+    // compiled from source again and the stale binary is replaced
+    if link_status != gl::TRUE as GLint {
+        unsafe { gl::DeleteProgram(gl_handle) };
+        fs::remove_file(&path).ok();
+        return None;
+    }
+    Some(gl_handle)
+}
+
+pub fn store_program_binary(key: u64, gl_handle: IntHandle) {
+    if let Err(error) = write_program_binary(key, gl_handle) {
+        if DEBUG {
+            eprintln!("Could not cache program binary: {}", error);
+        }
+    }
+}
+
+fn write_program_binary(key: u64, gl_handle: IntHandle) -> io::Result<()> {
+    // Fetch the binary from the driver
+    let mut length: GLint = 0;
+    unsafe { gl::GetProgramiv(gl_handle, gl::PROGRAM_BINARY_LENGTH, &mut length) };
+    if length < 1 {
+        return Ok(());
+    }
+    let mut binary = vec![0u8; length as usize];
+    let mut written: GLsizei = 0;
+    let mut format: GLenum = 0;
+    unsafe {
+        gl::GetProgramBinary(
+            gl_handle,
+            length,
+            &mut written,
+            &mut format,
+            binary.as_mut_ptr() as *mut _,
+        )
+    };
+    binary.truncate(written.max(0) as usize);
+    if binary.is_empty() {
+        return Ok(());
+    }
+
+    // Write it out next to the final path first, so a crash never leaves half a binary behind
+    let path = program_cache_path(key);
+    fs::create_dir_all(program_cache_dir())?;
+    let mut bytes = Vec::with_capacity(PROGRAM_CACHE_HEADER_SIZE + binary.len());
+    bytes.extend_from_slice(PROGRAM_CACHE_MAGIC);
+    bytes.extend_from_slice(&PROGRAM_CACHE_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&format.to_le_bytes());
+    bytes.extend_from_slice(&binary);
+    let temporary_path = path.with_extension("bin.tmp");
+    fs::write(&temporary_path, &bytes)?;
+    fs::rename(&temporary_path, &path)
+}
@@ -0,0 +1,153 @@
+use crate::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// Called before drawing with a program that uses the feature, along with the location of each of
+// the feature's uniforms (in the order they were registered, None if the shader optimised it out)
+pub type ShaderFeatureSetter = Rc<dyn Fn(&Program, &[Option<GLuint>])>;
+
+#[derive(Clone, Debug)]
+pub enum ShaderSnippet {
+    Code(String),
+    File(PathBuf),
+}
+
+impl ShaderSnippet {
+    pub fn code(&self) -> BloomResult<String> {
+        match self {
+            ShaderSnippet::Code(code) => Ok(code.clone()),
+            // Files are read whenever they are included so that edits get picked up by reloads
+            ShaderSnippet::File(path) => {
+                std::fs::read_to_string(path).map_err(|error| BloomError::Io {
+                    path: path.clone(),
+                    error,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CustomShaderFeatureId(usize);
+
+#[derive(Clone)]
+pub struct CustomShaderFeature {
+    name: String,
+    code: String,
+    uniforms: Vec<String>,
+    setter: ShaderFeatureSetter,
+}
+
+impl CustomShaderFeature {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn uniforms(&self) -> &[String] {
+        &self.uniforms
+    }
+
+    pub fn apply(&self, program: &Program) {
+        let locations = self
+            .uniforms
+            .iter()
+            .map(|uniform| program.uniform_location(uniform))
+            .collect::<Vec<Option<GLuint>>>();
+        (self.setter)(program, &locations);
+    }
+}
+
+impl fmt::Debug for CustomShaderFeature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CustomShaderFeature")
+            .field("name", &self.name)
+            .field("code", &self.code)
+            .field("uniforms", &self.uniforms)
+            .finish()
+    }
+}
+
+#[derive(Default)]
+struct ShaderLibrary {
+    snippets: HashMap<String, ShaderSnippet>,
+    features: Vec<CustomShaderFeature>,
+}
+
+thread_local! {
+    static SHADER_LIBRARY: RefCell<ShaderLibrary> = RefCell::new(ShaderLibrary::default());
+}
+
+pub fn register_shader_snippet(name: impl Into<String>, code: impl Into<String>) {
+    let snippet = ShaderSnippet::Code(code.into());
+    SHADER_LIBRARY.with(|library| library.borrow_mut().snippets.insert(name.into(), snippet));
+}
+
+pub fn register_shader_snippet_file(name: impl Into<String>, path: impl AsRef<Path>) {
+    let snippet = ShaderSnippet::File(path.as_ref().to_path_buf());
+    SHADER_LIBRARY.with(|library| library.borrow_mut().snippets.insert(name.into(), snippet));
+}
+
+pub fn shader_snippet(name: &str) -> Option<ShaderSnippet> {
+    SHADER_LIBRARY.with(|library| library.borrow().snippets.get(name).cloned())
+}
+
+pub fn register_shader_feature(
+    name: impl Into<String>,
+    code: impl Into<String>,
+    uniforms: impl IntoIterator<Item = impl Into<String>>,
+    setter: impl Fn(&Program, &[Option<GLuint>]) + 'static,
+) -> ShaderFeature {
+    let name = name.into();
+    if DEBUG && ShaderFeature::built_in_from_name(&name).is_some() {
+        panic!(
+            "Shader feature {:?} is built in and can't be replaced",
+            name
+        );
+    }
+    let feature = CustomShaderFeature {
+        name,
+        code: code.into(),
+        uniforms: uniforms.into_iter().map(Into::into).collect(),
+        setter: Rc::new(setter),
+    };
+
+    // Registering a name again replaces the feature; programs already compiled keep their code but
+    // use the new setter
+    SHADER_LIBRARY.with(|library| {
+        let features = &mut library.borrow_mut().features;
+        let idx = match features.iter().position(|e| e.name == feature.name) {
+            Some(idx) => {
+                features[idx] = feature;
+                idx
+            }
+            None => {
+                features.push(feature);
+                features.len() - 1
+            }
+        };
+        ShaderFeature::Custom(CustomShaderFeatureId(idx))
+    })
+}
+
+pub fn find_shader_feature(name: &str) -> Option<ShaderFeature> {
+    SHADER_LIBRARY.with(|library| {
+        library
+            .borrow()
+            .features
+            .iter()
+            .position(|e| e.name == name)
+            .map(|idx| ShaderFeature::Custom(CustomShaderFeatureId(idx)))
+    })
+}
+
+pub fn custom_shader_feature(id: CustomShaderFeatureId) -> CustomShaderFeature {
+    // Ids are only handed out by register_shader_feature, so they always exist
+    SHADER_LIBRARY.with(|library| library.borrow().features[id.0].clone())
+}
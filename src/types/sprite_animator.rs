@@ -11,8 +11,22 @@ struct Actor
 {
     vec4 positionVelocity;
     vec4 scaleVelocity;
-    vec4 rotationUpdatedAnimtime;
+    vec4 rotationUpdatedAnimtimeRadius;
     vec4 rectangle;
+    uvec2 motionProgramStep;
+    float motionStepTime;
+    uint motionParent;
+    uint generation;
+    uint motionParentGeneration;
+    uvec2 padding;
+};
+
+struct MotionStep
+{
+    uint kind;
+    float duration;
+    float param0;
+    float param1;
 };
 
 struct Instance
@@ -31,6 +45,35 @@ layout (std140, binding = 1) buffer buffer_Instances
     Instance instances[];
 };
 
+layout (std430, binding = 2) buffer buffer_MotionSteps
+{
+    MotionStep motionSteps[];
+};
+
+layout (std430, binding = 3) buffer buffer_MotionPrograms
+{
+    uvec2 motionPrograms[];
+};
+
+// The actors as they were before this frame, so orbits never read a parent another invocation is writing
+layout (std140, binding = 4) readonly buffer buffer_PreviousActors
+{
+    Actor previousActors[];
+};
+
+uniform vec2 u_aimTarget;
+uniform vec2 u_boundsHalfSize;
+
+const uint MOTION_WAIT = 0;
+const uint MOTION_ACCELERATE = 1;
+const uint MOTION_TURN = 2;
+const uint MOTION_STOP = 3;
+const uint MOTION_AIM = 4;
+const uint MOTION_HOME = 5;
+const uint MOTION_BOUNCE = 6;
+const uint MOTION_ORBIT = 7;
+const int MAX_MOTION_STEPS_PER_FRAME = 16;
+
 mat4 mat_scale(vec3 scale)
 {
     return mat4(
@@ -65,10 +108,131 @@ mat4 mat_rotateZ(float angle)
 
 mat4 buildMatrix(Actor actor)
 {
-    mat4 mat = mat_translate(vec3(actor.positionVelocity.xy, 0.0)) * mat_scale(vec3(actor.scaleVelocity.xy, 1.0)) * mat_rotateZ(actor.rotationUpdatedAnimtime.x);
+    mat4 mat = mat_translate(vec3(actor.positionVelocity.xy, 0.0)) * mat_scale(vec3(actor.scaleVelocity.xy, 1.0)) * mat_rotateZ(actor.rotationUpdatedAnimtimeRadius.x);
     return mat;
 }
 
+vec2 rotate(vec2 v, float angle)
+{
+    float cos = cos(angle);
+    float sin = sin(angle);
+    return vec2(v.x * cos - v.y * sin, v.x * sin + v.y * cos);
+}
+
+void enterMotionStep(inout Actor actor, MotionStep step)
+{
+    if (step.kind == MOTION_STOP)
+    {
+        actor.positionVelocity.zw = vec2(0.0);
+    }
+    else if (step.kind == MOTION_AIM)
+    {
+        vec2 toTarget = u_aimTarget - actor.positionVelocity.xy;
+        float speed = step.param0 >= 0.0 ? step.param0 : length(actor.positionVelocity.zw);
+        if (length(toTarget) > 0.0)
+        {
+            actor.positionVelocity.zw = normalize(toTarget) * speed;
+        }
+    }
+}
+
+void runMotionStep(inout Actor actor, MotionStep step, float delta)
+{
+    // Change the velocity, then move
+    vec2 velocity = actor.positionVelocity.zw;
+    if (step.kind == MOTION_ACCELERATE)
+    {
+        float speed = length(velocity);
+        vec2 direction = speed > 0.0 ? velocity / speed : vec2(cos(actor.rotationUpdatedAnimtimeRadius.x), sin(actor.rotationUpdatedAnimtimeRadius.x));
+        float newSpeed = step.param0 >= 0.0 ? min(speed + step.param0 * delta, step.param1) : max(speed + step.param0 * delta, step.param1);
+        velocity = direction * newSpeed;
+    }
+    else if (step.kind == MOTION_TURN)
+    {
+        velocity = rotate(velocity, step.param0 * delta);
+    }
+    else if (step.kind == MOTION_HOME)
+    {
+        vec2 toTarget = u_aimTarget - actor.positionVelocity.xy;
+        float difference = atan(toTarget.y, toTarget.x) - atan(velocity.y, velocity.x);
+        difference = atan(sin(difference), cos(difference));
+        float maxTurn = step.param0 * delta;
+        velocity = rotate(velocity, clamp(difference, -maxTurn, maxTurn));
+    }
+    actor.positionVelocity.zw = velocity;
+    bool orbiting = false;
+    if (step.kind == MOTION_ORBIT && actor.motionParent != 0)
+    {
+        Actor parent = previousActors[actor.motionParent - 1];
+        orbiting = parent.generation != 0 && parent.generation == actor.motionParentGeneration;
+        if (orbiting)
+        {
+            vec2 parentPosition = parent.positionVelocity.xy;
+            actor.positionVelocity.xy = parentPosition + rotate(actor.positionVelocity.xy - parentPosition, step.param0 * delta);
+        }
+    }
+    if (!orbiting)
+    {
+        actor.positionVelocity.xy += velocity * delta;
+    }
+
+    // Bounce off the edges of the bounds
+    if (step.kind == MOTION_BOUNCE)
+    {
+        vec2 position = actor.positionVelocity.xy;
+        if ((position.x < -u_boundsHalfSize.x && velocity.x < 0.0) || (position.x > u_boundsHalfSize.x && velocity.x > 0.0))
+        {
+            actor.positionVelocity.z = -velocity.x;
+        }
+        if ((position.y < -u_boundsHalfSize.y && velocity.y < 0.0) || (position.y > u_boundsHalfSize.y && velocity.y > 0.0))
+        {
+            actor.positionVelocity.w = -velocity.y;
+        }
+    }
+
+    // Face the direction of travel
+    if (actor.positionVelocity.z != 0.0 || actor.positionVelocity.w != 0.0)
+    {
+        actor.rotationUpdatedAnimtimeRadius.x = atan(actor.positionVelocity.w, actor.positionVelocity.z);
+    }
+}
+
+void integrate(inout Actor actor, float delta)
+{
+    // Run the motion program's steps for as much of the frame as they cover
+    float remaining = delta;
+    for (int iteration = 0; iteration < MAX_MOTION_STEPS_PER_FRAME && actor.motionProgramStep.x != 0 && remaining > 0.0; iteration++)
+    {
+        uvec2 program = motionPrograms[actor.motionProgramStep.x - 1];
+        if (actor.motionProgramStep.y >= program.y)
+        {
+            actor.motionProgramStep.x = 0;
+            break;
+        }
+        MotionStep step = motionSteps[program.x + actor.motionProgramStep.y];
+
+        // Instant changes happen as the step is entered
+        if (actor.motionStepTime == 0.0)
+        {
+            enterMotionStep(actor, step);
+        }
+
+        // Run the step until it ends or the frame runs out
+        float slice = step.duration >= 0.0 ? min(remaining, step.duration - actor.motionStepTime) : remaining;
+        runMotionStep(actor, step, slice);
+        actor.motionStepTime += slice;
+        remaining -= slice;
+        if (step.duration >= 0.0 && actor.motionStepTime >= step.duration)
+        {
+            actor.motionProgramStep.y++;
+            actor.motionStepTime = 0.0;
+        }
+    }
+
+    // Move linearly for any time left over
+    actor.positionVelocity.xy += actor.positionVelocity.zw * remaining;
+}
+
 Instance buildInstance(Actor actor)
 {
     Instance vinst;
@@ -81,42 +245,340 @@ void main()
 {
     uint index = batchOffset() + gl_GlobalInvocationID.x;
     Actor actor = actors[index];
-    actor.positionVelocity.xy += actor.positionVelocity.zw * deltaTime();
+    integrate(actor, deltaTime());
     instances[index] = buildInstance(actor);
     actors[index] = actor;
 }";
 
 const MAX_DISPATCH: GLuint = 128;
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AnimatorBackend {
+    Gpu,
+    Cpu,
+}
+
 #[derive(Clone, Debug)]
+pub struct ActorSnapshot {
+    capture_time: f64,
+    actors: Vec<SpriteActorVertex>,
+}
+
+impl ActorSnapshot {
+    pub fn capture_time(&self) -> f64 {
+        self.capture_time
+    }
+
+    pub fn actors(&self) -> &[SpriteActorVertex] {
+        &self.actors
+    }
+}
+
+#[derive(Debug)]
+enum ActorReadback {
+    Gpu {
+        pending: Option<(Fence, f64)>,
+        mapped: BufferMap<SpriteActorVertex>,
+        buffer: Buffer,
+    },
+    Cpu {
+        pending: Option<f64>,
+    },
+}
+
+#[derive(Debug)]
 pub struct SpriteAnimator {
-    compute_pipeline: Rc<RefCell<ComputePipeline>>,
+    backend: AnimatorBackend,
+    compute_pipeline: Option<Rc<RefCell<ComputePipeline>>>,
+    cpu_actors: Vec<SpriteActorVertex>,
     actor_buffer: Option<Rc<RefCell<Buffer>>>,
+    previous_actor_buffer: Option<Rc<RefCell<Buffer>>>,
     instance_buffer: Option<Rc<RefCell<Buffer>>>,
+    readback: Option<ActorReadback>,
+    motion_programs: Rc<RefCell<MotionProgramTable>>,
+    aim_target: Vec2f,
+    motion_bounds_half_size: Vec2f,
+}
+
+impl Clone for SpriteAnimator {
+    fn clone(&self) -> Self {
+        // Clones share the buffers, but each gets its own readback so captures don't interfere
+        let mut animator = Self {
+            backend: self.backend,
+            compute_pipeline: self.compute_pipeline.clone(),
+            cpu_actors: self.cpu_actors.clone(),
+            actor_buffer: self.actor_buffer.clone(),
+            previous_actor_buffer: self.previous_actor_buffer.clone(),
+            instance_buffer: self.instance_buffer.clone(),
+            readback: None,
+            motion_programs: self.motion_programs.clone(),
+            aim_target: self.aim_target,
+            motion_bounds_half_size: self.motion_bounds_half_size,
+        };
+        if self.readback.is_some() {
+            animator.enable_readback();
+        }
+        animator
+    }
 }
 
 impl SpriteAnimator {
     pub fn new() -> Self {
-        let program = Program::new(ShaderStage::Compute, COMPUTE_SHADER);
-        let compute_pipeline = Rc::new(RefCell::new(ComputePipeline::new(program)));
+        Self::with_backend(AnimatorBackend::Gpu)
+    }
+
+    pub fn with_backend(backend: AnimatorBackend) -> Self {
+        // Only the GPU backend needs the compute shader
+        let compute_pipeline = match backend {
+            AnimatorBackend::Gpu => {
+                let program = Program::new(ShaderStage::Compute, COMPUTE_SHADER);
+                Some(Rc::new(RefCell::new(ComputePipeline::new(program))))
+            }
+            AnimatorBackend::Cpu => None,
+        };
+        let motion_programs = match backend {
+            AnimatorBackend::Gpu => MotionProgramTable::new(),
+            AnimatorBackend::Cpu => MotionProgramTable::without_buffers(),
+        };
 
         Self {
+            backend,
             compute_pipeline,
+            cpu_actors: Vec::new(),
             actor_buffer: None,
+            previous_actor_buffer: None,
             instance_buffer: None,
+            readback: None,
+            motion_programs: Rc::new(RefCell::new(motion_programs)),
+            aim_target: Vector::zero(),
+            motion_bounds_half_size: Vector::zero(),
         }
     }
 
+    pub fn backend(&self) -> AnimatorBackend {
+        self.backend
+    }
+
+    pub fn register_motion_program(&mut self, program: &MotionProgram) -> MotionProgramReference {
+        self.motion_programs.borrow_mut().register(program)
+    }
+
+    pub fn motion_programs(&self) -> &Rc<RefCell<MotionProgramTable>> {
+        &self.motion_programs
+    }
+
+    pub fn aim_target(&self) -> Vec2f {
+        self.aim_target
+    }
+
+    pub fn set_aim_target(&mut self, aim_target: Vec2f) {
+        self.aim_target = aim_target;
+    }
+
+    pub fn motion_bounds_half_size(&self) -> Vec2f {
+        self.motion_bounds_half_size
+    }
+
+    pub fn set_motion_bounds(&mut self, bounds_half_size: Vec2f) {
+        self.motion_bounds_half_size = bounds_half_size;
+    }
+
     pub fn set_buffers(
         &mut self,
         actor_buffer: Rc<RefCell<Buffer>>,
         instance_buffer: Rc<RefCell<Buffer>>,
     ) {
+        // The GPU backend keeps a copy of last frame's actors for orbits to read
+        // The CPU backend's copy of the actor buffer grows as actors are uploaded
+        if self.backend == AnimatorBackend::Gpu {
+            let length = actor_buffer.borrow().length();
+            self.previous_actor_buffer =
+                Some(Rc::new(RefCell::new(Buffer::new::<SpriteActorVertex>(
+                    length, false, false,
+                ))));
+        }
+        self.cpu_actors.clear();
+
         self.actor_buffer = Some(actor_buffer);
         self.instance_buffer = Some(instance_buffer);
     }
 
-    pub fn animate(&mut self, gfx: &mut GFX, delta_time: f64) {
+    pub fn upload_actors(
+        &mut self,
+        start: GLsizeiptr,
+        actors: &[Option<SpriteActorVertex>],
+        keep_motion_progress: bool,
+    ) {
+        if DEBUG && actors.is_empty() {
+            panic!("Must upload at least one actor");
+        }
+        if DEBUG && keep_motion_progress && actors.iter().any(Option::is_none) {
+            panic!("Freed slots can't keep their motion progress");
+        }
+        let write_actor =
+            |dest: &mut SpriteActorVertex, actor: &Option<SpriteActorVertex>| match actor {
+                Some(actor) if keep_motion_progress => dest.set_keeping_motion_progress(actor),
+                Some(actor) => *dest = *actor,
+                None => *dest = SpriteActorVertex::zeroed(),
+            };
+
+        match self.backend {
+            AnimatorBackend::Gpu => {
+                // Write the actors into the mapped range of the actor buffer, zeroing freed slots so they are not drawn
+                // Actors that keep their motion progress only have their other fields written
+                let actor_buffer = self
+                    .actor_buffer
+                    .as_ref()
+                    .expect("SpriteAnimator buffers are not set")
+                    .borrow();
+                let mut mapped = actor_buffer.map(start..start + actors.len() as GLsizeiptr);
+                actors
+                    .iter()
+                    .enumerate()
+                    .for_each(|(idx, actor)| write_actor(&mut mapped[idx], actor));
+            }
+            AnimatorBackend::Cpu => {
+                // Write the actors into the CPU copy of the actor buffer, zeroing freed slots so they are not drawn
+                let start = start as usize;
+                let end = start + actors.len();
+                if DEBUG {
+                    let length = self
+                        .actor_buffer
+                        .as_ref()
+                        .expect("SpriteAnimator buffers are not set")
+                        .borrow()
+                        .length() as usize;
+                    if end > length {
+                        panic!(
+                            "Actors {:?} are outside the valid range of sprites",
+                            start..end
+                        );
+                    }
+                }
+                if end > self.cpu_actors.len() {
+                    self.cpu_actors.resize(end, SpriteActorVertex::zeroed());
+                }
+                self.cpu_actors[start..end]
+                    .iter_mut()
+                    .zip(actors.iter())
+                    .for_each(|(dest, actor)| write_actor(dest, actor));
+            }
+        }
+    }
+
+    pub fn animate(&mut self, gfx: &mut GFX, delta_time: f64, current_time: f64) {
+        match self.backend {
+            AnimatorBackend::Gpu => self.animate_gpu(gfx, delta_time),
+            AnimatorBackend::Cpu => self.animate_cpu(delta_time),
+        }
+        self.capture_readback(current_time);
+    }
+
+    pub fn enable_readback(&mut self) {
+        if DEBUG && self.readback.is_some() {
+            panic!("Readback is already enabled");
+        }
+
+        let readback = match self.backend {
+            AnimatorBackend::Gpu => {
+                // Create a persistently mapped buffer to copy the actor buffer into
+                let length = self
+                    .actor_buffer
+                    .as_ref()
+                    .expect("SpriteAnimator buffers are not set")
+                    .borrow()
+                    .length();
+                let buffer = Buffer::new::<SpriteActorVertex>(length, true, false);
+                let mapped = buffer.map(..);
+                ActorReadback::Gpu {
+                    pending: None,
+                    mapped,
+                    buffer,
+                }
+            }
+            AnimatorBackend::Cpu => ActorReadback::Cpu { pending: None },
+        };
+        self.readback = Some(readback);
+    }
+
+    pub fn readback_enabled(&self) -> bool {
+        self.readback.is_some()
+    }
+
+    fn capture_readback(&mut self, current_time: f64) {
+        let readback = match self.readback.as_mut() {
+            Some(readback) => readback,
+            None => return,
+        };
+
+        match readback {
+            ActorReadback::Gpu {
+                pending, buffer, ..
+            } => {
+                // Only keep one capture in flight so the mapped data is not overwritten while unread
+                if pending.is_some() {
+                    return;
+                }
+
+                // Copy the actor buffer once the compute shader's writes are visible, then fence the copy
+                let actor_buffer = self
+                    .actor_buffer
+                    .as_ref()
+                    .expect("SpriteAnimator buffers are not set")
+                    .borrow();
+                unsafe {
+                    gl::MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+                    gl::CopyNamedBufferSubData(
+                        actor_buffer.handle(),
+                        buffer.handle(),
+                        0,
+                        0,
+                        actor_buffer.length() * actor_buffer.element_size() as GLsizeiptr,
+                    );
+                }
+                *pending = Some((Fence::new(), current_time));
+            }
+            ActorReadback::Cpu { pending } => *pending = Some(current_time),
+        }
+    }
+
+    pub fn poll_readback(&mut self) -> Option<ActorSnapshot> {
+        let readback = self.readback.as_mut().expect("Readback is not enabled");
+
+        match readback {
+            ActorReadback::Gpu {
+                pending,
+                mapped,
+                buffer,
+            } => {
+                // Nothing to read until the copy's fence has signaled
+                let capture_time = match pending {
+                    Some((fence, capture_time)) if fence.is_signaled() => *capture_time,
+                    _ => return None,
+                };
+                *pending = None;
+
+                // Copy the actors out of the mapped buffer
+                let actors = (0..buffer.length() as usize)
+                    .map(|idx| mapped[idx])
+                    .collect();
+                Some(ActorSnapshot {
+                    capture_time,
+                    actors,
+                })
+            }
+            ActorReadback::Cpu { pending } => pending.take().map(|capture_time| ActorSnapshot {
+                capture_time,
+                actors: self.cpu_actors.clone(),
+            }),
+        }
+    }
+
+    fn animate_gpu(&mut self, gfx: &mut GFX, delta_time: f64) {
+        let compute_pipeline = self
+            .compute_pipeline
+            .as_ref()
+            .expect("GPU sprite animator has no compute pipeline");
         let mut actor_buffer = None;
         let mut instance_buffer = None;
         swap(&mut actor_buffer, &mut self.actor_buffer);
@@ -124,14 +586,51 @@ impl SpriteAnimator {
         if DEBUG && (actor_buffer.is_none() || instance_buffer.is_none()) {
             panic!("SpriteAnimator buffers are not set");
         }
-        if let Some(delta_uniform_location) = self.compute_pipeline.borrow().program().uniform_location(FEATURE_DELTA_TIME_UNIFORM_NAME) {
-            self.compute_pipeline.borrow().program().set_uniform_f(delta_uniform_location, delta_time as f32);
+        {
+            let compute_pipeline = compute_pipeline.borrow();
+            let program = compute_pipeline.program();
+            if let Some(delta_uniform_location) =
+                program.uniform_location(FEATURE_DELTA_TIME_UNIFORM_NAME)
+            {
+                program.set_uniform_f(delta_uniform_location, delta_time as f32);
+            }
+            if let Some(aim_target_location) = program.uniform_location("u_aimTarget") {
+                program.set_uniform_vec2f(aim_target_location, self.aim_target);
+            }
+            if let Some(bounds_location) = program.uniform_location("u_boundsHalfSize") {
+                program.set_uniform_vec2f(bounds_location, self.motion_bounds_half_size);
+            }
+        }
+
+        // Snapshot the actors once uploads and last frame's dispatch are visible
+        let previous_actor_buffer = self
+            .previous_actor_buffer
+            .as_ref()
+            .expect("SpriteAnimator buffers are not set");
+        {
+            let actor_buffer = actor_buffer.as_ref().unwrap().borrow();
+            unsafe {
+                gl::MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT | gl::SHADER_STORAGE_BARRIER_BIT);
+                gl::CopyNamedBufferSubData(
+                    actor_buffer.handle(),
+                    previous_actor_buffer.borrow().handle(),
+                    0,
+                    0,
+                    actor_buffer.length() * actor_buffer.element_size() as GLsizeiptr,
+                );
+                gl::MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT);
+            }
         }
+
+        let motion_programs = self.motion_programs.borrow();
         gfx.dispatch_compute_1d(
-            self.compute_pipeline.borrow_mut(),
+            compute_pipeline.borrow_mut(),
             &[
                 actor_buffer.clone().unwrap().borrow(),
                 instance_buffer.clone().unwrap().borrow(),
+                motion_programs.step_buffer().borrow(),
+                motion_programs.range_buffer().borrow(),
+                previous_actor_buffer.borrow(),
             ],
             0..actor_buffer.clone().unwrap().borrow().length() as GLuint,
             MAX_DISPATCH,
@@ -140,6 +639,40 @@ impl SpriteAnimator {
         swap(&mut instance_buffer, &mut self.instance_buffer);
     }
 
+    fn animate_cpu(&mut self, delta_time: f64) {
+        // Nothing to animate until actors have been uploaded
+        if self.cpu_actors.is_empty() {
+            return;
+        }
+
+        let motion_programs = self.motion_programs.borrow();
+        let environment = MotionEnvironment::new(
+            &motion_programs,
+            self.aim_target,
+            self.motion_bounds_half_size,
+        );
+        integrate_actors(&mut self.cpu_actors, delta_time as f32, &environment);
+
+        // Build the instances and write them to the part of the instance buffer that is in use
+        let instance_buffer = self
+            .instance_buffer
+            .as_ref()
+            .expect("SpriteAnimator buffers are not set")
+            .borrow();
+        let mut mapped = instance_buffer.map(0..self.cpu_actors.len() as GLsizeiptr);
+        self.cpu_actors
+            .iter()
+            .enumerate()
+            .for_each(|(idx, actor)| mapped[idx] = actor.build_instance());
+    }
+
+    pub fn cpu_actors(&self) -> Option<&[SpriteActorVertex]> {
+        match self.backend {
+            AnimatorBackend::Gpu => None,
+            AnimatorBackend::Cpu => Some(&self.cpu_actors),
+        }
+    }
+
     pub fn actor_buffer(&self) -> Option<&Rc<RefCell<Buffer>>> {
         self.actor_buffer.as_ref()
     }
@@ -148,3 +681,27 @@ impl SpriteAnimator {
         self.instance_buffer.as_ref()
     }
 }
+
+pub fn integrate_actors(
+    actors: &mut [SpriteActorVertex],
+    delta_time: f32,
+    environment: &MotionEnvironment,
+) {
+    // Integrate every actor exactly like the compute shader does, orbiting parents where they were last frame
+    let parent_positions = actors
+        .iter()
+        .map(|actor| {
+            actor
+                .motion_parent()
+                .and_then(|parent| actors.get(parent))
+                .filter(|parent| actor.is_motion_parent(parent))
+                .map(|parent| parent.position(parent.last_updated() as f64))
+        })
+        .collect::<Vec<_>>();
+    actors
+        .iter_mut()
+        .zip(parent_positions.into_iter())
+        .for_each(|(actor, parent_position)| {
+            actor.integrate(delta_time, environment, parent_position)
+        });
+}
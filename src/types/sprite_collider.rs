@@ -0,0 +1,376 @@
+use crate::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const COMPUTE_SHADER: &str = "
+#[feature(batch)]
+layout (local_size_x = 128) in;
+
+struct Actor
+{
+    vec4 positionVelocity;
+    vec4 scaleVelocity;
+    vec4 rotationUpdatedAnimtimeRadius;
+    vec4 rectangle;
+    uvec2 motionProgramStep;
+    float motionStepTime;
+    uint motionParent;
+    uint generation;
+    uint motionParentGeneration;
+    uvec2 padding;
+};
+
+struct Target
+{
+    vec2 position;
+    float radius;
+    uint id;
+};
+
+struct Hit
+{
+    uint actor;
+    uint generation;
+    uint target;
+};
+
+layout (std140, binding = 0) buffer buffer_Actors
+{
+    Actor actors[];
+};
+
+layout (std140, binding = 1) buffer buffer_Targets
+{
+    Target targets[];
+};
+
+layout (std430, binding = 2) buffer buffer_Hits
+{
+    Hit hits[];
+};
+
+layout (binding = 0) uniform atomic_uint hitCount;
+
+uniform uint u_actorCount;
+uniform uint u_targetCount;
+uniform uint u_maxHits;
+
+void main()
+{
+    uint index = batchOffset() + gl_GlobalInvocationID.x;
+    if (index >= u_actorCount)
+    {
+        return;
+    }
+
+    Actor actor = actors[index];
+    float radius = actor.rotationUpdatedAnimtimeRadius.w;
+    if (radius <= 0.0)
+    {
+        return;
+    }
+
+    for (uint targetIdx = 0; targetIdx < u_targetCount; targetIdx++)
+    {
+        Target target = targets[targetIdx];
+        vec2 offset = actor.positionVelocity.xy - target.position;
+        float reach = radius + target.radius;
+        if (dot(offset, offset) <= reach * reach)
+        {
+            uint hitIdx = atomicCounterIncrement(hitCount);
+            if (hitIdx < u_maxHits)
+            {
+                hits[hitIdx] = Hit(index, actor.generation, target.id);
+            }
+        }
+    }
+}";
+
+const MAX_DISPATCH: GLuint = 128;
+pub const MAX_COLLISION_TARGETS: usize = 16;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CollisionTarget {
+    position: Vec2f,
+    radius: f32,
+    id: u32,
+}
+
+impl CollisionTarget {
+    pub fn new(position: Vec2f, radius: f32, id: u32) -> Self {
+        Self {
+            position,
+            radius,
+            id,
+        }
+    }
+
+    pub fn position(&self) -> Vec2f {
+        self.position
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CollisionHit {
+    actor_idx: u32,
+    actor_generation: u32,
+    target_id: u32,
+}
+
+impl CollisionHit {
+    pub fn actor_idx(&self) -> usize {
+        self.actor_idx as usize
+    }
+
+    pub fn target_id(&self) -> u32 {
+        self.target_id
+    }
+
+    pub fn hits_sprite(&self, sprite: &SpriteReference) -> bool {
+        // Hits are read a frame late, so the slot may hold a newer sprite by now
+        self.actor_idx() == sprite.idx()
+            && self.actor_generation == sprite.generation().wrapping_add(1)
+    }
+}
+
+#[derive(Debug)]
+enum CollisionPass {
+    Gpu {
+        compute_pipeline: Rc<RefCell<ComputePipeline>>,
+        pending: Option<Fence>,
+        mapped_hits: BufferMap<CollisionHit>,
+        mapped_hit_count: BufferMap<GLuint>,
+        target_buffer: Buffer,
+        hit_buffer: Buffer,
+        hit_count_buffer: Buffer,
+    },
+    Cpu {
+        pending: Option<Vec<CollisionHit>>,
+    },
+}
+
+#[derive(Debug)]
+pub struct SpriteCollider {
+    backend: AnimatorBackend,
+    max_hits: usize,
+    pass: CollisionPass,
+}
+
+impl SpriteCollider {
+    pub fn new(max_hits: usize) -> Self {
+        Self::with_backend(AnimatorBackend::Gpu, max_hits)
+    }
+
+    pub fn with_backend(backend: AnimatorBackend, max_hits: usize) -> Self {
+        if DEBUG && max_hits == 0 {
+            panic!("Max hits must be greater than 0");
+        }
+
+        let pass = match backend {
+            AnimatorBackend::Gpu => {
+                // Create the compute pipeline
+                let program = Program::new(ShaderStage::Compute, COMPUTE_SHADER);
+                let compute_pipeline = Rc::new(RefCell::new(ComputePipeline::new(program)));
+
+                // Create the target buffer and the persistently mapped result buffers
+                let target_buffer = Buffer::new::<CollisionTarget>(
+                    MAX_COLLISION_TARGETS as GLsizeiptr,
+                    false,
+                    true,
+                );
+                let hit_buffer = Buffer::new::<CollisionHit>(max_hits as GLsizeiptr, true, false);
+                let hit_count_buffer = Buffer::new::<GLuint>(1, true, false);
+                let mapped_hits = hit_buffer.map(..);
+                let mapped_hit_count = hit_count_buffer.map(..);
+                CollisionPass::Gpu {
+                    compute_pipeline,
+                    pending: None,
+                    mapped_hits,
+                    mapped_hit_count,
+                    target_buffer,
+                    hit_buffer,
+                    hit_count_buffer,
+                }
+            }
+            // The CPU backend tests the sprite list's CPU actors directly
+            AnimatorBackend::Cpu => CollisionPass::Cpu { pending: None },
+        };
+
+        Self {
+            backend,
+            max_hits,
+            pass,
+        }
+    }
+
+    pub fn backend(&self) -> AnimatorBackend {
+        self.backend
+    }
+
+    pub fn max_hits(&self) -> usize {
+        self.max_hits
+    }
+
+    pub fn is_pending(&self) -> bool {
+        match &self.pass {
+            CollisionPass::Gpu { pending, .. } => pending.is_some(),
+            CollisionPass::Cpu { pending } => pending.is_some(),
+        }
+    }
+
+    pub fn check(
+        &mut self,
+        gfx: &mut GFX,
+        sprite_list: &SpriteList,
+        targets: &[CollisionTarget],
+    ) -> bool {
+        if DEBUG && targets.len() > MAX_COLLISION_TARGETS {
+            panic!(
+                "Cannot check against {} targets; the maximum is {}",
+                targets.len(),
+                MAX_COLLISION_TARGETS
+            );
+        }
+        if DEBUG && sprite_list.animator_backend() != self.backend {
+            panic!(
+                "{:?} sprite collider can't check a sprite list animated by the {:?} backend",
+                self.backend,
+                sprite_list.animator_backend()
+            );
+        }
+
+        // Only keep one check in flight so the results are not overwritten while unread
+        if self.is_pending() || targets.is_empty() || sprite_list.actor_count() == 0 {
+            return false;
+        }
+
+        let max_hits = self.max_hits;
+        match &mut self.pass {
+            CollisionPass::Gpu {
+                compute_pipeline,
+                pending,
+                target_buffer,
+                hit_buffer,
+                hit_count_buffer,
+                ..
+            } => {
+                // Upload the targets
+                {
+                    let mut mapped = target_buffer.map(0..targets.len() as GLsizeiptr);
+                    targets
+                        .iter()
+                        .enumerate()
+                        .for_each(|(idx, &target)| mapped[idx] = target);
+                }
+
+                // Reset the hit counter
+                let zero: GLuint = 0;
+                unsafe {
+                    gl::ClearNamedBufferData(
+                        hit_count_buffer.handle(),
+                        gl::R32UI,
+                        gl::RED_INTEGER,
+                        gl::UNSIGNED_INT,
+                        &zero as *const GLuint as *const _,
+                    )
+                };
+
+                // Set the uniforms
+                {
+                    let compute_pipeline = compute_pipeline.borrow();
+                    let program = compute_pipeline.program();
+                    if let Some(location) = program.uniform_location("u_actorCount") {
+                        program.set_uniform_uint(location, sprite_list.actor_count() as GLuint);
+                    }
+                    if let Some(location) = program.uniform_location("u_targetCount") {
+                        program.set_uniform_uint(location, targets.len() as GLuint);
+                    }
+                    if let Some(location) = program.uniform_location("u_maxHits") {
+                        program.set_uniform_uint(location, max_hits as GLuint);
+                    }
+                }
+
+                // Run the collision pass with the hit counter bound
+                let actor_buffer = sprite_list.actor_buffer().borrow();
+                unsafe {
+                    gl::BindBufferBase(gl::ATOMIC_COUNTER_BUFFER, 0, hit_count_buffer.handle())
+                };
+                gfx.dispatch_compute_1d(
+                    compute_pipeline.borrow_mut(),
+                    &[&*actor_buffer, &*target_buffer, &*hit_buffer],
+                    0..sprite_list.actor_count() as GLuint,
+                    MAX_DISPATCH,
+                );
+                unsafe { gl::BindBufferBase(gl::ATOMIC_COUNTER_BUFFER, 0, 0) };
+
+                // Make the results visible to the mapped buffers and fence them
+                unsafe { gl::MemoryBarrier(gl::CLIENT_MAPPED_BUFFER_BARRIER_BIT) };
+                *pending = Some(Fence::new());
+            }
+            CollisionPass::Cpu { pending } => {
+                // Same test as the compute shader, keeping hits in actor order
+                let actors = sprite_list
+                    .cpu_actors()
+                    .expect("CPU sprite collider needs a CPU animated sprite list");
+                let hits = actors
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, actor)| actor.hitbox_radius() > 0.0)
+                    .flat_map(|(idx, actor)| {
+                        let position = actor.position(actor.last_updated() as f64);
+                        targets
+                            .iter()
+                            .filter(move |target| {
+                                let offset = position - target.position;
+                                let reach = actor.hitbox_radius() + target.radius;
+                                offset[0] * offset[0] + offset[1] * offset[1] <= reach * reach
+                            })
+                            .map(move |target| CollisionHit {
+                                actor_idx: idx as u32,
+                                actor_generation: actor
+                                    .generation()
+                                    .map_or(0, |generation| generation.wrapping_add(1)),
+                                target_id: target.id,
+                            })
+                    })
+                    .take(max_hits)
+                    .collect();
+                *pending = Some(hits);
+            }
+        }
+        true
+    }
+
+    pub fn poll_hits(&mut self) -> Option<Vec<CollisionHit>> {
+        match &mut self.pass {
+            CollisionPass::Gpu {
+                pending,
+                mapped_hits,
+                mapped_hit_count,
+                ..
+            } => {
+                // Nothing to read until the check's fence has signaled
+                match pending {
+                    Some(fence) if fence.is_signaled() => (),
+                    _ => return None,
+                }
+                *pending = None;
+
+                // Copy the hits out of the mapped buffer, ignoring any that did not fit
+                let hit_count = (mapped_hit_count[0] as usize).min(self.max_hits);
+                Some((0..hit_count).map(|idx| mapped_hits[idx]).collect())
+            }
+            CollisionPass::Cpu { pending } => pending.take(),
+        }
+    }
+}
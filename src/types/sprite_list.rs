@@ -1,22 +1,36 @@
 use crate::*;
 use fennec_algebra::*;
 use std::cell::RefCell;
-use std::ops::Range;
+use std::collections::{BTreeMap, HashSet};
 use std::rc::Rc;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct SpriteReference {
     idx: usize,
+    generation: u32,
+}
+
+impl SpriteReference {
+    pub fn idx(&self) -> usize {
+        self.idx
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
 }
 
 #[derive(Debug)]
 pub struct SpriteList {
     max_sprites: GLsizeiptr,
     actors: Vec<Option<SpriteActorVertex>>,
+    generations: Vec<u32>,
+    cull_half_size: Option<Vec2f>,
+    culled: HashSet<SpriteReference>,
     sprite_material: Rc<RefCell<SpriteMaterial>>,
     sprite_animator: SpriteAnimator,
     sprite_model: Option<Model>,
-    range_changed: Option<Range<GLsizeiptr>>,
+    changed_slots: BTreeMap<usize, bool>,
     texture_size: Vec2f,
 }
 
@@ -59,10 +73,13 @@ impl SpriteList {
         Self {
             max_sprites,
             actors: Vec::with_capacity(max_sprites as usize),
+            generations: Vec::with_capacity(max_sprites as usize),
+            cull_half_size: None,
+            culled: HashSet::new(),
             sprite_material,
             sprite_animator,
             sprite_model: Some(sprite_model),
-            range_changed: None,
+            changed_slots: BTreeMap::new(),
             texture_size,
         }
     }
@@ -80,13 +97,9 @@ impl SpriteList {
         }
     }
 
-    fn mark_sprite_changed(&mut self, idx: usize) {
-        if let Some(range_changed) = self.range_changed.as_mut() {
-            *range_changed =
-                range_changed.start.min(idx as isize)..range_changed.end.max(idx as isize + 1);
-        } else {
-            self.range_changed = Some(idx as isize..idx as isize + 1);
-        }
+    fn mark_sprite_changed(&mut self, idx: usize, motion_replaced: bool) {
+        // Slots whose motion was replaced are uploaded whole, the rest keep the animator's motion progress
+        *self.changed_slots.entry(idx).or_insert(false) |= motion_replaced;
     }
 
     pub fn new_sprite_object(&mut self, sprite: SpriteActorVertex) -> SpriteObject {
@@ -94,40 +107,67 @@ impl SpriteList {
     }
 
     pub fn add_sprite(&mut self, sprite: SpriteActorVertex) -> SpriteReference {
-        if let Some(idx) = self.next_available_idx() {
-            if idx < self.actors.len() {
-                self.actors[idx] = Some(sprite);
-            } else {
-                self.actors.push(Some(sprite));
-            }
-            self.mark_sprite_changed(idx);
-            SpriteReference { idx }
-        } else {
-            panic!("No available sprite indices left");
+        self.try_add_sprite(sprite)
+            .expect("No available sprite indices left")
+    }
+
+    pub fn try_add_sprite(&mut self, mut sprite: SpriteActorVertex) -> Option<SpriteReference> {
+        // A full list adds nothing, so callers can drop sprites that don't fit
+        let idx = self.next_available_idx()?;
+        if idx >= self.actors.len() {
+            self.actors.push(None);
+            self.generations.push(0);
         }
+        sprite.set_generation(self.generations[idx]);
+        self.actors[idx] = Some(sprite);
+        self.mark_sprite_changed(idx, true);
+        Some(SpriteReference {
+            idx,
+            generation: self.generations[idx],
+        })
     }
 
     pub fn remove_sprite(&mut self, sprite: SpriteReference) {
-        self.actors[sprite.idx] = None;
-        self.mark_sprite_changed(sprite.idx);
+        // Sprites that were already culled have nothing left to remove
+        if self.is_alive(&sprite) {
+            self.free_slot(sprite.idx);
+        }
+    }
+
+    fn free_slot(&mut self, idx: usize) {
+        // Bump the slot's generation so every reference to the old sprite becomes invalid
+        self.actors[idx] = None;
+        self.generations[idx] = self.generations[idx].wrapping_add(1);
+        self.mark_sprite_changed(idx, true);
+    }
+
+    pub fn is_alive(&self, sprite: &SpriteReference) -> bool {
+        sprite.idx < self.actors.len()
+            && self.generations[sprite.idx] == sprite.generation
+            && self.actors[sprite.idx].is_some()
     }
 
     pub fn change_sprite(&mut self, sprite: &SpriteReference, f: impl Fn(&mut SpriteActorVertex)) {
-        if let Some(sprite_instance) = self.actors[sprite.idx].as_mut() {
-            f(sprite_instance);
-            self.mark_sprite_changed(sprite.idx);
-        } else {
+        if !self.is_alive(sprite) {
             panic!(
                 "SpriteReference {:?} does not point to a valid sprite",
                 sprite
             );
         }
+        let actor = self.actors[sprite.idx].as_mut().unwrap();
+        let previous = *actor;
+        f(actor);
+        let motion_replaced = !actor.same_motion(&previous);
+        self.mark_sprite_changed(sprite.idx, motion_replaced);
     }
 
     pub fn sprite_actor(&self, sprite: &SpriteReference) -> Option<&SpriteActorVertex> {
         if DEBUG && sprite.idx > self.actors.len() {
             panic!("Idx {:?} is outside the valid range of sprites", sprite.idx);
         }
+        if self.generations[sprite.idx] != sprite.generation {
+            return None;
+        }
         self.actors[sprite.idx].as_ref()
     }
 
@@ -135,33 +175,147 @@ impl SpriteList {
         if DEBUG && sprite.idx > self.actors.len() {
             panic!("Idx {:?} is outside the valid range of sprites", sprite.idx);
         }
+        if self.generations[sprite.idx] != sprite.generation {
+            return None;
+        }
         self.actors[sprite.idx].as_mut()
     }
 
-    pub fn draw(&mut self, gfx: &mut GFX, delta_time: f64) {
-        if let Some(range_changed) = &self.range_changed {
-            let range_changed =
-                range_changed.start..range_changed.end.min(self.actors.len() as isize);
-            {
-                let actor_buffer = self
-                    .sprite_animator
-                    .actor_buffer()
-                    .expect("Sprite animator does not have its buffers set")
-                    .borrow();
-                {
-                    let mut mapped = actor_buffer.map(range_changed.clone());
-                    for idx in 0..range_changed.end - range_changed.start {
-                        if let Some(sprite_instance) =
-                            self.sprite_actor(&SpriteReference {idx: (range_changed.start + idx) as usize})
-                        {
-                            mapped[idx as usize] = *sprite_instance;
-                        }
-                    }
-                }
+    pub fn set_cull_bounds(&mut self, playing_field: &PlayingField, margin: f32) {
+        if DEBUG && margin < 0.0 {
+            panic!("Cull margin must not be negative");
+        }
+        self.cull_half_size = Some(playing_field.size() * 0.5 + vector!(margin, margin));
+    }
+
+    pub fn disable_culling(&mut self) {
+        self.cull_half_size = None;
+    }
+
+    pub fn cull(&mut self, current_time: f64) {
+        let half_size = match self.cull_half_size {
+            Some(half_size) => half_size,
+            None => return,
+        };
+
+        // Free every sprite whose position is outside the field plus its margin
+        // Sprites moved by motion programs are only accurate here when readback is applied
+        let outside = self
+            .actors
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, actor)| actor.as_ref().map(|actor| (idx, actor)))
+            .filter(|(_, actor)| {
+                let position = actor.position(current_time);
+                position[0] < -half_size[0]
+                    || position[0] > half_size[0]
+                    || position[1] < -half_size[1]
+                    || position[1] > half_size[1]
+            })
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        for idx in outside {
+            self.culled.insert(SpriteReference {
+                idx,
+                generation: self.generations[idx],
+            });
+            self.free_slot(idx);
+        }
+    }
+
+    pub fn take_culled(&mut self) -> HashSet<SpriteReference> {
+        // The returned references compare equal to the owners' now-invalid references, and come as
+        // a set so owners can check their sprites against them cheaply
+        std::mem::take(&mut self.culled)
+    }
+
+    pub fn actor_count(&self) -> usize {
+        self.actors.len()
+    }
+
+    pub fn sprite_at(&self, idx: usize) -> Option<SpriteReference> {
+        // The live sprite in a slot, if there is one
+        self.actors
+            .get(idx)
+            .and_then(Option::as_ref)
+            .map(|_| SpriteReference {
+                idx,
+                generation: self.generations[idx],
+            })
+    }
+
+    pub fn animator_backend(&self) -> AnimatorBackend {
+        self.sprite_animator.backend()
+    }
+
+    pub fn cpu_actors(&self) -> Option<&[SpriteActorVertex]> {
+        self.sprite_animator.cpu_actors()
+    }
+
+    pub fn actor_buffer(&self) -> &Rc<RefCell<Buffer>> {
+        self.sprite_animator
+            .actor_buffer()
+            .expect("Sprite animator does not have its buffers set")
+    }
+
+    pub fn register_motion_program(&mut self, program: &MotionProgram) -> MotionProgramReference {
+        self.sprite_animator.register_motion_program(program)
+    }
+
+    pub fn set_aim_target(&mut self, aim_target: Vec2f) {
+        self.sprite_animator.set_aim_target(aim_target);
+    }
+
+    pub fn set_motion_bounds(&mut self, bounds_half_size: Vec2f) {
+        self.sprite_animator.set_motion_bounds(bounds_half_size);
+    }
+
+    pub fn enable_readback(&mut self) {
+        self.sprite_animator.enable_readback();
+    }
+
+    pub fn apply_readback(&mut self) -> bool {
+        // Get the latest finished capture of the animated actors, if any
+        let snapshot = match self.sprite_animator.poll_readback() {
+            Some(snapshot) => snapshot,
+            None => return false,
+        };
+        let capture_time = snapshot.capture_time();
+
+        // Replace each actor with its animated state, unless it was changed on the CPU after the capture
+        self.actors
+            .iter_mut()
+            .zip(snapshot.actors().iter())
+            .filter_map(|(actor, animated)| actor.as_mut().map(|actor| (actor, animated)))
+            .filter(|(actor, _)| actor.last_updated() <= capture_time as f32)
+            .for_each(|(actor, animated)| actor.set_animated_state(animated, capture_time));
+        true
+    }
+
+    pub fn draw(&mut self, gfx: &mut GFX, delta_time: f64, current_time: f64) {
+        // Nothing to animate or draw until a sprite has been added
+        if self.actors.is_empty() {
+            return;
+        }
+        self.cull(current_time);
+
+        // Upload each run of changed actors to the sprite animator
+        // Unchanged actors in between are left alone, since the animator has moved them on since they were uploaded
+        let changed_slots = std::mem::take(&mut self.changed_slots);
+        let mut changed_slots = changed_slots.into_iter().peekable();
+        while let Some((start, motion_replaced)) = changed_slots.next() {
+            let mut end = start + 1;
+            while changed_slots.peek() == Some(&(end, motion_replaced)) {
+                changed_slots.next();
+                end += 1;
             }
-            self.range_changed = None;
+            self.sprite_animator.upload_actors(
+                start as GLsizeiptr,
+                &self.actors[start..end],
+                !motion_replaced,
+            );
         }
-        self.sprite_animator.animate(gfx, delta_time);
+        self.sprite_animator.animate(gfx, delta_time, current_time);
         gfx.draw_model(
             self.sprite_model.as_ref().unwrap(),
             self.actors.len() as GLsizei,
@@ -66,4 +66,8 @@ impl SpriteObject {
     pub fn set_rectangle(&mut self, sprite_list: &mut SpriteList, rectangle: Vec4f, current_time: f64) {
         sprite_list.change_sprite(&self.sprite_reference, |actor| actor.set_rectangle(rectangle, current_time))
     }
-}
\ No newline at end of file
+
+    pub fn remove(self, sprite_list: &mut SpriteList) {
+        sprite_list.remove_sprite(self.sprite_reference)
+    }
+}
@@ -23,21 +23,18 @@ pub struct Texture<const TYPE: crate::TextureType> {
     gl_handle: IntHandle,
     size: Vec2u,
     sprites: HashMap<String, Vec<Vec4f>>,
+    source: Option<(FileWatch, image::ImageFormat)>,
 }
 
 impl<const TYPE: crate::TextureType> Texture<TYPE> {
     pub fn new(size: Vec2u, count: i32) -> Vec<Self> {
+        Self::try_new(size, count).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_new(size: Vec2u, count: i32) -> BloomResult<Vec<Self>> {
         // Check that the width and height are powers of 2
-        if DEBUG {
-            if !size[0].is_power_of_2() {
-                if !size[1].is_power_of_2() {
-                    panic!("Dimensions are not a power of 2");
-                }
-                panic!("Width is not a power of 2");
-            }
-            if !size[1].is_power_of_2() {
-                panic!("Height is not a power of 2");
-            }
+        if DEBUG && (!size[0].is_power_of_2() || !size[1].is_power_of_2()) {
+            return Err(BloomError::InvalidTextureSize(size));
         }
 
         // Create handle array
@@ -61,37 +58,66 @@ impl<const TYPE: crate::TextureType> Texture<TYPE> {
         }
 
         // Wrap the handles and return the wrappers
-        handles
+        Ok(handles
             .drain(..)
             .map(|gl_handle| Self {
                 gl_handle,
                 size,
                 sprites: HashMap::new(),
+                source: None,
             })
-            .collect()
+            .collect())
     }
 
     pub fn from_bytes(bytes: &[u8], format: image::ImageFormat) -> Self {
-        let image = image::load_from_memory_with_format(bytes, format).unwrap();
-        let image = image.flipv().into_bgra();
-        let mut tex = Self::new(vector!(image.width(), image.height()), 1)
-            .pop()
-            .unwrap();
-        let data = image.into_raw();
-        tex.set_data_bytes(&data);
-        tex
+        Self::try_from_bytes(bytes, format).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_from_bytes(bytes: &[u8], format: image::ImageFormat) -> BloomResult<Self> {
+        let image = image::load_from_memory_with_format(bytes, format)
+            .map_err(|error| BloomError::Image { path: None, error })?;
+        Self::try_from_image(image)
     }
 
     pub fn from_file(path: impl AsRef<Path>, format: image::ImageFormat) -> Self {
-        let file = BufReader::new(File::open(path).unwrap());
-        let image = image::load(file, format).unwrap();
+        Self::try_from_file(path, format).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_from_file(path: impl AsRef<Path>, format: image::ImageFormat) -> BloomResult<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|error| BloomError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let image =
+            image::load(BufReader::new(file), format).map_err(|error| BloomError::Image {
+                path: Some(path.to_path_buf()),
+                error,
+            })?;
+        let mut tex = Self::try_from_image(image)?;
+        tex.source = Some((FileWatch::new(path), format));
+        Ok(tex)
+    }
+
+    fn try_from_image(image: image::DynamicImage) -> BloomResult<Self> {
         let image = image.flipv().into_bgra();
-        let mut tex = Self::new(vector!(image.width(), image.height()), 1)
+        let mut tex = Self::try_new(vector!(image.width(), image.height()), 1)?
             .pop()
             .unwrap();
         let data = image.into_raw();
         tex.set_data_bytes(&data);
-        tex
+        Ok(tex)
+    }
+
+    pub fn placeholder() -> Self {
+        // A single white pixel, so anything drawn with it still shows up
+        let texture = Self::new(vector!(1, 1), 1).pop().unwrap();
+        texture.set_data(&[vector!(255, 255, 255, 255)]);
+        texture
+    }
+
+    pub fn source_path(&self) -> Option<&Path> {
+        self.source.as_ref().map(|(source, _)| source.path())
     }
 
     pub fn set_data(&self, bgra_data: &[Vector<u8, 4>]) {
@@ -160,6 +186,47 @@ impl<const TYPE: crate::TextureType> Texture<TYPE> {
     }
 }
 
+impl<const TYPE: crate::TextureType> HotReload for Texture<TYPE> {
+    fn hot_reload(&self) -> Result<bool, String> {
+        let (source, format) = match self.source.as_ref() {
+            Some((source, format)) if source.changed() => (source, *format),
+            _ => return Ok(false),
+        };
+        let error = |error: &dyn std::fmt::Display| {
+            format!("Could not reload texture {:?}: {}", source.path(), error)
+        };
+        let file = BufReader::new(File::open(source.path()).map_err(|e| error(&e))?);
+        let image = image::load(file, format).map_err(|e| error(&e))?;
+        let image = image.flipv().into_bgra();
+
+        // The texture's storage is immutable, so only same-sized images can replace it in place
+        if image.width() != self.size[0] || image.height() != self.size[1] {
+            return Err(error(&format!(
+                "size changed from {}x{} to {}x{}; restart to load it",
+                self.size[0],
+                self.size[1],
+                image.width(),
+                image.height()
+            )));
+        }
+        let data = image.into_raw();
+        unsafe {
+            gl::TextureSubImage2D(
+                self.handle(),
+                0,
+                0,
+                0,
+                self.size[0] as GLsizei,
+                self.size[1] as GLsizei,
+                gl::BGRA,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as *const _,
+            )
+        };
+        Ok(true)
+    }
+}
+
 impl<const TYPE: crate::TextureType> GLHandle for Texture<TYPE> {
     fn handle(&self) -> IntHandle {
         self.gl_handle
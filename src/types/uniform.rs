@@ -0,0 +1,261 @@
+use crate::*;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UniformInfo {
+    name: String,
+    location: GLint,
+    gl_type: GLenum,
+    size: GLint,
+}
+
+impl UniformInfo {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn location(&self) -> GLint {
+        self.location
+    }
+
+    pub fn gl_type(&self) -> GLenum {
+        self.gl_type
+    }
+
+    pub fn type_name(&self) -> String {
+        gl_type_name(self.gl_type)
+    }
+
+    // Number of array elements, 1 for uniforms that aren't arrays
+    pub fn size(&self) -> GLint {
+        self.size
+    }
+
+    pub fn accepts(&self, gl_type: GLenum) -> bool {
+        // Samplers and images are set to a texture unit, and bools can be set from integers
+        gl_type == self.gl_type
+            || (gl_type == gl::INT && is_opaque_type(self.gl_type))
+            || (self.gl_type == gl::BOOL && (gl_type == gl::INT || gl_type == gl::UNSIGNED_INT))
+    }
+}
+
+pub fn reflect_uniforms(gl_handle: IntHandle) -> HashMap<String, UniformInfo> {
+    let mut count: GLint = 0;
+    let mut max_name_length: GLint = 0;
+    unsafe {
+        gl::GetProgramiv(gl_handle, gl::ACTIVE_UNIFORMS, &mut count);
+        gl::GetProgramiv(
+            gl_handle,
+            gl::ACTIVE_UNIFORM_MAX_LENGTH,
+            &mut max_name_length,
+        );
+    }
+
+    let mut uniforms = HashMap::new();
+    let mut name = vec![0u8; max_name_length.max(1) as usize];
+    for idx in 0..count.max(0) as GLuint {
+        let mut length: GLsizei = 0;
+        let mut size: GLint = 0;
+        let mut gl_type: GLenum = 0;
+        let location = unsafe {
+            gl::GetActiveUniform(
+                gl_handle,
+                idx,
+                name.len() as GLsizei,
+                &mut length,
+                &mut size,
+                &mut gl_type,
+                name.as_mut_ptr() as *mut GLchar,
+            );
+            gl::GetUniformLocation(gl_handle, name.as_ptr() as *const GLchar)
+        };
+
+        // Uniforms inside blocks don't have a location and can't be set individually
+        if location < 0 {
+            continue;
+        }
+
+        // Arrays are reported as their first element, but are set through their plain name
+        let uniform_name = String::from_utf8_lossy(&name[..length.max(0) as usize]);
+        let uniform_name = uniform_name
+            .strip_suffix("[0]")
+            .unwrap_or(&uniform_name)
+            .to_string();
+        uniforms.insert(
+            uniform_name.clone(),
+            UniformInfo {
+                name: uniform_name,
+                location,
+                gl_type,
+                size,
+            },
+        );
+    }
+    uniforms
+}
+
+fn is_opaque_type(gl_type: GLenum) -> bool {
+    matches!(
+        gl_type,
+        gl::SAMPLER_1D
+            | gl::SAMPLER_2D
+            | gl::SAMPLER_3D
+            | gl::SAMPLER_CUBE
+            | gl::SAMPLER_1D_ARRAY
+            | gl::SAMPLER_2D_ARRAY
+            | gl::SAMPLER_2D_SHADOW
+            | gl::SAMPLER_2D_MULTISAMPLE
+            | gl::SAMPLER_BUFFER
+            | gl::INT_SAMPLER_2D
+            | gl::UNSIGNED_INT_SAMPLER_2D
+            | gl::IMAGE_2D
+            | gl::IMAGE_BUFFER
+            | gl::INT_IMAGE_2D
+            | gl::UNSIGNED_INT_IMAGE_2D
+    )
+}
+
+pub fn gl_type_name(gl_type: GLenum) -> String {
+    let name = match gl_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::BOOL => "bool",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::IMAGE_2D => "image2D",
+        _ => return format!("GL type 0x{:04X}", gl_type),
+    };
+    name.to_string()
+}
+
+// A single value that can be stored in a uniform (or in one element of a uniform array)
+pub trait UniformElement: Copy {
+    const GL_TYPE: GLenum;
+
+    /// # Safety
+    /// `location` must be a uniform of the program with a type that accepts `GL_TYPE`, and hold
+    /// at least `values.len()` elements.
+    unsafe fn upload(gl_handle: IntHandle, location: GLint, values: &[Self]);
+}
+
+macro_rules! impl_uniform_element {
+    ($type:ty, $gl_type:expr, $function:ident, $scalar:ty) => {
+        impl UniformElement for $type {
+            const GL_TYPE: GLenum = $gl_type;
+
+            unsafe fn upload(gl_handle: IntHandle, location: GLint, values: &[Self]) {
+                gl::$function(
+                    gl_handle,
+                    location,
+                    values.len() as GLsizei,
+                    values.as_ptr() as *const $scalar,
+                );
+            }
+        }
+    };
+}
+
+impl_uniform_element!(f32, gl::FLOAT, ProgramUniform1fv, f32);
+impl_uniform_element!(Vec2f, gl::FLOAT_VEC2, ProgramUniform2fv, f32);
+impl_uniform_element!(Vec3f, gl::FLOAT_VEC3, ProgramUniform3fv, f32);
+impl_uniform_element!(Vec4f, gl::FLOAT_VEC4, ProgramUniform4fv, f32);
+impl_uniform_element!(i32, gl::INT, ProgramUniform1iv, GLint);
+impl_uniform_element!(Vec2i, gl::INT_VEC2, ProgramUniform2iv, GLint);
+impl_uniform_element!(Vec3i, gl::INT_VEC3, ProgramUniform3iv, GLint);
+impl_uniform_element!(Vec4i, gl::INT_VEC4, ProgramUniform4iv, GLint);
+impl_uniform_element!(u32, gl::UNSIGNED_INT, ProgramUniform1uiv, GLuint);
+impl_uniform_element!(Vec2u, gl::UNSIGNED_INT_VEC2, ProgramUniform2uiv, GLuint);
+impl_uniform_element!(Vec3u, gl::UNSIGNED_INT_VEC3, ProgramUniform3uiv, GLuint);
+impl_uniform_element!(Vec4u, gl::UNSIGNED_INT_VEC4, ProgramUniform4uiv, GLuint);
+
+impl UniformElement for bool {
+    const GL_TYPE: GLenum = gl::BOOL;
+
+    unsafe fn upload(gl_handle: IntHandle, location: GLint, values: &[Self]) {
+        let values = values
+            .iter()
+            .map(|&value| value as GLint)
+            .collect::<Vec<GLint>>();
+        gl::ProgramUniform1iv(
+            gl_handle,
+            location,
+            values.len() as GLsizei,
+            values.as_ptr(),
+        );
+    }
+}
+
+impl UniformElement for Mat4f {
+    const GL_TYPE: GLenum = gl::FLOAT_MAT4;
+
+    unsafe fn upload(gl_handle: IntHandle, location: GLint, values: &[Self]) {
+        gl::ProgramUniformMatrix4fv(
+            gl_handle,
+            location,
+            values.len() as GLsizei,
+            gl::FALSE,
+            values.as_ptr() as *const f32,
+        );
+    }
+}
+
+// Anything that can be passed to Program::set_uniform: a single element or an array of them
+pub trait UniformValue {
+    const GL_TYPE: GLenum;
+
+    fn element_count(&self) -> usize;
+
+    /// # Safety
+    /// See `UniformElement::upload`.
+    unsafe fn upload(&self, gl_handle: IntHandle, location: GLint);
+}
+
+impl<T: UniformElement> UniformValue for T {
+    const GL_TYPE: GLenum = <T as UniformElement>::GL_TYPE;
+
+    fn element_count(&self) -> usize {
+        1
+    }
+
+    unsafe fn upload(&self, gl_handle: IntHandle, location: GLint) {
+        <T as UniformElement>::upload(gl_handle, location, std::slice::from_ref(self));
+    }
+}
+
+impl<T: UniformElement> UniformValue for [T] {
+    const GL_TYPE: GLenum = <T as UniformElement>::GL_TYPE;
+
+    fn element_count(&self) -> usize {
+        self.len()
+    }
+
+    unsafe fn upload(&self, gl_handle: IntHandle, location: GLint) {
+        <T as UniformElement>::upload(gl_handle, location, self);
+    }
+}
+
+impl<T: UniformElement, const N: usize> UniformValue for [T; N] {
+    const GL_TYPE: GLenum = <T as UniformElement>::GL_TYPE;
+
+    fn element_count(&self) -> usize {
+        N
+    }
+
+    unsafe fn upload(&self, gl_handle: IntHandle, location: GLint) {
+        <T as UniformElement>::upload(gl_handle, location, self);
+    }
+}
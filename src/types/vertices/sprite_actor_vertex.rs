@@ -1,12 +1,15 @@
 use crate::*;
 use fennec_algebra::*;
 
-const VERTEX_ATTRIBUTE_BINDINGS: [VertexAttributeBinding; 4] = [
+const VERTEX_ATTRIBUTE_BINDINGS: [VertexAttributeBinding; 6] = [
+    VertexAttributeBinding::Float4,
+    VertexAttributeBinding::Float4,
     VertexAttributeBinding::Float4,
     VertexAttributeBinding::Float4,
     VertexAttributeBinding::Float4,
     VertexAttributeBinding::Float4,
 ];
+const MAX_MOTION_STEPS_PER_FRAME: usize = 16;
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -18,8 +21,15 @@ pub struct SpriteActorVertex {
     rotation: f32,
     last_updated: f32,
     animation_time: f32,
-    _0: f32,
+    hitbox_radius: f32,
     rectangle: Vec4f,
+    motion_program: u32,
+    motion_step: u32,
+    motion_step_time: f32,
+    motion_parent: u32,
+    generation: u32,
+    motion_parent_generation: u32,
+    _padding: [u32; 2],
 }
 
 impl SpriteActorVertex {
@@ -32,11 +42,22 @@ impl SpriteActorVertex {
             rotation: 0.0,
             last_updated: current_time as f32,
             animation_time: 0.0,
-            _0: 0.0,
+            hitbox_radius: 0.0,
             rectangle,
+            motion_program: 0,
+            motion_step: 0,
+            motion_step_time: 0.0,
+            motion_parent: 0,
+            generation: 0,
+            motion_parent_generation: 0,
+            _padding: [0; 2],
         }
     }
 
+    pub fn zeroed() -> Self {
+        Self::new(Vector::zero(), 0.0).with_scale(Vector::zero())
+    }
+
     pub fn with_position(mut self, position: Vec2f) -> Self {
         self.position = position;
         self
@@ -67,6 +88,30 @@ impl SpriteActorVertex {
         self
     }
 
+    pub fn with_hitbox_radius(mut self, hitbox_radius: f32) -> Self {
+        self.hitbox_radius = hitbox_radius;
+        self
+    }
+
+    pub fn with_motion_program(mut self, motion_program: MotionProgramReference) -> Self {
+        self.motion_program = motion_program.idx() + 1;
+        self.motion_step = 0;
+        self.motion_step_time = 0.0;
+        self
+    }
+
+    pub fn with_motion_parent(mut self, parent: &SpriteReference) -> Self {
+        // The generation is checked before orbiting, so a sprite reusing the parent's slot is never followed
+        self.motion_parent = parent.idx() as u32 + 1;
+        self.motion_parent_generation = parent.generation().wrapping_add(1);
+        self
+    }
+
+    pub fn set_generation(&mut self, generation: u32) {
+        // Stored off by one so that freed (zeroed) slots never match a parent reference
+        self.generation = generation.wrapping_add(1);
+    }
+
     pub fn position(&self, current_time: f64) -> Vec2f {
         let time_passed = (current_time - self.last_updated as f64) as f32;
         self.position + self.velocity() * time_passed
@@ -93,6 +138,47 @@ impl SpriteActorVertex {
         self.rectangle
     }
 
+    pub fn hitbox_radius(&self) -> f32 {
+        self.hitbox_radius
+    }
+
+    pub fn motion_program(&self) -> Option<MotionProgramReference> {
+        if self.motion_program == 0 {
+            None
+        } else {
+            Some(MotionProgramReference::from_idx(self.motion_program - 1))
+        }
+    }
+
+    pub fn motion_parent(&self) -> Option<usize> {
+        if self.motion_parent == 0 {
+            None
+        } else {
+            Some(self.motion_parent as usize - 1)
+        }
+    }
+
+    pub fn generation(&self) -> Option<u32> {
+        // Freed slots are zeroed, so they have no generation
+        self.generation.checked_sub(1)
+    }
+
+    pub fn is_motion_parent(&self, parent: &SpriteActorVertex) -> bool {
+        parent.generation != 0 && parent.generation == self.motion_parent_generation
+    }
+
+    pub fn same_motion(&self, other: &SpriteActorVertex) -> bool {
+        self.motion_program == other.motion_program
+            && self.motion_step == other.motion_step
+            && self.motion_step_time == other.motion_step_time
+            && self.motion_parent == other.motion_parent
+            && self.motion_parent_generation == other.motion_parent_generation
+    }
+
+    pub fn last_updated(&self) -> f32 {
+        self.last_updated
+    }
+
     pub fn set_position(&mut self, position: Vec2f, current_time: f64) {
         self.apply_time_changes(current_time);
         self.position = position;
@@ -123,6 +209,191 @@ impl SpriteActorVertex {
         self.rectangle = rectangle;
     }
 
+    pub fn integrate(
+        &mut self,
+        delta_time: f32,
+        environment: &MotionEnvironment,
+        parent_position: Option<Vec2f>,
+    ) {
+        // Same as the integration step in the sprite animator's compute shader
+        // Run the motion program's steps for as much of the frame as they cover
+        let mut remaining = delta_time;
+        let mut iterations = 0;
+        while self.motion_program != 0 && remaining > 0.0 && iterations < MAX_MOTION_STEPS_PER_FRAME
+        {
+            let steps = environment
+                .table()
+                .program_steps(MotionProgramReference::from_idx(self.motion_program - 1));
+            if self.motion_step as usize >= steps.len() {
+                self.motion_program = 0;
+                break;
+            }
+            let step = steps[self.motion_step as usize];
+
+            // Instant changes happen as the step is entered
+            if self.motion_step_time == 0.0 {
+                self.enter_motion_step(&step, environment);
+            }
+
+            // Run the step until it ends or the frame runs out
+            let slice = match step.duration() {
+                Some(duration) => remaining.min(duration - self.motion_step_time),
+                None => remaining,
+            };
+            self.run_motion_step(&step, slice, environment, parent_position);
+            self.motion_step_time += slice;
+            remaining -= slice;
+            if step
+                .duration()
+                .map_or(false, |duration| self.motion_step_time >= duration)
+            {
+                self.motion_step += 1;
+                self.motion_step_time = 0.0;
+            }
+            iterations += 1;
+        }
+
+        // Move linearly for any time left over
+        self.position = self.position + self.velocity * remaining;
+    }
+
+    fn enter_motion_step(&mut self, step: &MotionStep, environment: &MotionEnvironment) {
+        match step.kind() {
+            MotionKind::Stop => self.velocity = Vector::zero(),
+            MotionKind::Aim => {
+                let to_target = environment.aim_target() - self.position;
+                let distance = (to_target[0] * to_target[0] + to_target[1] * to_target[1]).sqrt();
+                let speed = if step.param0() >= 0.0 {
+                    step.param0()
+                } else {
+                    (self.velocity[0] * self.velocity[0] + self.velocity[1] * self.velocity[1])
+                        .sqrt()
+                };
+                if distance > 0.0 {
+                    self.velocity = to_target * (speed / distance);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn run_motion_step(
+        &mut self,
+        step: &MotionStep,
+        delta_time: f32,
+        environment: &MotionEnvironment,
+        parent_position: Option<Vec2f>,
+    ) {
+        // Change the velocity, then move
+        match step.kind() {
+            MotionKind::Accelerate => {
+                let speed = (self.velocity[0] * self.velocity[0]
+                    + self.velocity[1] * self.velocity[1])
+                    .sqrt();
+                let direction = if speed > 0.0 {
+                    self.velocity * (1.0 / speed)
+                } else {
+                    Vec2f::from_angle(self.rotation)
+                };
+                let new_speed = if step.param0() >= 0.0 {
+                    (speed + step.param0() * delta_time).min(step.param1())
+                } else {
+                    (speed + step.param0() * delta_time).max(step.param1())
+                };
+                self.velocity = direction * new_speed;
+            }
+            MotionKind::Turn => self.velocity = self.velocity.rotated(step.param0() * delta_time),
+            MotionKind::Home => {
+                let difference =
+                    (environment.aim_target() - self.position).angle() - self.velocity.angle();
+                let difference = difference.sin().atan2(difference.cos());
+                let max_turn = step.param0() * delta_time;
+                self.velocity = self
+                    .velocity
+                    .rotated(difference.max(-max_turn).min(max_turn));
+            }
+            _ => (),
+        }
+        match (step.kind(), parent_position) {
+            (MotionKind::Orbit, Some(parent_position)) => {
+                self.position = parent_position
+                    + (self.position - parent_position).rotated(step.param0() * delta_time);
+            }
+            _ => self.position = self.position + self.velocity * delta_time,
+        }
+
+        // Bounce off the edges of the bounds
+        if step.kind() == MotionKind::Bounce {
+            let bounds = environment.bounds_half_size();
+            if (self.position[0] < -bounds[0] && self.velocity[0] < 0.0)
+                || (self.position[0] > bounds[0] && self.velocity[0] > 0.0)
+            {
+                self.velocity = vector!(-self.velocity[0], self.velocity[1]);
+            }
+            if (self.position[1] < -bounds[1] && self.velocity[1] < 0.0)
+                || (self.position[1] > bounds[1] && self.velocity[1] > 0.0)
+            {
+                self.velocity = vector!(self.velocity[0], -self.velocity[1]);
+            }
+        }
+
+        // Face the direction of travel
+        if self.velocity[0] != 0.0 || self.velocity[1] != 0.0 {
+            self.rotation = self.velocity.angle();
+        }
+    }
+
+    pub fn build_transform(&self) -> Mat4f {
+        // Same as mat_translate * mat_scale * mat_rotateZ in the sprite animator's compute shader
+        let (sin, cos) = self.rotation.sin_cos();
+        let columns: [[f32; 4]; 4] = [
+            [self.scale[0] * cos, self.scale[1] * sin, 0.0, 0.0],
+            [self.scale[0] * -sin, self.scale[1] * cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [self.position[0], self.position[1], 0.0, 1.0],
+        ];
+
+        // Mat4f is laid out column-major, exactly like a GLSL mat4
+        Mat4f::new(columns)
+    }
+
+    pub fn build_instance(&self) -> SpriteInstanceVertex {
+        SpriteInstanceVertex::from_transform(self.build_transform(), self.rectangle)
+    }
+
+    pub fn set_hitbox_radius(&mut self, hitbox_radius: f32, current_time: f64) {
+        self.apply_time_changes(current_time);
+        self.hitbox_radius = hitbox_radius;
+    }
+
+    pub fn set_animated_state(&mut self, animated: &SpriteActorVertex, capture_time: f64) {
+        // Take the motion integrated by the sprite animator as of the capture time
+        self.position = animated.position;
+        self.velocity = animated.velocity;
+        self.rotation = animated.rotation;
+        self.motion_program = animated.motion_program;
+        self.motion_step = animated.motion_step;
+        self.motion_step_time = animated.motion_step_time;
+        self.last_updated = capture_time as f32;
+    }
+
+    pub fn set_keeping_motion_progress(&mut self, actor: &SpriteActorVertex) {
+        // Everything but the motion program's progress, which only the sprite animator moves on
+        // Fields are written one at a time so this can be used on write-only mapped buffers
+        self.position = actor.position;
+        self.velocity = actor.velocity;
+        self.scale = actor.scale;
+        self.scalar_velocity = actor.scalar_velocity;
+        self.rotation = actor.rotation;
+        self.last_updated = actor.last_updated;
+        self.animation_time = actor.animation_time;
+        self.hitbox_radius = actor.hitbox_radius;
+        self.rectangle = actor.rectangle;
+        self.motion_parent = actor.motion_parent;
+        self.generation = actor.generation;
+        self.motion_parent_generation = actor.motion_parent_generation;
+    }
+
     pub fn apply_time_changes(&mut self, current_time: f64) {
         self.position = self.position(current_time);
         self.scale = self.scale(current_time);
@@ -135,3 +406,112 @@ impl Vertex for SpriteActorVertex {
         &VERTEX_ATTRIBUTE_BINDINGS
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn assert_near(actual: Vec2f, expected: [f32; 2]) {
+        if (actual[0] - expected[0]).abs() > EPSILON || (actual[1] - expected[1]).abs() > EPSILON {
+            panic!("Expected {:?}, got {:?}", expected, actual);
+        }
+    }
+
+    fn actor(position: Vec2f, velocity: Vec2f) -> SpriteActorVertex {
+        SpriteActorVertex::new(Vector::zero(), 0.0)
+            .with_position(position)
+            .with_velocity(velocity)
+    }
+
+    fn integrate(
+        actors: &mut [SpriteActorVertex],
+        table: &MotionProgramTable,
+        aim_target: Vec2f,
+        delta_time: f32,
+    ) {
+        let environment = MotionEnvironment::new(table, aim_target, vector!(100.0, 100.0));
+        integrate_actors(actors, delta_time, &environment);
+    }
+
+    #[test]
+    fn actors_without_a_program_move_linearly() {
+        let table = MotionProgramTable::without_buffers();
+        let mut actors = [actor(vector!(1.0, 1.0), vector!(2.0, -1.0))];
+        integrate(&mut actors, &table, Vector::zero(), 0.5);
+        assert_near(actors[0].position, [2.0, 0.5]);
+    }
+
+    #[test]
+    fn steps_ending_mid_frame_hand_the_rest_of_the_frame_on() {
+        // Waits half the frame at speed 1, then accelerates at 2 for the other half
+        let mut table = MotionProgramTable::without_buffers();
+        let program = table.register(
+            &MotionProgram::new()
+                .then(MotionStep::wait(0.5))
+                .then(MotionStep::accelerate(None, 2.0, 10.0)),
+        );
+        let mut actors = [actor(Vector::zero(), vector!(1.0, 0.0)).with_motion_program(program)];
+        integrate(&mut actors, &table, Vector::zero(), 1.0);
+        assert_near(actors[0].position, [1.5, 0.0]);
+        assert_near(actors[0].velocity, [2.0, 0.0]);
+        assert_eq!(actors[0].motion_step, 1);
+        assert!((actors[0].motion_step_time - 0.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn instant_steps_apply_on_entry_and_finished_programs_end() {
+        let mut table = MotionProgramTable::without_buffers();
+        let aim = table.register(&MotionProgram::new().then(MotionStep::aim(Some(2.0))));
+        let stop = table.register(&MotionProgram::new().then(MotionStep::stop()));
+        let mut actors = [
+            actor(Vector::zero(), vector!(1.0, 0.0)).with_motion_program(aim),
+            actor(Vector::zero(), vector!(3.0, 0.0)).with_motion_program(stop),
+        ];
+        integrate(&mut actors, &table, vector!(3.0, 4.0), 1.0);
+        assert_near(actors[0].velocity, [1.2, 1.6]);
+        assert_near(actors[0].position, [1.2, 1.6]);
+        assert_eq!(actors[0].motion_program(), None);
+        assert_near(actors[1].velocity, [0.0, 0.0]);
+        assert_near(actors[1].position, [0.0, 0.0]);
+        assert_eq!(actors[1].motion_program(), None);
+    }
+
+    #[test]
+    fn steps_per_frame_are_capped() {
+        let mut table = MotionProgramTable::without_buffers();
+        let program = (0..MAX_MOTION_STEPS_PER_FRAME + 4)
+            .fold(MotionProgram::new(), |program, _| {
+                program.then(MotionStep::wait(0.0))
+            })
+            .then(MotionStep::stop());
+        let program = table.register(&program);
+        let mut actors = [actor(Vector::zero(), vector!(1.0, 0.0)).with_motion_program(program)];
+        integrate(&mut actors, &table, Vector::zero(), 0.25);
+        assert_eq!(actors[0].motion_step as usize, MAX_MOTION_STEPS_PER_FRAME);
+        assert_near(actors[0].position, [0.25, 0.0]);
+    }
+
+    #[test]
+    fn orbits_follow_the_parents_previous_position_and_generation() {
+        let mut table = MotionProgramTable::without_buffers();
+        let orbit = table.register(&MotionProgram::new().then(MotionStep::orbit(None, FRAC_PI_2)));
+        let mut parent = actor(Vector::zero(), vector!(10.0, 0.0));
+        parent.set_generation(3);
+        let mut child = actor(vector!(1.0, 0.0), Vector::zero()).with_motion_program(orbit);
+        child.motion_parent = 1;
+        child.motion_parent_generation = 4;
+        let mut orphan = actor(vector!(1.0, 0.0), vector!(1.0, 0.0)).with_motion_program(orbit);
+        orphan.motion_parent = 1;
+        orphan.motion_parent_generation = 3;
+
+        // The child circles where the parent was, the orphan's parent slot has been reused
+        let mut actors = [parent, child, orphan];
+        integrate(&mut actors, &table, Vector::zero(), 1.0);
+        assert_near(actors[0].position, [10.0, 0.0]);
+        assert_near(actors[1].position, [0.0, 1.0]);
+        assert_near(actors[2].position, [2.0, 0.0]);
+    }
+}
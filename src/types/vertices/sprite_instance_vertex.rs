@@ -21,6 +21,13 @@ impl SpriteInstanceVertex {
         }
     }
 
+    pub fn from_transform(transform: Mat4f, rectangle: Vec4f) -> Self {
+        Self {
+            transform,
+            rectangle,
+        }
+    }
+
     pub fn with_position(mut self, position: Vec3f) -> Self {
         self.transform.set_position(position);
         self
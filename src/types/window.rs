@@ -13,6 +13,14 @@ pub struct Window {
 
 impl Window {
     pub fn new(glfw: &mut glfw::Glfw, size: Vec2u, title: impl AsRef<str>) -> Self {
+        Self::try_new(glfw, size, title).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_new(
+        glfw: &mut glfw::Glfw,
+        size: Vec2u,
+        title: impl AsRef<str>,
+    ) -> BloomResult<Self> {
         // Set hints for window
         glfw.window_hint(glfw::WindowHint::ContextVersion(4, 5));
         glfw.window_hint(glfw::WindowHint::OpenGlDebugContext(true));
@@ -24,18 +32,18 @@ impl Window {
         // Create GLFW window and event receiver for the window
         let (mut glfw_window, event_receiver) = glfw
             .create_window(size[0], size[1], title.as_ref(), glfw::WindowMode::Windowed)
-            .expect("Could not create window.");
+            .ok_or(BloomError::WindowCreation)?;
 
         // Set initial settings
         glfw_window.set_key_polling(true);
         glfw_window.set_close_polling(true);
 
-        Self {
+        Ok(Self {
             glfw_window: Some(glfw_window),
             event_receiver,
             closed: false,
             size,
-        }
+        })
     }
 
     pub fn process_events(&mut self) -> Vec<glfw::WindowEvent> {
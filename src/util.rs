@@ -174,6 +174,8 @@ impl IntTools for u64 {
 pub trait VecfTools {
     type AngleType;
     fn from_angle(radians: Self::AngleType) -> Self;
+    fn angle(&self) -> Self::AngleType;
+    fn rotated(&self, radians: Self::AngleType) -> Self;
 }
 
 impl VecfTools for Vec2f {
@@ -181,6 +183,15 @@ impl VecfTools for Vec2f {
     fn from_angle(radians: Self::AngleType) -> Self {
         Self::new([radians.cos(), radians.sin()])
     }
+
+    fn angle(&self) -> Self::AngleType {
+        self[1].atan2(self[0])
+    }
+
+    fn rotated(&self, radians: Self::AngleType) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Self::new([self[0] * cos - self[1] * sin, self[0] * sin + self[1] * cos])
+    }
 }
 
 pub trait AngleTools {
@@ -253,3 +264,23 @@ macro_rules! path {
         }
     };
 }
+
+pub fn user_data_dir() -> std::path::PathBuf {
+    // Follow each platform's convention for per-user application data
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(std::path::PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| path!(home, "Library", "Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(std::path::PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| path!(home, ".local", "share")))
+    };
+    base.unwrap_or_else(|| path!(".")).join("Bloom")
+}
+
+pub fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}